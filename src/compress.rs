@@ -0,0 +1,38 @@
+use crate::SessionError;
+use base64::{engine::general_purpose, Engine as _};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::io::{Read, Write};
+
+/// Marks stored Session data as compressed. Neither Json nor encrypted data can start with it
+/// so Session data stored before compression was enabled still loads.
+pub(crate) const COMPRESSED_PREFIX: &str = "gz:";
+
+///Used to compress the database Values when they are larger than the threshold.
+pub(crate) fn compress(value: String, threshold: Option<usize>) -> Result<String, SessionError> {
+    match threshold {
+        Some(threshold) if value.len() > threshold => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(value.as_bytes())?;
+
+            Ok(format!(
+                "{}{}",
+                COMPRESSED_PREFIX,
+                general_purpose::STANDARD.encode(encoder.finish()?)
+            ))
+        }
+        _ => Ok(value),
+    }
+}
+
+///Used to decompress the database Values. Values without the prefix are returned as is.
+pub(crate) fn decompress(value: String) -> Result<String, SessionError> {
+    if let Some(compressed) = value.strip_prefix(COMPRESSED_PREFIX) {
+        let data = general_purpose::STANDARD.decode(compressed)?;
+        let mut decompressed = String::new();
+        GzDecoder::new(data.as_slice()).read_to_string(&mut decompressed)?;
+
+        Ok(decompressed)
+    } else {
+        Ok(value)
+    }
+}
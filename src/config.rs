@@ -1,6 +1,15 @@
-use chrono::Duration;
+use crate::SessionData;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
 pub use cookie::{Key, SameSite};
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc};
+use uuid::Uuid;
+
+/// Hook called with a Session's data, used by `with_on_store` and `with_on_load`.
+pub(crate) type SessionDataHook = Arc<dyn Fn(&mut SessionData) + Send + Sync>;
+
+/// Hook called with a Session ID, used by `with_on_evict`.
+pub(crate) type SessionIdHook = Arc<dyn Fn(&str) + Send + Sync>;
 
 /// Mode at which the Session will function As.
 ///
@@ -43,6 +52,198 @@ impl SessionMode {
     }
 }
 
+/// How the Session's expiration is extended on each request.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{Expiration, SessionConfig};
+/// use chrono::Duration;
+///
+/// let config = SessionConfig::default().with_expiration(Expiration::Absolute(Duration::days(1)));
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiration {
+    /// Each request extends the Session's expiration by its lifespan.
+    Sliding,
+    /// Each request extends the Session's expiration by its lifespan but never
+    /// past the given Duration from when the Session was created, regardless of activity.
+    /// Once this is reached the Session's data is Cleared and it is given a new Session ID.
+    Absolute(Duration),
+}
+
+/// What to do when a Session fails to load from the database, such as when the database is unreachable.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{LoadErrorPolicy, SessionConfig};
+///
+/// let config = SessionConfig::default().with_load_error_policy(LoadErrorPolicy::Fail);
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadErrorPolicy {
+    /// The request gets a new empty Session that is saved over the stored Session.
+    /// This logs the user out but keeps the site working. When the load timed out
+    /// the new Session is only kept in memory like `LoadErrorPolicy::MemoryOnly`.
+    NewSession,
+    /// The request fails with a 503 Service Unavailable response.
+    Fail,
+    /// The request gets a new empty Session kept only in memory. It is not saved to the
+    /// database, so the stored Session is loaded again on a later request once the database is reachable.
+    MemoryOnly,
+}
+
+/// Which Sessions are unloaded from memory first once more than `max_memory_sessions` are loaded.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{EvictionPolicy, SessionConfig};
+///
+/// let config = SessionConfig::default()
+///     .with_max_memory_sessions(Some(10_000))
+///     .with_eviction_policy(EvictionPolicy::LeastRecentlyUsed);
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicy {
+    /// Unloads the Sessions that were used the longest time ago.
+    LeastRecentlyUsed,
+    /// Unloads the Sessions whose memory lifespan ends the soonest,
+    /// as they would be removed by the next memory sweep anyways. This is the default.
+    #[default]
+    SoonestAutoremove,
+}
+
+/// The format Session data is serialized into before it is stored in the database.
+/// Stored data is marked with its format so Sessions stored with one serializer
+/// still load after switching to the other.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{SessionConfig, SessionSerializer};
+///
+/// let config = SessionConfig::default().with_serializer(SessionSerializer::Json);
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum SessionSerializer {
+    /// Stores the Session data as Json. This is the default.
+    #[default]
+    Json,
+    /// Stores the Session data as base64 encoded MessagePack. Requires the messagepack feature.
+    #[cfg(feature = "messagepack")]
+    MessagePack,
+}
+
+/// How the Session ID is written within the session cookie or header.
+/// Sessions are always kept in memory and the database by their hyphenated Session ID.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{IdEncoding, SessionConfig};
+///
+/// let config = SessionConfig::default().with_id_encoding(IdEncoding::Base64Url);
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdEncoding {
+    /// The 36 character hyphenated Uuid. This is the default.
+    #[default]
+    Hyphenated,
+    /// The 32 character hex Uuid without hyphens.
+    Hex,
+    /// The 22 character url safe base64 of the Uuid's 16 bytes.
+    Base64Url,
+}
+
+impl IdEncoding {
+    /// Writes the Session ID in this encoding.
+    pub(crate) fn encode(&self, id: Uuid) -> String {
+        match self {
+            IdEncoding::Hyphenated => id.hyphenated().to_string(),
+            IdEncoding::Hex => id.simple().to_string(),
+            IdEncoding::Base64Url => general_purpose::URL_SAFE_NO_PAD.encode(id.as_bytes()),
+        }
+    }
+
+    /// Reads a Session ID written in this encoding. Hyphenated and hex Session ID's are
+    /// always read so cookies sent before the encoding was changed keep their Session.
+    /// Returns None if the value is not a valid Session ID.
+    pub(crate) fn decode(&self, value: &str) -> Option<Uuid> {
+        if *self == IdEncoding::Base64Url && value.len() == 22 {
+            return general_purpose::URL_SAFE_NO_PAD
+                .decode(value)
+                .ok()
+                .and_then(|bytes| Uuid::from_slice(&bytes).ok());
+        }
+
+        Uuid::parse_str(value).ok()
+    }
+}
+
+/// Session lifecycle events given to the callback Set with `SessionConfig::with_event_callback`.
+/// Useful for collecting metrics.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{SessionConfig, SessionEvent};
+///
+/// let config = SessionConfig::default().with_event_callback(|event| {
+///     if event == SessionEvent::Created {
+///         println!("A session was created");
+///     }
+/// });
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    /// A new Session was created.
+    Created,
+    /// A Session was Destroyed.
+    Destroyed,
+    /// A Session's data was Cleared as it expired.
+    Expired,
+    /// The memory sweep removed this many Sessions from memory.
+    MemorySweep { removed: usize },
+    /// The database sweep removed this many expired Sessions from the database.
+    DatabaseSweep { removed: usize },
+}
+
+/// Prefix prepended to the Session's cookie names to have the browser enforce extra restrictions.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::{CookiePrefix, SessionConfig};
+///
+/// let config = SessionConfig::default().with_cookie_prefix(CookiePrefix::Host);
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CookiePrefix {
+    /// Cookie names are used as is.
+    None,
+    /// Prepends `__Host-` to the cookie names.
+    /// The cookie will always be secure, use the path `/` and will not have a domain set.
+    Host,
+    /// Prepends `__Secure-` to the cookie names.
+    /// The cookie will always be secure.
+    Secure,
+}
+
+impl CookiePrefix {
+    /// Returns the prefix to prepend to the cookie names.
+    #[inline]
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            CookiePrefix::None => "",
+            CookiePrefix::Host => "__Host-",
+            CookiePrefix::Secure => "__Secure-",
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct CookieAndHeaderConfig {
     /// The Cookie or Header name that contains a boolean for session saving.
@@ -61,13 +262,33 @@ pub struct CookieAndHeaderConfig {
     pub(crate) cookie_path: Cow<'static, str>,
     /// Resticts how Cookies are sent cross-site. Default is `SameSite::Lax`.
     pub(crate) cookie_same_site: SameSite,
-    /// Session cookie secure flag.
+    /// Session cookie secure flag. Enabled by default so the cookie is only sent over https.
     pub(crate) cookie_secure: bool,
+    /// Session cookie Partitioned flag so browsers keep a separate cookie per top level site (CHIPS).
+    /// Disabled by default.
+    pub(crate) cookie_partitioned: bool,
+    /// Sends an expired cookie when the session is destroyed so the browser drops it right away.
+    /// Enabled by default.
+    pub(crate) remove_cookie_on_destroy: bool,
     /// Encyption Key used to sign cookies and header for integrity, and authenticity.
     pub(crate) key: Option<Key>,
-    /// This is used to append __Host- to the front of all Cookie names to prevent sub domain usage.
-    /// This will not append to Headers only Cookies. It is enabled by default.
-    pub(crate) prefix_with_host: bool,
+    /// If Key is Some and this is true then cookies are also encrypted so their values can not be read.
+    /// It is disabled by default.
+    pub(crate) encrypt_cookies: bool,
+    /// If true then in rest_mode the Session ID is also read from an `Authorization: Bearer` header
+    /// when the session header is not sent. It is disabled by default.
+    pub(crate) bearer_token: bool,
+    /// The query parameter the Session ID is read from when the session cookie is not sent.
+    /// It is disabled by default as ids within URLs end up in logs and browser history.
+    pub(crate) query_param: Option<Cow<'static, str>>,
+    /// If true the Session's CSRF token is sent in the `x-csrf-token` response header.
+    /// It is disabled by default.
+    pub(crate) csrf_header: bool,
+    /// How the Session ID is written within the cookie or header. Default is IdEncoding::Hyphenated.
+    pub(crate) id_encoding: IdEncoding,
+    /// This is used to prepend __Host- or __Secure- to the front of all Cookie names.
+    /// This will not prepend to Headers only Cookies. It is disabled by default.
+    pub(crate) cookie_prefix: CookiePrefix,
     /// If Key is Some and this is true then the ip and user agent will be used to further sign cookies
     /// and header values. This will help prevent others from spoofing your cookies and headers.
     /// It is enabled by default.
@@ -85,13 +306,79 @@ impl std::fmt::Debug for CookieAndHeaderConfig {
             .field("cookie_path", &self.cookie_path)
             .field("cookie_same_site", &self.cookie_same_site)
             .field("cookie_secure", &self.cookie_secure)
-            .field("prefix_with_host", &self.prefix_with_host)
+            .field("cookie_partitioned", &self.cookie_partitioned)
+            .field("remove_cookie_on_destroy", &self.remove_cookie_on_destroy)
+            .field("cookie_prefix", &self.cookie_prefix)
+            .field("encrypt_cookies", &self.encrypt_cookies)
+            .field("bearer_token", &self.bearer_token)
+            .field("query_param", &self.query_param)
+            .field("csrf_header", &self.csrf_header)
+            .field("id_encoding", &self.id_encoding)
             .field("with_ip_and_user_agent", &self.with_ip_and_user_agent)
             .field("key", &"key hidden")
             .finish()
     }
 }
 
+impl CookieAndHeaderConfig {
+    /// Returns the first session or store name that can not be used as a cookie or header name.
+    /// Names must not be empty, must only contain letters, numbers and ``!#$%&'*+-.^_`|~``
+    /// and the store name must differ from the session name so the cookies do not clobber each other.
+    pub(crate) fn invalid_name(&self) -> Option<&str> {
+        let is_token = |name: &str| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+        };
+
+        if !is_token(&self.session_name) {
+            Some(&self.session_name)
+        } else if !is_token(&self.store_name) || self.store_name == self.session_name {
+            Some(&self.store_name)
+        } else {
+            None
+        }
+    }
+
+    /// Returns if the cookie needs the secure flag.
+    /// Browsers reject SameSite=None, partitioned and prefixed cookies unless they are also secure.
+    #[inline]
+    pub(crate) fn is_secure(&self) -> bool {
+        self.cookie_secure
+            || self.cookie_same_site == SameSite::None
+            || self.cookie_partitioned
+            || self.cookie_prefix != CookiePrefix::None
+    }
+
+    /// Checks if SameSite=None was combined with secure being disabled,
+    /// which browsers reject instead of storing the cookie.
+    #[inline]
+    pub(crate) fn insecure_same_site_none(&self) -> bool {
+        self.cookie_same_site == SameSite::None && !self.cookie_secure
+    }
+
+    /// Returns the cookie path. __Host- prefixed cookies must use `/`.
+    #[inline]
+    pub(crate) fn path(&self) -> Cow<'static, str> {
+        if self.cookie_prefix == CookiePrefix::Host {
+            "/".into()
+        } else {
+            self.cookie_path.clone()
+        }
+    }
+
+    /// Returns the cookie domain. __Host- prefixed cookies must not have one.
+    #[inline]
+    pub(crate) fn domain(&self) -> Option<Cow<'static, str>> {
+        if self.cookie_prefix == CookiePrefix::Host {
+            None
+        } else {
+            self.cookie_domain.clone()
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct DatabaseConfig {
     /// Encyption Key used to encypt Session data stored in the database for confidentiality.
@@ -102,6 +389,32 @@ pub struct DatabaseConfig {
     pub(crate) purge_database_update: Duration,
     /// Ignore's the update checks and will always save the session to the database if set to true.
     pub(crate) always_save: bool,
+    /// Skips loading the session from the database before the request is handled.
+    /// The handler must call `session.load().await` before using the session's data.
+    pub(crate) lazy_loading: bool,
+    /// Session data larger than this many bytes gets compressed before it is stored.
+    /// Only used with the compression feature. Default is None which disables compression.
+    pub(crate) compression_threshold: Option<usize>,
+    /// The format Session data is stored in. Default is SessionSerializer::Json.
+    pub(crate) serializer: SessionSerializer,
+    /// Sends the ids of changed or destroyed sessions to the other servers sharing the database
+    /// so they drop their cached copy. Default is false.
+    pub(crate) invalidation: bool,
+    /// The largest size in bytes of a session's serialized data that can be stored in the database.
+    /// Default is None which does not limit the size.
+    pub(crate) max_session_size: Option<usize>,
+    /// How long past their expiration Sessions are kept in the database before the sweep removes them.
+    pub(crate) cleanup_grace: Duration,
+    /// How many expired Sessions each cleanup delete removes. Default is None which removes them all at once.
+    pub(crate) cleanup_batch_size: Option<usize>,
+    /// How long to wait between cleanup batches so other queries can run. Default is zero.
+    pub(crate) cleanup_batch_pause: Duration,
+    /// The longest a database load, store or cleanup can take before it fails. Default is None which waits forever.
+    pub(crate) backend_timeout: Option<Duration>,
+    /// Database calls taking longer than this are logged as a warning. Default is None which logs none.
+    pub(crate) slow_backend_threshold: Option<Duration>,
+    /// What to do when a Session fails to load from the database. Default is LoadErrorPolicy::NewSession.
+    pub(crate) load_error_policy: LoadErrorPolicy,
 }
 
 impl std::fmt::Debug for DatabaseConfig {
@@ -110,6 +423,17 @@ impl std::fmt::Debug for DatabaseConfig {
             .field("table_name", &self.table_name)
             .field("purge_database_update", &self.purge_database_update)
             .field("always_save", &self.always_save)
+            .field("lazy_loading", &self.lazy_loading)
+            .field("compression_threshold", &self.compression_threshold)
+            .field("serializer", &self.serializer)
+            .field("invalidation", &self.invalidation)
+            .field("max_session_size", &self.max_session_size)
+            .field("cleanup_grace", &self.cleanup_grace)
+            .field("cleanup_batch_size", &self.cleanup_batch_size)
+            .field("cleanup_batch_pause", &self.cleanup_batch_pause)
+            .field("backend_timeout", &self.backend_timeout)
+            .field("slow_backend_threshold", &self.slow_backend_threshold)
+            .field("load_error_policy", &self.load_error_policy)
             .field("database_key", &"key hidden")
             .finish()
     }
@@ -134,6 +458,10 @@ pub struct MemoryConfig {
     /// the false positives it can give you can disable it by setting it to false. This will reduce memory usage.
     /// By default this is enabled unless the specific database cant function with it then disabled.
     pub(crate) use_bloom_filters: bool,
+    /// The most Sessions to keep in memory. The least recently used are unloaded past this.
+    pub(crate) max_memory_sessions: Option<usize>,
+    /// Which Sessions are unloaded first once max_memory_sessions is reached.
+    pub(crate) eviction_policy: EvictionPolicy,
 }
 
 impl std::fmt::Debug for MemoryConfig {
@@ -142,6 +470,8 @@ impl std::fmt::Debug for MemoryConfig {
             .field("memory_lifespan", &self.memory_lifespan)
             .field("filter_expected_elements", &self.filter_expected_elements)
             .field("use_bloom_filters", &self.use_bloom_filters)
+            .field("max_memory_sessions", &self.max_memory_sessions)
+            .field("eviction_policy", &self.eviction_policy)
             .field("purge_update", &self.purge_update)
             .field(
                 "filter_false_positive_probability",
@@ -164,6 +494,23 @@ pub struct IpUserAgentConfig {
     pub(crate) use_real_ip: bool,
     /// The Browser user agent.
     pub(crate) use_user_agent: bool,
+    /// Binds each Session to the ip's it was first used from.
+    pub(crate) bind_ip: bool,
+    /// Binds each Session to the user agent it was first used from.
+    pub(crate) bind_user_agent: bool,
+    /// The most new Sessions each ip can create within new_session_period.
+    /// Default is None which does not limit them.
+    pub(crate) new_session_limit: Option<usize>,
+    /// How long each new Session counts against the new_session_limit.
+    pub(crate) new_session_period: Duration,
+}
+
+impl IpUserAgentConfig {
+    /// Checks if Sessions are bound to either their ip's or user agent.
+    #[inline]
+    pub(crate) fn is_binding(&self) -> bool {
+        self.bind_ip || self.bind_user_agent
+    }
 }
 
 impl std::fmt::Debug for IpUserAgentConfig {
@@ -174,17 +521,36 @@ impl std::fmt::Debug for IpUserAgentConfig {
             .field("use_forward_ip", &self.use_forward_ip)
             .field("use_real_ip", &self.use_real_ip)
             .field("use_user_agent", &self.use_user_agent)
+            .field("bind_ip", &self.bind_ip)
+            .field("bind_user_agent", &self.bind_user_agent)
+            .field("new_session_limit", &self.new_session_limit)
+            .field("new_session_period", &self.new_session_period)
             .finish()
     }
 }
 
 /// Configuration for how the Session and Cookies are used.
 ///
+/// The Default is a Persistent session with a Secure permanent cookie, a 6 hour lifespan and
+/// a 60 minute memory lifespan. Each setting can be changed using the chainable `with_` methods.
+///
 /// # Examples
 /// ```rust
-/// use axum_session::SessionConfig;
+/// use axum_session::{SameSite, SessionConfig, SessionNullPool, SessionStore};
+/// use chrono::Duration;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let config = SessionConfig::default()
+///     .with_session_name("my_session")
+///     .with_lifetime(Duration::hours(6))
+///     .with_memory_lifetime(Duration::minutes(30))
+///     .with_secure(true)
+///     .with_http_only(true)
+///     .with_cookie_same_site(SameSite::Lax);
 ///
-/// let config = SessionConfig::default();
+/// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+/// # }
 /// ```
 ///
 #[derive(Clone)]
@@ -198,6 +564,12 @@ pub struct SessionConfig {
     /// This is set to the Cookie before sending and to the database before updating/inserting.
     /// Only Set when Long Term is true.
     pub(crate) max_lifespan: Duration,
+    /// Sliding or Absolute extending of the Session's expiration.
+    pub(crate) expiration: Expiration,
+    /// Extends the Session's expiration when it is saved at the end of a request.
+    pub(crate) extend_on_request: bool,
+    /// How often a Session is given a new Session ID. Default is None which never rotates it.
+    pub(crate) rotation_interval: Option<Duration>,
     /// This is to be used when your handling multiple Parallel Sessions to prevent the next one from unloaded data.
     pub(crate) clear_check_on_load: bool,
     /// where All Database Storage options exist.
@@ -208,6 +580,18 @@ pub struct SessionConfig {
     pub(crate) cookie_and_header: CookieAndHeaderConfig,
     /// tells how we should build the string for hashing to secure the cookie.
     pub(crate) ip_user_agent: IpUserAgentConfig,
+    /// Called for each Session lifecycle event.
+    pub(crate) event_callback: Option<Arc<dyn Fn(SessionEvent) + Send + Sync>>,
+    /// Generates new Session ID's. Uuid::new_v4 is used when None.
+    pub(crate) id_generator: Option<Arc<dyn Fn() -> Uuid + Send + Sync>>,
+    /// Called on a copy of the Session's data before it is saved to the database.
+    pub(crate) on_store: Option<SessionDataHook>,
+    /// Called on the Session's data after it is loaded from the database.
+    pub(crate) on_load: Option<SessionDataHook>,
+    /// Called with the Session ID of each Session unloaded from memory by a sweep or eviction.
+    pub(crate) on_evict: Option<SessionIdHook>,
+    /// Gives the current time used for every expiration. Default is None which uses Utc::now.
+    pub(crate) clock: Option<Arc<dyn Fn() -> DateTime<Utc> + Send + Sync>>,
 }
 
 impl std::fmt::Debug for SessionConfig {
@@ -219,7 +603,16 @@ impl std::fmt::Debug for SessionConfig {
             .field("session_mode", &self.session_mode)
             .field("lifespan", &self.lifespan)
             .field("max_lifespan", &self.max_lifespan)
+            .field("expiration", &self.expiration)
+            .field("extend_on_request", &self.extend_on_request)
+            .field("rotation_interval", &self.rotation_interval)
             .field("clear_check_on_load", &self.clear_check_on_load)
+            .field("event_callback", &self.event_callback.is_some())
+            .field("id_generator", &self.id_generator.is_some())
+            .field("on_store", &self.on_store.is_some())
+            .field("on_load", &self.on_load.is_some())
+            .field("on_evict", &self.on_evict.is_some())
+            .field("clock", &self.clock.is_some())
             .finish()
     }
 }
@@ -232,6 +625,26 @@ impl SessionConfig {
         Default::default()
     }
 
+    /// Generates a new Session ID using the configured generator.
+    #[inline]
+    pub(crate) fn generate_id(&self) -> Uuid {
+        if let Some(generator) = &self.id_generator {
+            generator()
+        } else {
+            Uuid::new_v4()
+        }
+    }
+
+    /// Gets the current time from the clock or Utc::now when no clock is Set.
+    #[inline]
+    pub(crate) fn now(&self) -> DateTime<Utc> {
+        if let Some(clock) = &self.clock {
+            clock()
+        } else {
+            Utc::now()
+        }
+    }
+
     /// Set the session's store Cookie or Header name.
     ///
     /// # Examples
@@ -296,6 +709,8 @@ impl SessionConfig {
     }
 
     /// Set's the session's cookie's Same Site Setting for Cross-Site restrictions.
+    /// `SameSite::None` requires the secure flag, so creating the SessionStore fails with
+    /// `SessionError::InsecureSameSiteNone` if it is used with `with_secure(false)`.
     ///
     /// # Examples
     /// ```rust
@@ -394,6 +809,62 @@ impl SessionConfig {
         self
     }
 
+    /// Set's if the session's expiration is Sliding or capped at an Absolute time from creation.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{Expiration, SessionConfig};
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default().with_expiration(Expiration::Absolute(Duration::days(1)));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_expiration(mut self, expiration: Expiration) -> Self {
+        self.expiration = expiration;
+        self
+    }
+
+    /// Set's if requests extend the session's expiration when the session is saved.
+    /// When false the session keeps the expiration it was created with, a fixed window,
+    /// unless it is extended with `session.renew_expiry()` or `session.touch()`.
+    /// Sessions that expired still start over with a new expiration.
+    ///
+    /// Defaults to true.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_extend_on_request(false);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_extend_on_request(mut self, extend: bool) -> Self {
+        self.extend_on_request = extend;
+        self
+    }
+
+    /// Set's how often the session is given a new Session ID, limiting how long a stolen cookie
+    /// stays useful. Once the interval passed since the ID was created or last renewed the next
+    /// request keeps the session's data under a new ID, removes the old ID and sends the new cookie.
+    ///
+    /// Defaults to None which only changes the ID when `session.renew()` is called.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default().with_rotation_interval(Duration::try_minutes(15));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_rotation_interval(mut self, interval: Option<Duration>) -> Self {
+        self.rotation_interval = interval;
+        self
+    }
+
     /// Set's the session's lifetime (expiration time) within memory storage.
     /// This setting should be Less than lifespan and max_lifespan. This is to
     /// Unload the data from memory and allow it to stay stored in the database.
@@ -415,7 +886,45 @@ impl SessionConfig {
         self
     }
 
+    /// Set's the most Sessions kept in memory at once. When more are loaded the Sessions not in
+    /// use by a request are unloaded from memory in the order of the EvictionPolicy, those whose
+    /// memory lifespan ends the soonest first by default. They stay in the database and are
+    /// loaded again on their next request. Without a database unloaded Sessions are lost.
+    /// Sessions are unloaded until 90% of the max remain so this does not happen on every new Session.
+    ///
+    /// None keeps every Session in memory until its memory lifespan ends.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_max_memory_sessions(Some(10_000));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_max_memory_sessions(mut self, max: Option<usize>) -> Self {
+        self.memory.max_memory_sessions = max;
+        self
+    }
+
+    /// Set's which Sessions are unloaded from memory first once max_memory_sessions is reached.
+    /// Defaults to EvictionPolicy::SoonestAutoremove.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{EvictionPolicy, SessionConfig};
+    ///
+    /// let config = SessionConfig::default().with_eviction_policy(EvictionPolicy::LeastRecentlyUsed);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_eviction_policy(mut self, policy: EvictionPolicy) -> Self {
+        self.memory.eviction_policy = policy;
+        self
+    }
+
     /// This value represents the offset duration for how often session purge for memory is ran.
+    /// This is independent of the memory lifespan so sessions can be swept more often than they expire.
     ///
     /// # Examples
     /// ```rust
@@ -466,13 +975,34 @@ impl SessionConfig {
         self
     }
 
+    /// Set's the session to lazily load its data from the database.
+    ///
+    /// When enabled a session that is not in memory will not be loaded from the database
+    /// before the request is handled. Handlers that use the session's data must call
+    /// `session.load().await` first. Handlers that never use the session will not hit the database.
+    /// Changes made without loading are kept and the session is loaded after the handler to save them.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_lazy_loading(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_lazy_loading(mut self, lazy_loading: bool) -> Self {
+        self.database.lazy_loading = lazy_loading;
+        self
+    }
+
     /// Set's the session's secure flag for if it gets sent over https.
+    /// This is enabled by default. Disable it only for local http development.
     ///
     /// # Examples
     /// ```rust
     /// use axum_session::SessionConfig;
     ///
-    /// let config = SessionConfig::default().with_secure(true);
+    /// let config = SessionConfig::default().with_secure(false);
     /// ```
     ///
     #[must_use]
@@ -481,7 +1011,47 @@ impl SessionConfig {
         self
     }
 
+    /// Set's the session's Partitioned flag (CHIPS) so browsers keep a separate cookie for
+    /// each top level site the app is embedded in, such as within a cross site iframe.
+    /// Partitioned cookies are always sent with the secure flag.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{SameSite, SessionConfig};
+    ///
+    /// let config = SessionConfig::default()
+    ///     .with_cookie_same_site(SameSite::None)
+    ///     .with_partitioned(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_partitioned(mut self, is_set: bool) -> Self {
+        self.cookie_and_header.cookie_partitioned = is_set;
+        self
+    }
+
+    /// Set's if an expired cookie is sent when a handler destroys the session so the browser
+    /// drops the dead session id right away. Enabled by default. When disabled the browser keeps
+    /// sending the old id, which gets a new session on its next request.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_remove_cookie_on_destroy(false);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_remove_cookie_on_destroy(mut self, is_set: bool) -> Self {
+        self.cookie_and_header.remove_cookie_on_destroy = is_set;
+        self
+    }
+
     /// Set's the session's database table name.
+    /// For the SQL databases and SurrealDB it must only contain letters, numbers and _ and can be
+    /// prefixed by a schema like `auth.sessions`, otherwise `SessionStore::new` returns
+    /// `SessionError::InvalidTableName`. Other databases check the name themselves, such as Redis
+    /// allowing a key prefix like `myapp:sessions` or an empty name for no key prefix.
     ///
     /// # Examples
     /// ```rust
@@ -519,15 +1089,378 @@ impl SessionConfig {
         self
     }
 
-    /// Set's the session's database encyption key for per session key storage.
+    /// Set's the session's cookies to be encrypted using the Key so the Session ID is not visible.
+    /// Cookies are still signed before being encrypted. This does nothing unless a Key is Set.
     ///
-    /// Must be Set to Some() in order to use Security::PerSession or will panic if not.
+    /// A Cookie that fails to decrypt is treated as a new Session.
+    /// This only applies to Cookies and not Headers.
     ///
     /// # Examples
     /// ```rust
     /// use axum_session::{Key, SessionConfig};
     ///
-    /// let config = SessionConfig::default().with_key(Key::generate());
+    /// let config = SessionConfig::default()
+    ///     .with_key(Key::generate())
+    ///     .with_encrypted_cookies(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_encrypted_cookies(mut self, enable: bool) -> Self {
+        self.cookie_and_header.encrypt_cookies = enable;
+        self
+    }
+
+    /// Set's the session to also read the Session ID from an `Authorization: Bearer <id>` header
+    /// when the session header is not sent. The Session ID is still returned in the session header.
+    /// If a Key is Set the Bearer token must be the signed value returned in the session header.
+    /// The Bearer scheme is matched case insensitively.
+    ///
+    /// This only applies when the rest_mode feature is enabled. Whether the Session ID travels in
+    /// cookies or headers is already chosen by that feature at compile time, and the header's name
+    /// by `with_session_name`, so this only needs to choose if the Authorization header is read too.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_bearer_token(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_bearer_token(mut self, enable: bool) -> Self {
+        self.cookie_and_header.bearer_token = enable;
+        self
+    }
+
+    /// Set's the session to also read the Session ID from the given query parameter when the
+    /// session cookie is not sent, such as from an email confirmation link. The Session is given
+    /// a new Session ID, which is set as the session cookie on the response, so a link with a
+    /// Session ID chosen by someone else can not be used to fixate the Session. The link
+    /// therefore only works once.
+    /// If a Key is Set the parameter must be the session cookie's signed or encrypted value.
+    /// This only applies when the rest_mode feature is disabled.
+    ///
+    /// Only enable this for flows that need it as the Session ID is exposed within the URL,
+    /// which ends up in server logs, browser history and Referer headers.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_query_param("sid");
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_query_param(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.cookie_and_header.query_param = Some(name.into());
+        self
+    }
+
+    /// Set's the session to send its CSRF token in the `x-csrf-token` response header so single
+    /// page apps can read it without a separate endpoint. A token is created for Sessions without one.
+    /// It is disabled by default so the token is only sent where it is wanted.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_csrf_header(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_csrf_header(mut self, enable: bool) -> Self {
+        self.cookie_and_header.csrf_header = enable;
+        self
+    }
+
+    /// Set's how the Session ID is written within the session cookie or header.
+    /// IdEncoding::Base64Url shortens it from 36 to 22 characters. Cookies using the
+    /// hyphenated or hex Session ID are still read after the encoding is changed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{IdEncoding, SessionConfig};
+    ///
+    /// let config = SessionConfig::default().with_id_encoding(IdEncoding::Hex);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_id_encoding(mut self, encoding: IdEncoding) -> Self {
+        self.cookie_and_header.id_encoding = encoding;
+        self
+    }
+
+    /// Set's a callback that is called for each Session lifecycle event.
+    /// This is called within the Session Layer so it should return quickly.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{SessionConfig, SessionEvent};
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// static CREATED: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// let config = SessionConfig::default().with_event_callback(|event| {
+    ///     if event == SessionEvent::Created {
+    ///         CREATED.fetch_add(1, Ordering::Relaxed);
+    ///     }
+    /// });
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_event_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(SessionEvent) + Send + Sync + 'static,
+    {
+        self.event_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Set's a hook that is called on a copy of the Session's data before it is saved to the database.
+    /// Changes only affect what is saved, not the Session in memory. Useful to redact keys or
+    /// to bump a schema version. This is called within the Session Layer so it should return quickly.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_on_store(|data| {
+    ///     data.remove("password-reset-code");
+    /// });
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_on_store<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut SessionData) + Send + Sync + 'static,
+    {
+        self.on_store = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set's a hook that is called on the Session's data after it is loaded from the database.
+    /// Useful to migrate the data of older Sessions or drop deprecated keys.
+    /// This is called within the Session Layer so it should return quickly.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_on_load(|data| {
+    ///     if !data.contains_key("schema") {
+    ///         data.set("schema", 2);
+    ///     }
+    /// });
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_on_load<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut SessionData) + Send + Sync + 'static,
+    {
+        self.on_load = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set's a hook that is called with the Session ID of each Session the memory sweep or
+    /// max_memory_sessions unloads from memory. Sessions kept in the database can be loaded
+    /// again with `SessionStore::reload`. Useful to keep an external list of loaded Sessions in sync.
+    /// The hook is called after the Sessions were removed and should return quickly.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_on_evict(|id| {
+    ///     println!("Session {} was unloaded", id);
+    /// });
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_on_evict<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.on_evict = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set's the generator used to create new Session ID's. The Default is Uuid::new_v4.
+    /// Useful for time sortable ID's like UUIDv7 for better database index locality.
+    ///
+    /// Generated ID's are still checked against the existing Sessions, so the generator
+    /// must not keep returning ID's that are in use.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use uuid::Uuid;
+    ///
+    /// let config = SessionConfig::default().with_id_generator(Uuid::new_v4);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_id_generator<F>(mut self, generator: F) -> Self
+    where
+        F: Fn() -> Uuid + Send + Sync + 'static,
+    {
+        self.id_generator = Some(Arc::new(generator));
+        self
+    }
+
+    /// Set's the clock used to get the current time for the session's expirations, memory
+    /// unloading and the database and memory sweeps. Default uses Utc::now.
+    /// Mostly useful in tests to move time forward without waiting.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Utc;
+    ///
+    /// let config = SessionConfig::default().with_clock(Utc::now);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_clock<F>(mut self, clock: F) -> Self
+    where
+        F: Fn() -> DateTime<Utc> + Send + Sync + 'static,
+    {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Set's the largest size in bytes a session's serialized data can be when it is stored in the database.
+    /// Storing a larger session fails with `SessionError::SessionTooLarge` and the request
+    /// gets a 500 response instead of writing the oversized row. The size is checked before compression.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_max_session_size(Some(64 * 1024));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_max_session_size(mut self, max_size: Option<usize>) -> Self {
+        self.database.max_session_size = max_size;
+        self
+    }
+
+    /// Set's how long past their expiration Sessions are kept in the database before the
+    /// database sweep removes them. This avoids removing a Session while a request that is
+    /// extending it is still running. Custom DatabasePools must implement
+    /// `DatabasePool::delete_expired_before` for this to be used.
+    ///
+    /// Defaults to zero. Ignored by databases that remove expired Sessions themselves.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default().with_cleanup_grace(Duration::minutes(5));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_cleanup_grace(mut self, grace: Duration) -> Self {
+        self.database.cleanup_grace = grace;
+        self
+    }
+
+    /// Set's how many expired Sessions the database sweep removes per delete along with how long
+    /// to wait between each delete. Removing them in batches keeps a large cleanup from locking
+    /// the table and stalling other writes. The sweep stops once a batch removes fewer than the
+    /// batch size. Custom DatabasePools must implement `DatabasePool::delete_expired_batch`
+    /// for the batch size to be used.
+    ///
+    /// Defaults to None which removes every expired Session with a single delete.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default()
+    ///     .with_cleanup_batch_size(Some(1000), Duration::milliseconds(50));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_cleanup_batch_size(mut self, batch_size: Option<usize>, pause: Duration) -> Self {
+        self.database.cleanup_batch_size = batch_size.filter(|batch_size| *batch_size > 0);
+        self.database.cleanup_batch_pause = pause;
+        self
+    }
+
+    /// Set's the longest a database load, store or cleanup can take before it fails with
+    /// `SessionError::BackendTimeout` so a hung database can not hold requests forever.
+    /// A load that times out is handled by the LoadErrorPolicy, except the new Session it gives
+    /// the request is never saved over the stored Session as that may still be valid.
+    ///
+    /// Defaults to None which waits for the database however long it takes.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default().with_backend_timeout(Duration::try_seconds(2));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_backend_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.database.backend_timeout = timeout;
+        self
+    }
+
+    /// Set's how long a database load, store or cleanup can take before it is logged as a warning.
+    /// Useful to notice a struggling database before calls reach the backend timeout.
+    ///
+    /// Defaults to None which logs no warnings.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default().with_slow_backend_threshold(Duration::try_milliseconds(250));
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_slow_backend_threshold(mut self, threshold: Option<Duration>) -> Self {
+        self.database.slow_backend_threshold = threshold;
+        self
+    }
+
+    /// Set's what happens when a Session fails to load from the database, such as when it is unreachable.
+    /// The default `LoadErrorPolicy::NewSession` gives the request a new empty Session.
+    ///
+    /// When lazy loading, `LoadErrorPolicy::Fail` leaves the Session unloaded instead
+    /// as `session.load()` can not fail the request.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{LoadErrorPolicy, SessionConfig};
+    ///
+    /// let config = SessionConfig::default().with_load_error_policy(LoadErrorPolicy::MemoryOnly);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_load_error_policy(mut self, policy: LoadErrorPolicy) -> Self {
+        self.database.load_error_policy = policy;
+        self
+    }
+
+    /// Set's the session's database encyption key used to encrypt the session data stored in the database.
+    ///
+    /// Each session is encrypted with AES-256-GCM using a random nonce stored with it and the
+    /// session id as associated data, so stored rows can not be read or moved to another session.
+    /// Sessions stored before the key was set can not be loaded once it is set.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{Key, SessionConfig};
+    ///
+    /// let config = SessionConfig::default().with_database_key(Key::generate());
     /// ```
     ///
     #[must_use]
@@ -536,6 +1469,60 @@ impl SessionConfig {
         self
     }
 
+    /// Set's the size in bytes above which session data gets gzip compressed before it is stored
+    /// in the database. Compression happens before encryption when a database key is set.
+    /// Session data stored without compression can still be loaded.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_compression_threshold(Some(4096));
+    /// ```
+    ///
+    #[cfg(feature = "compression")]
+    #[cfg_attr(all(nightly, doc), doc(cfg(feature = "compression")))]
+    #[must_use]
+    pub fn with_compression_threshold(mut self, threshold: Option<usize>) -> Self {
+        self.database.compression_threshold = threshold;
+        self
+    }
+
+    /// Set's the format session data is serialized into before it is stored in the database.
+    /// Sessions already stored in the other format still load, so a store can be switched
+    /// without clearing it. They are rewritten in the new format the next time they are saved.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{SessionConfig, SessionSerializer};
+    ///
+    /// let config = SessionConfig::default().with_serializer(SessionSerializer::Json);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_serializer(mut self, serializer: SessionSerializer) -> Self {
+        self.database.serializer = serializer;
+        self
+    }
+
+    /// Set's the session to tell the other servers sharing the database when a session changes
+    /// or is destroyed so they remove their copy from memory and load it again on its next request.
+    /// Use this when running more than one server. Supported by the Postgres pool and any
+    /// DatabasePool that implements `publish_invalidation` and `subscribe_invalidations`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_invalidation(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_invalidation(mut self, enable: bool) -> Self {
+        self.database.invalidation = enable;
+        self
+    }
+
     /// Set's the session's filters expected elements.
     /// Please Set this by a daily value.
     /// Example: 1000 * 60(secs) * 60(mins) * 24(hours) to get 1 days worth of visitors.
@@ -625,6 +1612,7 @@ impl SessionConfig {
     }
 
     /// Set's the session's prefix_with_host to either true: __Host- gets prefixed to the cookie names false: __Host- does not get prepended.
+    /// This is the same as using `with_cookie_prefix(CookiePrefix::Host)` or `with_cookie_prefix(CookiePrefix::None)`.
     ///
     /// __Host- prefix: Cookies with names starting with __Host- must be set with the secure flag, must be from a secure page (HTTPS),
     /// must not have a domain specified (and therefore, are not sent to subdomains), and the path must be /.
@@ -638,7 +1626,29 @@ impl SessionConfig {
     ///
     #[must_use]
     pub fn with_prefix_with_host(mut self, enable: bool) -> Self {
-        self.cookie_and_header.prefix_with_host = enable;
+        self.cookie_and_header.cookie_prefix = if enable {
+            CookiePrefix::Host
+        } else {
+            CookiePrefix::None
+        };
+        self
+    }
+
+    /// Set's the session's cookie name prefix.
+    ///
+    /// CookiePrefix::Host: the cookie is always secure, the path is forced to / and the domain is not set.
+    /// CookiePrefix::Secure: the cookie is always secure.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::{CookiePrefix, SessionConfig};
+    ///
+    /// let config = SessionConfig::default().with_cookie_prefix(CookiePrefix::Secure);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_cookie_prefix(mut self, prefix: CookiePrefix) -> Self {
+        self.cookie_and_header.cookie_prefix = prefix;
         self
     }
 
@@ -740,6 +1750,73 @@ impl SessionConfig {
         self.ip_user_agent.use_user_agent = enable;
         self
     }
+
+    /// Set's the session's to be bound to the ip's it was first used from.
+    /// A request from a different ip is given a new Session, leaving the bound Session as is.
+    /// The ip's used are the ones enabled with with_hashed_ip, with_hashed_xforward,
+    /// with_hashed_forward and with_hashed_real_ip. This does not require a Key.
+    ///
+    /// This is disabled by default as NAT and mobile roaming can change a users ip.
+    /// Sessions are always loaded before the handler when enabled, so lazy loading is ignored.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_ip_binding(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_ip_binding(mut self, enable: bool) -> Self {
+        self.ip_user_agent.bind_ip = enable;
+        self
+    }
+
+    /// Set's the session's to be bound to the user agent it was first used from.
+    /// A request from a different user agent is given a new Session, leaving the bound Session as is.
+    /// Only a hash of the user agent is stored. This does not require a Key.
+    ///
+    /// This is disabled by default as some clients change their user agent when updated.
+    /// Sessions are always loaded before the handler when enabled, so lazy loading is ignored.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    ///
+    /// let config = SessionConfig::default().with_user_agent_binding(true);
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_user_agent_binding(mut self, enable: bool) -> Self {
+        self.ip_user_agent.bind_user_agent = enable;
+        self
+    }
+
+    /// Set's the most new Sessions a single ip can create within the period.
+    /// Requests without a Session over the limit get a 429 Too Many Requests response
+    /// instead of a new Session, stopping cookieless request floods from filling memory
+    /// and the database. Session ID's that do not exist and Sessions replaced for not matching
+    /// their ip or user agent binding count as new Sessions. Requests with an existing Session are not limited.
+    /// The ip's used are the ones enabled with with_hashed_ip, with_hashed_xforward,
+    /// with_hashed_forward and with_hashed_real_ip. From the forwarding headers the last address,
+    /// added by the proxy in front of the app, is used. Requests where none of them are found are
+    /// not limited. Default is None which does not limit them.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use axum_session::SessionConfig;
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default()
+    ///     .with_new_session_limit(Some(100), Duration::try_minutes(1).unwrap());
+    /// ```
+    ///
+    #[must_use]
+    pub fn with_new_session_limit(mut self, limit: Option<usize>, period: Duration) -> Self {
+        self.ip_user_agent.new_session_limit = limit;
+        self.ip_user_agent.new_session_period = period;
+        self
+    }
 }
 
 impl Default for SessionConfig {
@@ -752,9 +1829,19 @@ impl Default for SessionConfig {
             memory: MemoryConfig::default(),
             // Unload long term session after 60 days if it has not been accessed.
             max_lifespan: Duration::try_days(60).unwrap_or_default(),
+            // Sliding is the Default mode for compatibilty with older versions of the crate.
+            expiration: Expiration::Sliding,
+            extend_on_request: true,
+            rotation_interval: None,
             session_mode: SessionMode::Persistent,
             clear_check_on_load: true,
             ip_user_agent: IpUserAgentConfig::default(),
+            event_callback: None,
+            id_generator: None,
+            clock: None,
+            on_store: None,
+            on_load: None,
+            on_evict: None,
         }
     }
 }
@@ -773,6 +1860,9 @@ impl Default for MemoryConfig {
             filter_false_positive_probability: 0.01,
             // Always set to on.
             use_bloom_filters: true,
+            // Unbounded so only the memory lifespan unloads sessions.
+            max_memory_sessions: None,
+            eviction_policy: EvictionPolicy::SoonestAutoremove,
         }
     }
 }
@@ -785,6 +1875,17 @@ impl Default for DatabaseConfig {
             // Default to purge old sessions in the database every 5 hours per request.
             purge_database_update: Duration::try_hours(5).unwrap_or_default(),
             always_save: false,
+            lazy_loading: false,
+            compression_threshold: None,
+            serializer: SessionSerializer::Json,
+            invalidation: false,
+            max_session_size: None,
+            cleanup_grace: Duration::zero(),
+            cleanup_batch_size: None,
+            cleanup_batch_pause: Duration::zero(),
+            backend_timeout: None,
+            slow_backend_threshold: None,
+            load_error_policy: LoadErrorPolicy::NewSession,
             // Database key is set to None it will panic if you attempt to use SecurityMode::PerSession.
             database_key: None,
         }
@@ -798,13 +1899,20 @@ impl Default for CookieAndHeaderConfig {
             cookie_path: "/".into(),
             cookie_max_age: Some(Duration::try_days(100).unwrap_or_default()),
             cookie_http_only: true,
-            cookie_secure: false,
+            cookie_secure: true,
+            cookie_partitioned: false,
+            remove_cookie_on_destroy: true,
             cookie_domain: None,
             cookie_same_site: SameSite::Lax,
             store_name: "store".into(),
             // Key is set to None so Private cookies are not used by default. Please set this if you want to use private cookies.
             key: None,
-            prefix_with_host: false,
+            cookie_prefix: CookiePrefix::None,
+            encrypt_cookies: false,
+            bearer_token: false,
+            query_param: None,
+            csrf_header: false,
+            id_encoding: IdEncoding::Hyphenated,
             with_ip_and_user_agent: true,
         }
     }
@@ -818,6 +1926,10 @@ impl Default for IpUserAgentConfig {
             use_forward_ip: false,
             use_real_ip: false,
             use_user_agent: true,
+            bind_ip: false,
+            bind_user_agent: false,
+            new_session_limit: None,
+            new_session_period: Duration::try_minutes(1).unwrap_or_default(),
         }
     }
 }
@@ -40,7 +40,19 @@ mod any_db;
 pub use self::any_db::*;
 
 mod database;
+mod file;
 mod null;
 
+#[cfg(any(
+    feature = "postgres-rustls",
+    feature = "postgres-native",
+    feature = "sqlite-rustls",
+    feature = "sqlite-native",
+    feature = "mysql-rustls",
+    feature = "mysql-native",
+    feature = "surreal"
+))]
+pub(crate) use database::is_sql_table_name;
 pub use database::DatabasePool;
+pub use file::*;
 pub use null::*;
@@ -2,6 +2,7 @@ use crate::{databases::DatabasePool, Session, SessionError, SessionStore};
 use async_trait::async_trait;
 use std::fmt::Debug;
 use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedReceiver;
 
 ///Any Session Helper type for the DatabasePool.
 pub type SessionAnySession = Session<SessionAnyPool>;
@@ -57,6 +58,14 @@ impl DatabasePool for SessionAnyPool {
         self.pool.load(id, table_name).await
     }
 
+    async fn load_with_expiry(
+        &self,
+        id: &str,
+        table_name: &str,
+    ) -> Result<Option<(String, Option<i64>)>, SessionError> {
+        self.pool.load_with_expiry(id, table_name).await
+    }
+
     async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
         self.pool.delete_one_by_id(id, table_name).await
     }
@@ -69,6 +78,25 @@ impl DatabasePool for SessionAnyPool {
         self.pool.delete_by_expiry(table_name).await
     }
 
+    async fn delete_expired_before(
+        &self,
+        before: i64,
+        table_name: &str,
+    ) -> Result<Vec<String>, SessionError> {
+        self.pool.delete_expired_before(before, table_name).await
+    }
+
+    async fn delete_expired_batch(
+        &self,
+        before: i64,
+        limit: usize,
+        table_name: &str,
+    ) -> Result<Vec<String>, SessionError> {
+        self.pool
+            .delete_expired_batch(before, limit, table_name)
+            .await
+    }
+
     async fn delete_all(&self, table_name: &str) -> Result<(), SessionError> {
         self.pool.delete_all(table_name).await
     }
@@ -80,4 +108,27 @@ impl DatabasePool for SessionAnyPool {
     fn auto_handles_expiry(&self) -> bool {
         self.pool.auto_handles_expiry()
     }
+
+    fn is_valid_table_name(&self, table_name: &str) -> bool {
+        self.pool.is_valid_table_name(table_name)
+    }
+
+    async fn touch(&self, id: &str, expires: i64, table_name: &str) -> Result<bool, SessionError> {
+        self.pool.touch(id, expires, table_name).await
+    }
+
+    async fn publish_invalidation(
+        &self,
+        message: &str,
+        table_name: &str,
+    ) -> Result<(), SessionError> {
+        self.pool.publish_invalidation(message, table_name).await
+    }
+
+    async fn subscribe_invalidations(
+        &self,
+        table_name: &str,
+    ) -> Result<Option<UnboundedReceiver<String>>, SessionError> {
+        self.pool.subscribe_invalidations(table_name).await
+    }
 }
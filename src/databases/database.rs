@@ -1,5 +1,6 @@
 use crate::SessionError;
 use async_trait::async_trait;
+use tokio::sync::mpsc::UnboundedReceiver;
 
 /// The Trait used to identify a database pool.
 /// This can be freely implemented but default implementations for the supported database types are already included
@@ -31,6 +32,21 @@ pub trait DatabasePool {
     /// if an error occurs it should be propagated to the caller.
     async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError>;
 
+    /// This is called to receive the session and its expiration from the database using the given table name.
+    /// The expiration is the unix timestamp last given to `store` or `touch`, or None if it is not known.
+    /// The default calls load and returns no expiration, in which case the expiration is set from the lifespan.
+    /// if an error occurs it should be propagated to the caller.
+    async fn load_with_expiry(
+        &self,
+        id: &str,
+        table_name: &str,
+    ) -> Result<Option<(String, Option<i64>)>, SessionError> {
+        Ok(self
+            .load(id, table_name)
+            .await?
+            .map(|session| (session, None)))
+    }
+
     /// This is called to delete one session from the database using the given table name.
     /// if an error occurs it should be propagated to the caller.
     async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError>;
@@ -43,6 +59,32 @@ pub trait DatabasePool {
     /// if an error occurs it should be propagated to the caller.
     async fn delete_by_expiry(&self, table_name: &str) -> Result<Vec<String>, SessionError>;
 
+    /// This is called to delete all sessions that expired before the given unix timestamp from the database
+    /// using the given table name, returning their ids. Used to give in flight requests a grace period.
+    /// The default calls delete_by_expiry, which ignores the grace period.
+    /// if an error occurs it should be propagated to the caller.
+    async fn delete_expired_before(
+        &self,
+        _before: i64,
+        table_name: &str,
+    ) -> Result<Vec<String>, SessionError> {
+        self.delete_by_expiry(table_name).await
+    }
+
+    /// This is called to delete at most limit sessions that expired before the given unix timestamp
+    /// from the database using the given table name, returning their ids. Used when cleanup batching is enabled.
+    /// Every returned id must have been deleted or the cleanup will keep asking for more.
+    /// The default calls delete_expired_before, which ignores the limit.
+    /// if an error occurs it should be propagated to the caller.
+    async fn delete_expired_batch(
+        &self,
+        before: i64,
+        _limit: usize,
+        table_name: &str,
+    ) -> Result<Vec<String>, SessionError> {
+        self.delete_expired_before(before, table_name).await
+    }
+
     /// This is called to delete all sessions from the database using the given table name.
     /// if an error occurs it should be propagated to the caller.
     async fn delete_all(&self, table_name: &str) -> Result<(), SessionError>;
@@ -52,4 +94,68 @@ pub trait DatabasePool {
     async fn get_ids(&self, table_name: &str) -> Result<Vec<String>, SessionError>;
 
     fn auto_handles_expiry(&self) -> bool;
+
+    /// Returns true if the database can safely use the table name, such as within a query.
+    /// SessionStore::new returns SessionError::InvalidTableName otherwise. The default accepts any name.
+    fn is_valid_table_name(&self, _table_name: &str) -> bool {
+        true
+    }
+
+    /// This is called to only update the expiration of a stored session using the given table name.
+    /// Returns false if the database can not update the expiration alone or the session is not stored,
+    /// in which case the whole session is stored instead. The default returns false.
+    /// if an error occurs it should be propagated to the caller.
+    async fn touch(
+        &self,
+        _id: &str,
+        _expires: i64,
+        _table_name: &str,
+    ) -> Result<bool, SessionError> {
+        Ok(false)
+    }
+
+    /// This is called to tell the other servers sharing the database that a session changed or was removed
+    /// so they can drop their cached copy. Only called when invalidation is enabled.
+    /// The message must be sent to every receiver returned by `subscribe_invalidations` including our own.
+    /// The default does nothing for databases that do not support it.
+    async fn publish_invalidation(
+        &self,
+        _message: &str,
+        _table_name: &str,
+    ) -> Result<(), SessionError> {
+        Ok(())
+    }
+
+    /// This is called once when the SessionStore is created with invalidation enabled.
+    /// Returns a receiver for every message sent by `publish_invalidation` or None if not supported.
+    /// if an error occurs it should be propagated to the caller.
+    async fn subscribe_invalidations(
+        &self,
+        _table_name: &str,
+    ) -> Result<Option<UnboundedReceiver<String>>, SessionError> {
+        Ok(None)
+    }
+}
+
+/// Checks the table name is safe to place within a SQL or SurrealDB query.
+/// Allows an identifier made of letters, numbers and _ with an optional schema. prefix.
+#[cfg(any(
+    feature = "postgres-rustls",
+    feature = "postgres-native",
+    feature = "sqlite-rustls",
+    feature = "sqlite-native",
+    feature = "mysql-rustls",
+    feature = "mysql-native",
+    feature = "surreal"
+))]
+pub(crate) fn is_sql_table_name(table_name: &str) -> bool {
+    let parts: Vec<&str> = table_name.split('.').collect();
+
+    parts.len() <= 2
+        && parts.iter().all(|part| {
+            part.chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
 }
@@ -0,0 +1,228 @@
+use crate::{DatabasePool, Session, SessionError, SessionStore};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use tokio::fs;
+
+///File's Session Helper type for the DatabasePool.
+pub type SessionFileSession = Session<SessionFilePool>;
+///File's Session Store Helper type for the DatabasePool.
+pub type SessionFileSessionStore = SessionStore<SessionFilePool>;
+
+/// File Pool type for the DatabasePool. Needs a directory path.
+/// Each Session is stored as a Json file named by its id within a folder named after the table name.
+/// Meant for single node deployments that do not have a database.
+#[derive(Debug, Clone)]
+pub struct SessionFilePool {
+    path: PathBuf,
+}
+
+impl SessionFilePool {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn table_path(&self, table_name: &str) -> PathBuf {
+        self.path.join(table_name)
+    }
+
+    /// Returns None for ids that could point outside of the table folder.
+    fn session_path(&self, id: &str, table_name: &str) -> Option<PathBuf> {
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return None;
+        }
+
+        Some(self.table_path(table_name).join(format!("{}.json", id)))
+    }
+
+    async fn read(path: &Path) -> Result<Option<FileSession>, SessionError> {
+        match fs::read_to_string(path).await {
+            Ok(data) => Ok(Some(serde_json::from_str(&data)?)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Removes a Session file, logging the error when it fails. Returns if it was removed.
+    /// A file already removed by another process is skipped.
+    async fn remove(path: &Path) -> bool {
+        match fs::remove_file(path).await {
+            Ok(()) => true,
+            Err(err) if err.kind() == ErrorKind::NotFound => false,
+            Err(err) => {
+                tracing::error!(err = %err, path = %path.display(), "Session file could not be removed so it was skipped.");
+                false
+            }
+        }
+    }
+
+    /// Lists the id and path of every Session file within the table folder.
+    async fn files(&self, table_name: &str) -> Result<Vec<(String, PathBuf)>, SessionError> {
+        let mut entries = match fs::read_dir(self.table_path(table_name)).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut files = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    files.push((id.to_owned(), path.clone()));
+                }
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct FileSession {
+    session: String,
+    expires: i64,
+}
+
+#[async_trait]
+impl DatabasePool for SessionFilePool {
+    async fn initiate(&self, table_name: &str) -> Result<(), SessionError> {
+        fs::create_dir_all(self.table_path(table_name)).await?;
+        Ok(())
+    }
+
+    async fn delete_by_expiry(&self, table_name: &str) -> Result<Vec<String>, SessionError> {
+        self.delete_expired_before(chrono::Utc::now().timestamp(), table_name)
+            .await
+    }
+
+    async fn delete_expired_before(
+        &self,
+        before: i64,
+        table_name: &str,
+    ) -> Result<Vec<String>, SessionError> {
+        let mut ids = Vec::new();
+
+        // One unreadable file or one removed by another process does not stop the sweep.
+        for (id, path) in self.files(table_name).await? {
+            let file = match Self::read(&path).await {
+                Ok(Some(file)) => file,
+                Ok(None) => continue,
+                Err(err) => {
+                    tracing::error!(err = %err, path = %path.display(), "Session file could not be read so it was skipped.");
+                    continue;
+                }
+            };
+
+            if file.expires < before && Self::remove(&path).await {
+                ids.push(id);
+            }
+        }
+
+        Ok(ids)
+    }
+
+    async fn count(&self, table_name: &str) -> Result<i64, SessionError> {
+        Ok(self.files(table_name).await?.len() as i64)
+    }
+
+    async fn store(
+        &self,
+        id: &str,
+        session: &str,
+        expires: i64,
+        table_name: &str,
+    ) -> Result<(), SessionError> {
+        let path = self.session_path(id, table_name).ok_or_else(|| {
+            SessionError::GenericInsertError(format!("Invalid Session id {} for a file name.", id))
+        })?;
+        let data = serde_json::to_string(&FileSession {
+            session: session.to_owned(),
+            expires,
+        })?;
+
+        // Write to a temporary file first so a partial write never replaces a Session.
+        // The rename is atomic when both files are on the same file system.
+        let temp = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+        fs::write(&temp, data).await?;
+
+        if let Err(err) = fs::rename(&temp, &path).await {
+            let _ = fs::remove_file(&temp).await;
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
+    async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError> {
+        let Some(path) = self.session_path(id, table_name) else {
+            return Ok(None);
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        Ok(Self::read(&path)
+            .await?
+            .filter(|file| file.expires >= now)
+            .map(|file| file.session))
+    }
+
+    async fn load_with_expiry(
+        &self,
+        id: &str,
+        table_name: &str,
+    ) -> Result<Option<(String, Option<i64>)>, SessionError> {
+        let Some(path) = self.session_path(id, table_name) else {
+            return Ok(None);
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        Ok(Self::read(&path)
+            .await?
+            .filter(|file| file.expires >= now)
+            .map(|file| (file.session, Some(file.expires))))
+    }
+
+    async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
+        if let Some(path) = self.session_path(id, table_name) {
+            match fs::remove_file(path).await {
+                Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn exists(&self, id: &str, table_name: &str) -> Result<bool, SessionError> {
+        Ok(self.load(id, table_name).await?.is_some())
+    }
+
+    async fn delete_all(&self, table_name: &str) -> Result<(), SessionError> {
+        for (_, path) in self.files(table_name).await? {
+            Self::remove(&path).await;
+        }
+
+        Ok(())
+    }
+
+    async fn get_ids(&self, table_name: &str) -> Result<Vec<String>, SessionError> {
+        Ok(self
+            .files(table_name)
+            .await?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect())
+    }
+
+    fn auto_handles_expiry(&self) -> bool {
+        false
+    }
+}
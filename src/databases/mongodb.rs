@@ -2,19 +2,26 @@ use crate::{DatabasePool, Session, SessionError, SessionStore};
 use async_trait::async_trait;
 use chrono::Utc;
 use mongodb::{
-    bson::{doc, Document},
-    Client,
+    bson::{doc, DateTime, Document},
+    options::IndexOptions,
+    Client, IndexModel,
 };
 use serde::{Deserialize, Serialize};
 
 pub type SessionMongoSession = Session<SessionMongoPool>;
 pub type SessionMongoSessionStore = SessionStore<SessionMongoPool>;
 
+/// Name of the TTL index Mongodb uses to remove expired sessions.
+pub(crate) const EXPIRY_INDEX_NAME: &str = "session_expire_at";
+
 #[derive(Default, Debug, Serialize, Deserialize)]
 struct MongoSessionData {
     id: String,
     expires: i64,
     session: String,
+    /// Bson Date copy of expires. Mongodb's TTL index can only remove documents by a Date.
+    #[serde(default)]
+    expire_at: Option<DateTime>,
 }
 impl MongoSessionData {
     fn to_document(&self) -> Document {
@@ -42,6 +49,7 @@ impl From<Client> for SessionMongoPool {
 impl DatabasePool for SessionMongoPool {
     // Make sure the collection exists in the database
     // by inserting a record then deleting it
+    // and create the TTL index so Mongodb removes expired sessions.
     async fn initiate(&self, table_name: &str) -> Result<(), SessionError> {
         let tmp = MongoSessionData::default();
         match &self.client.default_database() {
@@ -50,6 +58,17 @@ impl DatabasePool for SessionMongoPool {
 
                 let _ = &col.insert_one(&tmp, None).await?;
                 let _ = col.find_one_and_delete(tmp.to_document(), None).await?;
+
+                let index = IndexModel::builder()
+                    .keys(doc! {"expire_at": 1})
+                    .options(
+                        IndexOptions::builder()
+                            .name(Some(EXPIRY_INDEX_NAME.to_string()))
+                            .expire_after(Some(std::time::Duration::ZERO))
+                            .build(),
+                    )
+                    .build();
+                col.create_index(index, None).await?;
             }
             None => {}
         }
@@ -57,12 +76,15 @@ impl DatabasePool for SessionMongoPool {
     }
 
     async fn delete_by_expiry(&self, table_name: &str) -> Result<Vec<String>, SessionError> {
+        // Mongodb removes sessions with an expire_at for us using the TTL index.
+        // Sessions stored before expire_at existed are not in the index so remove those here.
         let mut ids: Vec<String> = Vec::new();
         match &self.client.default_database() {
             Some(db) => {
                 let now = Utc::now().timestamp();
-                let filter = doc! {"expires":
-                    {"$lte": now}
+                let filter = doc! {
+                    "expire_at": null,
+                    "expires": {"$lte": now}
                 };
                 let result = db
                     .collection::<MongoSessionData>(&table_name)
@@ -109,7 +131,8 @@ impl DatabasePool for SessionMongoPool {
                 let update_data = doc! {"$set": {
                     "id": id.to_string(),
                     "expires": expires,
-                    "session": session.to_string()
+                    "session": session.to_string(),
+                    "expire_at": DateTime::from_millis(expires.saturating_mul(1000))
                 }};
                 let update_options = mongodb::options::UpdateOptions::builder()
                     .upsert(Some(true))
@@ -124,6 +147,17 @@ impl DatabasePool for SessionMongoPool {
     }
 
     async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError> {
+        Ok(self
+            .load_with_expiry(id, table_name)
+            .await?
+            .map(|(session, _)| session))
+    }
+
+    async fn load_with_expiry(
+        &self,
+        id: &str,
+        table_name: &str,
+    ) -> Result<Option<(String, Option<i64>)>, SessionError> {
         Ok(match &self.client.default_database() {
             Some(db) => {
                 let filter = doc! {
@@ -141,7 +175,7 @@ impl DatabasePool for SessionMongoPool {
                         if result.session.is_empty() {
                             None
                         } else {
-                            Some(result.session)
+                            Some((result.session, Some(result.expires)))
                         }
                     }
                     None => None,
@@ -178,9 +212,10 @@ impl DatabasePool for SessionMongoPool {
     async fn delete_all(&self, table_name: &str) -> Result<(), SessionError> {
         match &self.client.default_database() {
             Some(db) => {
+                // delete_many instead of drop so the TTL index is kept.
                 let _ = db
                     .collection::<MongoSessionData>(&table_name)
-                    .drop(None)
+                    .delete_many(doc! {}, None)
                     .await?;
             }
             None => {}
@@ -212,6 +247,6 @@ impl DatabasePool for SessionMongoPool {
     }
 
     fn auto_handles_expiry(&self) -> bool {
-        false
+        true
     }
 }
@@ -1,4 +1,4 @@
-use crate::{DatabasePool, Session, SessionError, SessionStore};
+use crate::{is_sql_table_name, DatabasePool, Session, SessionError, SessionStore};
 use async_trait::async_trait;
 use chrono::Utc;
 use sqlx::{pool::Pool, MySql, MySqlPool};
@@ -40,6 +40,15 @@ impl DatabasePool for SessionMySqlPool {
     }
 
     async fn delete_by_expiry(&self, table_name: &str) -> Result<Vec<String>, SessionError> {
+        self.delete_expired_before(Utc::now().timestamp(), table_name)
+            .await
+    }
+
+    async fn delete_expired_before(
+        &self,
+        before: i64,
+        table_name: &str,
+    ) -> Result<Vec<String>, SessionError> {
         let result: Vec<(String,)> = sqlx::query_as(
             &r#"
             SELECT id FROM %%TABLE_NAME%%
@@ -47,7 +56,7 @@ impl DatabasePool for SessionMySqlPool {
         "#
             .replace("%%TABLE_NAME%%", table_name),
         )
-        .bind(Utc::now().timestamp())
+        .bind(before)
         .fetch_all(&self.pool)
         .await?;
 
@@ -57,13 +66,53 @@ impl DatabasePool for SessionMySqlPool {
             &r#"DELETE FROM %%TABLE_NAME%% WHERE expires < ?"#
                 .replace("%%TABLE_NAME%%", table_name),
         )
-        .bind(Utc::now().timestamp())
+        .bind(before)
         .execute(&self.pool)
         .await?;
 
         Ok(result)
     }
 
+    async fn delete_expired_batch(
+        &self,
+        before: i64,
+        limit: usize,
+        table_name: &str,
+    ) -> Result<Vec<String>, SessionError> {
+        let result: Vec<(String,)> = sqlx::query_as(
+            &r#"
+            SELECT id FROM %%TABLE_NAME%%
+            WHERE expires < ?
+            LIMIT ?
+        "#
+            .replace("%%TABLE_NAME%%", table_name),
+        )
+        .bind(before)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let result: Vec<String> = result.into_iter().map(|(s,)| s).collect();
+
+        if result.is_empty() {
+            return Ok(result);
+        }
+
+        // MySQL can not use LIMIT within an IN subquery so the selected ids are deleted directly.
+        let query = r#"DELETE FROM %%TABLE_NAME%% WHERE expires < ? AND id IN (%%IDS%%)"#
+            .replace("%%TABLE_NAME%%", table_name)
+            .replace("%%IDS%%", &vec!["?"; result.len()].join(", "));
+        let mut query = sqlx::query(&query).bind(before);
+
+        for id in &result {
+            query = query.bind(id);
+        }
+
+        query.execute(&self.pool).await?;
+
+        Ok(result)
+    }
+
     async fn count(&self, table_name: &str) -> Result<i64, SessionError> {
         let (count,) = sqlx::query_as(
             &r#"SELECT COUNT(*) FROM %%TABLE_NAME%%"#.replace("%%TABLE_NAME%%", table_name),
@@ -100,9 +149,20 @@ impl DatabasePool for SessionMySqlPool {
     }
 
     async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError> {
-        let result: Option<(String,)> = sqlx::query_as(
+        Ok(self
+            .load_with_expiry(id, table_name)
+            .await?
+            .map(|(session, _)| session))
+    }
+
+    async fn load_with_expiry(
+        &self,
+        id: &str,
+        table_name: &str,
+    ) -> Result<Option<(String, Option<i64>)>, SessionError> {
+        let result: Option<(String, Option<i64>)> = sqlx::query_as(
             &r#"
-            SELECT session FROM %%TABLE_NAME%%
+            SELECT session, expires FROM %%TABLE_NAME%%
             WHERE id = ? AND (expires IS NULL OR expires > ?)
         "#
             .replace("%%TABLE_NAME%%", table_name),
@@ -112,7 +172,7 @@ impl DatabasePool for SessionMySqlPool {
         .fetch_optional(&self.pool)
         .await?;
 
-        Ok(result.map(|(session,)| session))
+        Ok(result)
     }
 
     async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
@@ -168,4 +228,20 @@ impl DatabasePool for SessionMySqlPool {
     fn auto_handles_expiry(&self) -> bool {
         false
     }
+
+    fn is_valid_table_name(&self, table_name: &str) -> bool {
+        is_sql_table_name(table_name)
+    }
+
+    async fn touch(&self, id: &str, expires: i64, table_name: &str) -> Result<bool, SessionError> {
+        let result = sqlx::query(
+            &r#"UPDATE %%TABLE_NAME%% SET expires = ? WHERE id = ?"#
+                .replace("%%TABLE_NAME%%", table_name),
+        )
+        .bind(expires)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
 }
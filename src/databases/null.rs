@@ -8,6 +8,8 @@ pub type SessionNullSessionStore = SessionStore<SessionNullPool>;
 
 /// Null Pool type for a DatabaseLess Session.
 /// Use this when you do not want to load any database.
+/// Passing `None` as the client to `SessionStore::new` keeps all Sessions in memory only,
+/// where they are removed once their memory lifespan runs out.
 #[derive(Debug, Clone)]
 pub struct SessionNullPool;
 
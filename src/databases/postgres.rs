@@ -1,7 +1,9 @@
-use crate::{DatabasePool, Session, SessionError, SessionStore};
+use crate::{is_sql_table_name, DatabasePool, Session, SessionError, SessionStore};
 use async_trait::async_trait;
 use chrono::Utc;
-use sqlx::{pool::Pool, PgPool, Postgres};
+use sha2::{Digest, Sha256};
+use sqlx::{pool::Pool, postgres::PgListener, PgPool, Postgres};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
 
 ///Postgres's Session Helper type for the DatabasePool.
 pub type SessionPgSession = Session<SessionPgPool>;
@@ -20,6 +22,30 @@ impl From<Pool<Postgres>> for SessionPgPool {
     }
 }
 
+/// Postgres truncates identifiers such as channel names to 63 bytes.
+const MAX_CHANNEL_LEN: usize = 63;
+
+/// The NOTIFY channel used to send Session invalidations for the table.
+/// Long table names are cut short and end with a hash of the full name so
+/// tables sharing a long prefix still get their own channel.
+pub(crate) fn invalidation_channel(table_name: &str) -> String {
+    let channel = format!("{}_invalidation", table_name);
+
+    if channel.len() <= MAX_CHANNEL_LEN {
+        return channel;
+    }
+
+    let hash: String = Sha256::digest(table_name.as_bytes())
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    // Table names are ascii so any byte is a char boundary.
+    let prefix = &table_name[..MAX_CHANNEL_LEN - hash.len() - 1];
+    format!("{}_{}", prefix, hash)
+}
+
 #[async_trait]
 impl DatabasePool for SessionPgPool {
     async fn initiate(&self, table_name: &str) -> Result<(), SessionError> {
@@ -40,6 +66,15 @@ impl DatabasePool for SessionPgPool {
     }
 
     async fn delete_by_expiry(&self, table_name: &str) -> Result<Vec<String>, SessionError> {
+        self.delete_expired_before(Utc::now().timestamp(), table_name)
+            .await
+    }
+
+    async fn delete_expired_before(
+        &self,
+        before: i64,
+        table_name: &str,
+    ) -> Result<Vec<String>, SessionError> {
         let result: Vec<(String,)> = sqlx::query_as(
             &r#"
             SELECT id FROM %%TABLE_NAME%%
@@ -47,7 +82,7 @@ impl DatabasePool for SessionPgPool {
         "#
             .replace("%%TABLE_NAME%%", table_name),
         )
-        .bind(Utc::now().timestamp())
+        .bind(before)
         .fetch_all(&self.pool)
         .await?;
 
@@ -57,12 +92,34 @@ impl DatabasePool for SessionPgPool {
             &r#"DELETE FROM %%TABLE_NAME%% WHERE expires < $1"#
                 .replace("%%TABLE_NAME%%", table_name),
         )
-        .bind(Utc::now().timestamp())
+        .bind(before)
         .execute(&self.pool)
         .await?;
         Ok(result)
     }
 
+    async fn delete_expired_batch(
+        &self,
+        before: i64,
+        limit: usize,
+        table_name: &str,
+    ) -> Result<Vec<String>, SessionError> {
+        let result: Vec<(String,)> = sqlx::query_as(
+            &r#"
+            DELETE FROM %%TABLE_NAME%%
+            WHERE id IN (SELECT id FROM %%TABLE_NAME%% WHERE expires < $1 LIMIT $2)
+            RETURNING id
+        "#
+            .replace("%%TABLE_NAME%%", table_name),
+        )
+        .bind(before)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(result.into_iter().map(|(s,)| s).collect())
+    }
+
     async fn count(&self, table_name: &str) -> Result<i64, SessionError> {
         let (count,) = sqlx::query_as(
             &r#"SELECT COUNT(*) FROM %%TABLE_NAME%%"#.replace("%%TABLE_NAME%%", table_name),
@@ -99,9 +156,20 @@ impl DatabasePool for SessionPgPool {
     }
 
     async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError> {
-        let result: Option<(String,)> = sqlx::query_as(
+        Ok(self
+            .load_with_expiry(id, table_name)
+            .await?
+            .map(|(session, _)| session))
+    }
+
+    async fn load_with_expiry(
+        &self,
+        id: &str,
+        table_name: &str,
+    ) -> Result<Option<(String, Option<i64>)>, SessionError> {
+        let result: Option<(String, Option<i64>)> = sqlx::query_as(
             &r#"
-            SELECT session FROM %%TABLE_NAME%%
+            SELECT session, expires::BIGINT FROM %%TABLE_NAME%%
             WHERE id = $1 AND (expires IS NULL OR expires > $2)
         "#
             .replace("%%TABLE_NAME%%", table_name),
@@ -111,7 +179,7 @@ impl DatabasePool for SessionPgPool {
         .fetch_optional(&self.pool)
         .await?;
 
-        Ok(result.map(|(session,)| session))
+        Ok(result)
     }
 
     async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
@@ -167,4 +235,72 @@ impl DatabasePool for SessionPgPool {
     fn auto_handles_expiry(&self) -> bool {
         false
     }
+
+    fn is_valid_table_name(&self, table_name: &str) -> bool {
+        is_sql_table_name(table_name)
+    }
+
+    async fn touch(&self, id: &str, expires: i64, table_name: &str) -> Result<bool, SessionError> {
+        let result = sqlx::query(
+            &r#"UPDATE %%TABLE_NAME%% SET expires = $1 WHERE id = $2"#
+                .replace("%%TABLE_NAME%%", table_name),
+        )
+        .bind(expires)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn publish_invalidation(
+        &self,
+        message: &str,
+        table_name: &str,
+    ) -> Result<(), SessionError> {
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(invalidation_channel(table_name))
+            .bind(message)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn subscribe_invalidations(
+        &self,
+        table_name: &str,
+    ) -> Result<Option<UnboundedReceiver<String>>, SessionError> {
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        listener.listen(&invalidation_channel(table_name)).await?;
+
+        let (sender, receiver) = unbounded_channel();
+
+        // Stops once the SessionStore drops the receiver, which closes the listener's connection.
+        tokio::spawn(async move {
+            loop {
+                let result = tokio::select! {
+                    result = listener.recv() => result,
+                    _ = sender.closed() => break,
+                };
+
+                match result {
+                    Ok(notification) => {
+                        if sender.send(notification.payload().to_owned()).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        // The listener reconnects on the next recv so wait a bit before trying again.
+                        tracing::error!(err = %err, "Session invalidation listener lost its connection.");
+                        tokio::select! {
+                            _ = tokio::time::sleep(std::time::Duration::from_secs(1)) => {}
+                            _ = sender.closed() => break,
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Some(receiver))
+    }
 }
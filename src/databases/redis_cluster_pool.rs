@@ -85,8 +85,30 @@ impl DatabasePool for SessionRedisClusterPool {
         } else {
             format!("{}:{}", table_name, id)
         };
-        let result: String = redis::cmd("GET").arg(id).query_async(&mut con).await?;
-        Ok(Some(result))
+        // GET returns nil when the session does not exist or has expired.
+        let result: Option<String> = redis::cmd("GET").arg(id).query_async(&mut con).await?;
+        Ok(result)
+    }
+
+    async fn load_with_expiry(
+        &self,
+        id: &str,
+        table_name: &str,
+    ) -> Result<Option<(String, Option<i64>)>, SessionError> {
+        let mut con = self.pool.aquire().await?;
+        let id = if table_name.is_empty() {
+            id.to_string()
+        } else {
+            format!("{}:{}", table_name, id)
+        };
+        // TTL is negative when the key has no expiration or does not exist.
+        let (result, ttl): (Option<String>, i64) = redis::pipe()
+            .get(&id)
+            .ttl(&id)
+            .query_async(&mut con)
+            .await?;
+        let expires = (ttl >= 0).then(|| chrono::Utc::now().timestamp() + ttl);
+        Ok(result.map(|session| (session, expires)))
     }
 
     async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
@@ -130,15 +152,13 @@ impl DatabasePool for SessionRedisClusterPool {
 
     async fn get_ids(&self, table_name: &str) -> Result<Vec<String>, SessionError> {
         let mut con = self.pool.aquire().await?;
-        let table_name = if table_name.is_empty() {
-            "*".to_string()
-        } else {
-            format!("{}:0", table_name)
-        };
 
-        let result: Vec<String> =
-            super::redis_tools::scan_keys(&mut con, &format!("{}:*", table_name)).await?;
-        Ok(result)
+        if table_name.is_empty() {
+            return super::redis_tools::scan_keys(&mut con, "*").await;
+        }
+
+        let keys = super::redis_tools::scan_keys(&mut con, &format!("{}:*", table_name)).await?;
+        Ok(super::redis_tools::strip_table_name(keys, table_name))
     }
 
     fn auto_handles_expiry(&self) -> bool {
@@ -36,3 +36,15 @@ where
 
     Ok(keys)
 }
+
+/// Removes the table name from the front of the keys so only the session id's remain.
+pub fn strip_table_name(keys: Vec<String>, table_name: &str) -> Vec<String> {
+    let prefix = format!("{}:", table_name);
+
+    keys.into_iter()
+        .map(|key| match key.strip_prefix(&prefix) {
+            Some(id) => id.to_owned(),
+            None => key,
+        })
+        .collect()
+}
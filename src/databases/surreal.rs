@@ -1,4 +1,4 @@
-use crate::{DatabasePool, SessionError, SessionStore};
+use crate::{is_sql_table_name, DatabasePool, SessionError, SessionStore};
 use async_trait::async_trait;
 use chrono::Utc;
 use surrealdb::{Connection, Surreal};
@@ -53,6 +53,15 @@ impl<C: Connection> DatabasePool for SessionSurrealPool<C> {
     }
 
     async fn delete_by_expiry(&self, table_name: &str) -> Result<Vec<String>, SessionError> {
+        self.delete_expired_before(Utc::now().timestamp(), table_name)
+            .await
+    }
+
+    async fn delete_expired_before(
+        &self,
+        before: i64,
+        table_name: &str,
+    ) -> Result<Vec<String>, SessionError> {
         let mut res = self
             .connection
             .query(
@@ -60,6 +69,7 @@ impl<C: Connection> DatabasePool for SessionSurrealPool<C> {
                 WHERE sessionexpires = NONE OR sessionexpires < $expires;",
             )
             .bind(("table_name", table_name))
+            .bind(("expires", before))
             .await?;
 
         let ids: Vec<String> = res.take("sessionid")?;
@@ -67,7 +77,7 @@ impl<C: Connection> DatabasePool for SessionSurrealPool<C> {
         self.connection
             .query("DELETE type::table($table_name) WHERE sessionexpires < $expires;")
             .bind(("table_name", table_name))
-            .bind(("expires", Utc::now().timestamp()))
+            .bind(("expires", before))
             .await?;
 
         Ok(ids)
@@ -109,10 +119,21 @@ impl<C: Connection> DatabasePool for SessionSurrealPool<C> {
     }
 
     async fn load(&self, id: &str, table_name: &str) -> Result<Option<String>, SessionError> {
+        Ok(self
+            .load_with_expiry(id, table_name)
+            .await?
+            .map(|(session, _)| session))
+    }
+
+    async fn load_with_expiry(
+        &self,
+        id: &str,
+        table_name: &str,
+    ) -> Result<Option<(String, Option<i64>)>, SessionError> {
         let mut res = self
             .connection
             .query(
-                "SELECT sessionstore FROM type::thing($table_name, $session_id)
+                "SELECT sessionstore, sessionexpires FROM type::thing($table_name, $session_id)
                 WHERE sessionexpires = NONE OR sessionexpires > $expires;",
             )
             .bind(("table_name", table_name))
@@ -121,7 +142,8 @@ impl<C: Connection> DatabasePool for SessionSurrealPool<C> {
             .await?;
 
         let response: Option<String> = res.take("sessionstore")?;
-        Ok(response)
+        let expires: Option<i64> = res.take("sessionexpires")?;
+        Ok(response.map(|session| (session, expires)))
     }
 
     async fn delete_one_by_id(&self, id: &str, table_name: &str) -> Result<(), SessionError> {
@@ -177,4 +199,8 @@ impl<C: Connection> DatabasePool for SessionSurrealPool<C> {
     fn auto_handles_expiry(&self) -> bool {
         false
     }
+
+    fn is_valid_table_name(&self, table_name: &str) -> bool {
+        is_sql_table_name(table_name)
+    }
 }
@@ -27,6 +27,12 @@ pub enum SessionError {
     Mongodb(#[from] mongodb::error::Error),
     #[error(transparent)]
     SerdeJson(#[from] serde_json::error::Error),
+    #[cfg(feature = "messagepack")]
+    #[error(transparent)]
+    MessagePackEncode(#[from] rmp_serde::encode::Error),
+    #[cfg(feature = "messagepack")]
+    #[error(transparent)]
+    MessagePackDecode(#[from] rmp_serde::decode::Error),
     #[error(transparent)]
     HTTP(#[from] http::Error),
     #[error(transparent)]
@@ -51,6 +57,18 @@ pub enum SessionError {
     GenericDeleteError(String),
     #[error("{0}")]
     GenericNotSupportedError(String),
+    #[error("Invalid Session table name {0}. SQL databases and SurrealDB only allow letters, numbers and _ with an optional schema. prefix.")]
+    InvalidTableName(String),
+    #[error("Invalid Session cookie name {0}. Names must be unique, not empty and only contain letters, numbers and !#$%&'*+-.^_`|~.")]
+    InvalidCookieName(String),
+    #[error("Session cookies using SameSite=None must be secure. Browsers reject them otherwise, remove with_secure(false).")]
+    InsecureSameSiteNone,
+    #[error("Session data is {0} bytes which is larger than the max session size of {1} bytes.")]
+    SessionTooLarge(usize, usize),
+    #[error("Too many new Sessions were created from the same ip.")]
+    TooManyNewSessions,
+    #[error("The Session database did not finish the {0} call within the backend timeout.")]
+    BackendTimeout(&'static str),
     #[error("Session was not found. Either the session was unloaded or was never created.")]
     NoSessionError,
     #[error(
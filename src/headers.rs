@@ -1,20 +1,25 @@
 #[cfg(not(feature = "rest_mode"))]
 use crate::CookiesAdditionJar;
 use crate::{DatabasePool, Session, SessionConfig, SessionStore};
+use chrono::Duration;
 #[cfg(not(feature = "rest_mode"))]
 use cookie::{Cookie, CookieJar, Key};
 use forwarded_header_value::{ForwardedHeaderValue, Identifier};
 #[cfg(not(feature = "rest_mode"))]
 use http::header::{COOKIE, SET_COOKIE};
 #[cfg(feature = "rest_mode")]
-use http::{header::HeaderName, HeaderValue};
+use http::{
+    header::{HeaderName, AUTHORIZATION},
+    HeaderValue,
+};
 use http::{
     header::{FORWARDED, USER_AGENT},
     request::Request,
     HeaderMap,
 };
+use sha2::{Digest, Sha256};
 #[cfg(feature = "rest_mode")]
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap};
 use std::{
     fmt::Debug,
     net::{IpAddr, SocketAddr},
@@ -38,12 +43,10 @@ impl NameType {
         };
 
         #[cfg(not(feature = "rest_mode"))]
-        if config.cookie_and_header.prefix_with_host {
-            let mut prefixed = "__Host-".to_owned();
+        {
+            let mut prefixed = config.cookie_and_header.cookie_prefix.as_str().to_owned();
             prefixed.push_str(&name);
             prefixed
-        } else {
-            name
         }
 
         #[allow(clippy::let_and_return)]
@@ -62,24 +65,27 @@ where
     T: DatabasePool + Clone + Debug + Sync + Send + 'static,
 {
     let key = store.config.cookie_and_header.key.as_ref();
+    let encrypted = store.config.cookie_and_header.encrypt_cookies;
 
     let value = cookies
         .get_cookie(
-            &store.config.cookie_and_header.session_name,
+            &NameType::Data.get_name(&store.config),
             key,
+            encrypted,
             ip_user_agent.to_owned(),
             false,
         )
-        .and_then(|c| Uuid::parse_str(c.value()).ok());
+        .and_then(|c| store.config.cookie_and_header.id_encoding.decode(c.value()));
 
     let storable = cookies
         .get_cookie(
-            &store.config.cookie_and_header.store_name,
+            &NameType::Store.get_name(&store.config),
             key,
+            encrypted,
             ip_user_agent.to_owned(),
             true,
         )
-        .map_or(false, |c| c.value().parse().unwrap_or(false));
+        .is_some_and(|c| c.value().parse().unwrap_or(false));
 
     (value, storable)
 }
@@ -106,7 +112,7 @@ where
                 Some(c.to_owned())
             }
         })
-        .and_then(|c| Uuid::parse_str(&c).ok());
+        .and_then(|c| store.config.cookie_and_header.id_encoding.decode(&c));
 
     let name = store.config.cookie_and_header.store_name.to_string();
     let storable = headers
@@ -129,6 +135,7 @@ pub(crate) trait CookiesExt {
         &self,
         name: &str,
         key: Option<&Key>,
+        encrypted: bool,
         message: String,
         bypass: bool,
     ) -> Option<Cookie<'static>>;
@@ -136,6 +143,7 @@ pub(crate) trait CookiesExt {
         &mut self,
         cookie: Cookie<'static>,
         key: &Option<Key>,
+        encrypted: bool,
         message: String,
         bypass: bool,
     );
@@ -147,11 +155,18 @@ impl CookiesExt for CookieJar {
         &self,
         name: &str,
         key: Option<&Key>,
+        encrypted: bool,
         message: String,
         bypass: bool,
     ) -> Option<Cookie<'static>> {
         if !bypass {
             if let Some(key) = key {
+                if encrypted {
+                    // Decrypt first then verify the signed value within.
+                    let cookie = self.private(key).get(name)?;
+                    return CookieJar::new().message_signed(key, message).verify(cookie);
+                }
+
                 return self.message_signed(key, message).get(name);
             }
         }
@@ -163,11 +178,24 @@ impl CookiesExt for CookieJar {
         &mut self,
         cookie: Cookie<'static>,
         key: &Option<Key>,
+        encrypted: bool,
         message: String,
         bypass: bool,
     ) {
         if !bypass {
             if let Some(key) = key {
+                if encrypted {
+                    // Sign the value first so the ip and user agent are still checked, then encrypt it.
+                    let name = cookie.name().to_owned();
+                    let mut signed = CookieJar::new();
+                    signed.message_signed_mut(key, message).add(cookie);
+
+                    if let Some(cookie) = signed.get(&name).cloned() {
+                        self.private_mut(key).add(cookie);
+                    }
+                    return;
+                }
+
                 self.message_signed_mut(key, message).add(cookie);
                 return;
             }
@@ -178,21 +206,36 @@ impl CookiesExt for CookieJar {
 }
 
 #[cfg(not(feature = "rest_mode"))]
-fn create_cookie<'a>(config: &SessionConfig, value: String, cookie_type: NameType) -> Cookie<'a> {
+fn create_cookie<'a>(
+    config: &SessionConfig,
+    value: String,
+    cookie_type: NameType,
+    max_age: Option<Duration>,
+) -> Cookie<'a> {
     let mut cookie_builder = Cookie::build((cookie_type.get_name(config), value))
-        .path(config.cookie_and_header.cookie_path.clone())
-        .secure(config.cookie_and_header.cookie_secure)
+        .path(config.cookie_and_header.path())
+        .secure(config.cookie_and_header.is_secure())
         .http_only(config.cookie_and_header.cookie_http_only)
-        .same_site(config.cookie_and_header.cookie_same_site);
+        .same_site(config.cookie_and_header.cookie_same_site)
+        .partitioned(config.cookie_and_header.cookie_partitioned);
 
-    if let Some(domain) = &config.cookie_and_header.cookie_domain {
-        cookie_builder = cookie_builder.domain(domain.clone());
+    if let Some(domain) = config.cookie_and_header.domain() {
+        cookie_builder = cookie_builder.domain(domain);
     }
 
-    if let Some(max_age) = config.cookie_and_header.cookie_max_age {
-        let time_duration = max_age.to_std().expect("Max Age out of bounds");
+    if let Some(max_age) = max_age {
+        // A negative Max-Age expires the cookie right away instead of panicking.
+        let max_age = max_age.max(Duration::zero());
         cookie_builder =
-            cookie_builder.expires(Some((std::time::SystemTime::now() + time_duration).into()));
+            cookie_builder.max_age(cookie::time::Duration::seconds(max_age.num_seconds()));
+
+        if let Some(expires) = max_age
+            .to_std()
+            .ok()
+            .and_then(|duration| std::time::SystemTime::now().checked_add(duration))
+        {
+            cookie_builder = cookie_builder.expires(Some(expires.into()));
+        }
     }
 
     cookie_builder.build()
@@ -201,16 +244,14 @@ fn create_cookie<'a>(config: &SessionConfig, value: String, cookie_type: NameTyp
 #[cfg(not(feature = "rest_mode"))]
 fn remove_cookie<'a>(config: &SessionConfig, cookie_type: NameType) -> Cookie<'a> {
     let mut cookie_builder = Cookie::build((cookie_type.get_name(config), ""))
-        .path(config.cookie_and_header.cookie_path.clone())
+        .path(config.cookie_and_header.path())
+        .secure(config.cookie_and_header.is_secure())
         .http_only(config.cookie_and_header.cookie_http_only)
-        .same_site(cookie::SameSite::None);
+        .same_site(config.cookie_and_header.cookie_same_site)
+        .partitioned(config.cookie_and_header.cookie_partitioned);
 
-    if let Some(domain) = &config.cookie_and_header.cookie_domain {
-        cookie_builder = cookie_builder.domain(domain.clone());
-    }
-
-    if let Some(domain) = &config.cookie_and_header.cookie_domain {
-        cookie_builder = cookie_builder.domain(domain.clone());
+    if let Some(domain) = config.cookie_and_header.domain() {
+        cookie_builder = cookie_builder.domain(domain);
     }
 
     let mut cookie = cookie_builder.build();
@@ -237,6 +278,45 @@ pub(crate) fn get_cookies(headers: &HeaderMap) -> CookieJar {
     jar
 }
 
+#[cfg(not(feature = "rest_mode"))]
+/// Adds the Session ID from the configured query parameter to the CookieJar
+/// as the session cookie when the request did not send one.
+/// Returns true if the Session ID was read from the query parameter.
+pub(crate) fn add_query_session_id<B>(
+    req: &Request<B>,
+    config: &SessionConfig,
+    jar: &mut CookieJar,
+) -> bool {
+    let Some(param) = config.cookie_and_header.query_param.as_deref() else {
+        return false;
+    };
+
+    let name = NameType::Data.get_name(config);
+
+    if jar.get(&name).is_some() {
+        return false;
+    }
+
+    let value = req.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == param)
+            .map(|(_, value)| value)
+    });
+
+    // Parsed as an encoded cookie so percent encoded signed values are decoded.
+    if let Some(cookie) =
+        value.and_then(|value| Cookie::parse_encoded(format!("{name}={value}")).ok())
+    {
+        tracing::debug!("Session ID was read from the query parameter");
+        jar.add_original(cookie);
+        return true;
+    }
+
+    false
+}
+
 #[cfg(feature = "rest_mode")]
 /// This will get a Hashmap of all the headers that Exist.
 pub(crate) fn get_headers<T>(
@@ -259,6 +339,22 @@ where
         }
     }
 
+    if store.config.cookie_and_header.bearer_token {
+        let name = store.config.cookie_and_header.session_name.to_string();
+
+        if let Entry::Vacant(entry) = map.entry(name) {
+            if let Some(token) = headers
+                .get(AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split_once(' '))
+                .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("Bearer"))
+                .map(|(_, token)| token)
+            {
+                entry.insert(token.trim().to_owned());
+            }
+        }
+    }
+
     map
 }
 
@@ -278,6 +374,7 @@ pub(crate) fn set_headers<T>(
     ip_user_agent: &str,
     destroy: bool,
     storable: bool,
+    #[cfg_attr(feature = "rest_mode", allow(unused_variables))] cookie_max_age: Option<Duration>,
 ) where
     T: DatabasePool + Clone + Debug + Sync + Send + 'static,
 {
@@ -285,19 +382,37 @@ pub(crate) fn set_headers<T>(
     #[cfg(not(feature = "rest_mode"))]
     {
         let mut cookies = CookieJar::new();
+        let remove_cookies = !destroy
+            || session
+                .store
+                .config
+                .cookie_and_header
+                .remove_cookie_on_destroy;
 
         // Add SessionID
         if (storable || !session.store.config.session_mode.is_opt_in()) && !destroy {
             cookies.add_cookie(
-                create_cookie(&session.store.config, session.id.inner(), NameType::Data),
+                create_cookie(
+                    &session.store.config,
+                    session
+                        .store
+                        .config
+                        .cookie_and_header
+                        .id_encoding
+                        .encode(session.id.0),
+                    NameType::Data,
+                    cookie_max_age,
+                ),
                 &session.store.config.cookie_and_header.key,
+                session.store.config.cookie_and_header.encrypt_cookies,
                 ip_user_agent.to_owned(),
                 false,
             );
-        } else {
+        } else if remove_cookies {
             cookies.add_cookie(
                 remove_cookie(&session.store.config, NameType::Data),
                 &session.store.config.cookie_and_header.key,
+                session.store.config.cookie_and_header.encrypt_cookies,
                 ip_user_agent.to_owned(),
                 false,
             );
@@ -306,15 +421,22 @@ pub(crate) fn set_headers<T>(
         // Add Session Store Boolean
         if session.store.config.session_mode.is_opt_in() && storable && !destroy {
             cookies.add_cookie(
-                create_cookie(&session.store.config, storable.to_string(), NameType::Store),
+                create_cookie(
+                    &session.store.config,
+                    storable.to_string(),
+                    NameType::Store,
+                    cookie_max_age,
+                ),
                 &session.store.config.cookie_and_header.key,
+                session.store.config.cookie_and_header.encrypt_cookies,
                 ip_user_agent.to_owned(),
                 true,
             );
-        } else {
+        } else if remove_cookies {
             cookies.add_cookie(
                 remove_cookie(&session.store.config, NameType::Store),
                 &session.store.config.cookie_and_header.key,
+                session.store.config.cookie_and_header.encrypt_cookies,
                 ip_user_agent.to_owned(),
                 true,
             );
@@ -328,8 +450,14 @@ pub(crate) fn set_headers<T>(
         // Add SessionID
         if (storable || !session.store.config.session_mode.is_opt_in()) && !destroy {
             let name = NameType::Data.get_name(&session.store.config);
+            let id = session
+                .store
+                .config
+                .cookie_and_header
+                .id_encoding
+                .encode(session.id.0);
             let value = if let Some(key) = session.store.config.cookie_and_header.key.as_ref() {
-                match sign_header(&session.id.inner(), key, ip_user_agent) {
+                match sign_header(&id, key, ip_user_agent) {
                     Ok(v) => v,
                     Err(err) => {
                         tracing::error!(err = %err, "Failed to sign Session ID so blank will be used.");
@@ -337,7 +465,7 @@ pub(crate) fn set_headers<T>(
                     }
                 }
             } else {
-                session.id.inner()
+                id
             };
 
             if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
@@ -372,78 +500,188 @@ where
     if store.config.cookie_and_header.key.is_some()
         && store.config.cookie_and_header.with_ip_and_user_agent
     {
-        let headers = req.headers();
+        format!(
+            "{};{}",
+            get_ips(req, &store.config),
+            get_user_agent(req, &store.config)
+        )
+    } else {
+        String::new()
+    }
+}
+
+/// Combines the enabled socket, x forwarded, forwarded and x real ip's into a single String.
+pub(crate) fn get_ips<T>(req: &Request<T>, config: &SessionConfig) -> String {
+    let headers = req.headers();
+
+    let ip = if config.ip_user_agent.use_ip {
+        req.extensions()
+            .get::<axum::extract::ConnectInfo<SocketAddr>>()
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_default()
+    } else {
+        "".to_owned()
+    };
+
+    let x_forward_for_ip = if config.ip_user_agent.use_xforward_ip {
+        headers
+            .get(X_FORWARDED_FOR)
+            .and_then(|hv| hv.to_str().ok())
+            .and_then(|s| s.split(',').find_map(|s| s.trim().parse::<IpAddr>().ok()))
+            .map(|ip| ip.to_string())
+            .unwrap_or_default()
+    } else {
+        "".to_owned()
+    };
+
+    let forwarded_ip = if config.ip_user_agent.use_forward_ip {
+        headers
+            .get_all(FORWARDED)
+            .iter()
+            .find_map(|hv| {
+                hv.to_str()
+                    .ok()
+                    .and_then(|s| ForwardedHeaderValue::from_forwarded(s).ok())
+                    .and_then(|f| {
+                        f.iter()
+                            .filter_map(|fs| fs.forwarded_for.as_ref())
+                            .find_map(|ff| match ff {
+                                Identifier::SocketAddr(a) => Some(a.ip()),
+                                Identifier::IpAddr(ip) => Some(*ip),
+                                _ => None,
+                            })
+                    })
+            })
+            .map(|ip| ip.to_string())
+            .unwrap_or_default()
+    } else {
+        "".to_owned()
+    };
+
+    let real_ip = if config.ip_user_agent.use_real_ip {
+        headers
+            .get(X_REAL_IP)
+            .and_then(|hv| hv.to_str().ok())
+            .and_then(|s| s.parse::<IpAddr>().ok())
+            .map(|ip| ip.to_string())
+            .unwrap_or_default()
+    } else {
+        "".to_owned()
+    };
+
+    format!("{};{};{};{}", ip, x_forward_for_ip, forwarded_ip, real_ip)
+}
 
-        let ip = if store.config.ip_user_agent.use_ip {
+/// Gets the enabled ip's new Sessions are counted against for the new_session_limit.
+/// Clients can add any address to the front of the x forwarded and forwarded headers,
+/// so the last address, added by the proxy in front of us, is used from them instead.
+/// Returns None if none of the enabled ip's were found.
+pub(crate) fn get_limit_ip<T>(req: &Request<T>, config: &SessionConfig) -> Option<String> {
+    let headers = req.headers();
+    let mut ips = Vec::new();
+
+    if config.ip_user_agent.use_ip {
+        ips.extend(
             req.extensions()
                 .get::<axum::extract::ConnectInfo<SocketAddr>>()
-                .map(|addr| addr.ip().to_string())
-                .unwrap_or_default()
-        } else {
-            "".to_owned()
-        };
+                .map(|addr| addr.ip()),
+        );
+    }
 
-        let x_forward_for_ip = if store.config.ip_user_agent.use_xforward_ip {
+    if config.ip_user_agent.use_xforward_ip {
+        ips.extend(
             headers
-                .get(X_FORWARDED_FOR)
-                .and_then(|hv| hv.to_str().ok())
-                .and_then(|s| s.split(',').find_map(|s| s.trim().parse::<IpAddr>().ok()))
-                .map(|ip| ip.to_string())
-                .unwrap_or_default()
-        } else {
-            "".to_owned()
-        };
+                .get_all(X_FORWARDED_FOR)
+                .iter()
+                .filter_map(|hv| hv.to_str().ok())
+                .flat_map(|s| s.split(','))
+                .rev()
+                .find_map(|s| s.trim().parse::<IpAddr>().ok()),
+        );
+    }
 
-        let forwarded_ip = if store.config.ip_user_agent.use_forward_ip {
+    if config.ip_user_agent.use_forward_ip {
+        ips.extend(
             headers
                 .get_all(FORWARDED)
                 .iter()
-                .find_map(|hv| {
-                    hv.to_str()
-                        .ok()
-                        .and_then(|s| ForwardedHeaderValue::from_forwarded(s).ok())
-                        .and_then(|f| {
-                            f.iter()
-                                .filter_map(|fs| fs.forwarded_for.as_ref())
-                                .find_map(|ff| match ff {
-                                    Identifier::SocketAddr(a) => Some(a.ip()),
-                                    Identifier::IpAddr(ip) => Some(*ip),
-                                    _ => None,
-                                })
+                .filter_map(|hv| hv.to_str().ok())
+                .filter_map(|s| ForwardedHeaderValue::from_forwarded(s).ok())
+                .flat_map(|f| {
+                    f.iter()
+                        .filter_map(|fs| match fs.forwarded_for.as_ref() {
+                            Some(Identifier::SocketAddr(a)) => Some(a.ip()),
+                            Some(Identifier::IpAddr(ip)) => Some(*ip),
+                            _ => None,
                         })
+                        .collect::<Vec<_>>()
                 })
-                .map(|ip| ip.to_string())
-                .unwrap_or_default()
-        } else {
-            "".to_owned()
-        };
+                .last(),
+        );
+    }
 
-        let real_ip = if store.config.ip_user_agent.use_real_ip {
+    if config.ip_user_agent.use_real_ip {
+        ips.extend(
             headers
                 .get(X_REAL_IP)
                 .and_then(|hv| hv.to_str().ok())
-                .and_then(|s| s.parse::<IpAddr>().ok())
+                .and_then(|s| s.parse::<IpAddr>().ok()),
+        );
+    }
+
+    if ips.is_empty() {
+        None
+    } else {
+        Some(
+            ips.iter()
                 .map(|ip| ip.to_string())
-                .unwrap_or_default()
-        } else {
-            "".to_owned()
-        };
+                .collect::<Vec<_>>()
+                .join(";"),
+        )
+    }
+}
 
-        let user_agent = if store.config.ip_user_agent.use_user_agent {
-            headers
-                .get(USER_AGENT)
-                .and_then(|hv| hv.to_str().ok())
-                .map(|useragent| useragent.to_string())
-                .unwrap_or_default()
-        } else {
-            "".to_owned()
-        };
+/// Gets the browsers user agent if it is enabled.
+fn get_user_agent<T>(req: &Request<T>, config: &SessionConfig) -> String {
+    if config.ip_user_agent.use_user_agent {
+        req.headers()
+            .get(USER_AGENT)
+            .and_then(|hv| hv.to_str().ok())
+            .map(|useragent| useragent.to_string())
+            .unwrap_or_default()
+    } else {
+        "".to_owned()
+    }
+}
 
-        format!(
-            "{};{};{};{};{}",
-            ip, x_forward_for_ip, forwarded_ip, real_ip, user_agent
-        )
+/// Gets a hash of the request's user agent to compare against the user agent the Session was bound to.
+/// Returns None if user agent binding is disabled.
+pub(crate) fn get_user_agent_binding<T>(
+    req: &Request<T>,
+    config: &SessionConfig,
+) -> Option<String> {
+    if config.ip_user_agent.bind_user_agent {
+        let user_agent = req
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|hv| hv.to_str().ok())
+            .unwrap_or_default();
+
+        Some(format!("{:x}", Sha256::digest(user_agent.as_bytes())))
     } else {
-        String::new()
+        None
+    }
+}
+
+/// Gets a hash of the request's ip's to compare against the ip the Session was bound to.
+/// Returns None if ip binding is disabled.
+pub(crate) fn get_ip_binding<T>(req: &Request<T>, config: &SessionConfig) -> Option<String> {
+    if config.ip_user_agent.bind_ip {
+        Some(format!(
+            "{:x}",
+            Sha256::digest(get_ips(req, config).as_bytes())
+        ))
+    } else {
+        None
     }
 }
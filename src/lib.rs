@@ -3,48 +3,418 @@
 #![warn(clippy::all, nonstandard_style, future_incompatible)]
 #![forbid(unsafe_code)]
 
+#[cfg(feature = "compression")]
+mod compress;
 mod config;
 pub mod databases;
 mod errors;
 pub(crate) mod headers;
 mod layer;
 mod sec;
+mod serializer;
 mod service;
 mod session;
 mod session_data;
 mod session_store;
+#[cfg(feature = "test-util")]
+pub mod testing;
+mod tiered_store;
 
-pub use config::{Key, SameSite, SessionConfig, SessionMode};
+pub use config::{
+    CookiePrefix, EvictionPolicy, Expiration, IdEncoding, Key, LoadErrorPolicy, SameSite,
+    SessionConfig, SessionEvent, SessionMode, SessionSerializer,
+};
 pub use databases::*;
 pub use errors::SessionError;
 pub use layer::SessionLayer;
 pub use sec::*;
-pub use session::{ReadOnlySession, Session};
-pub use session_store::SessionStore;
+pub use service::{SessionService, SkipSession};
+pub use session::{ReadOnlySession, Session, TypedSession, TypedSessionData};
+pub use session_store::{migrate_sessions, SessionStore};
+pub use tiered_store::TieredStore;
 
-pub(crate) use service::SessionService;
-pub(crate) use session_data::{SessionData, SessionID, SessionTimers};
+pub use session_data::SessionData;
+pub(crate) use session_data::{SessionID, SessionTimers};
 
 #[cfg(test)]
 mod tests {
     #[allow(unused_imports)]
     use super::*;
+    #[cfg(not(feature = "rest_mode"))]
+    use axum::response::Redirect;
     use axum::{
         body::Body,
         http::{header, Request},
-        response::Redirect,
         routing::get,
         Router,
     };
     use http_body_util::BodyExt;
+    #[cfg(not(feature = "rest_mode"))]
     use log::LevelFilter;
+    #[cfg(not(feature = "rest_mode"))]
     use serde::{Deserialize, Serialize};
-    use sqlx::{
-        postgres::{PgConnectOptions, PgPoolOptions},
-        ConnectOptions,
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+    #[cfg(not(feature = "rest_mode"))]
+    use sqlx::ConnectOptions;
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
     };
     use tower::ServiceExt;
 
+    /// A HashMap backed DatabasePool that counts how often it is used.
+    #[derive(Debug, Clone, Default)]
+    struct MemoryPool {
+        sessions: Arc<Mutex<HashMap<String, (String, i64)>>>,
+        loads: Arc<AtomicUsize>,
+        stores: Arc<AtomicUsize>,
+        touches: Arc<AtomicUsize>,
+        fail_loads: Arc<std::sync::atomic::AtomicBool>,
+        load_delay: Arc<Mutex<Option<std::time::Duration>>>,
+        cleanup_batches: Arc<AtomicUsize>,
+        subscribers: Arc<Mutex<Vec<tokio::sync::mpsc::UnboundedSender<String>>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl DatabasePool for MemoryPool {
+        async fn initiate(&self, _table_name: &str) -> Result<(), SessionError> {
+            Ok(())
+        }
+
+        async fn count(&self, _table_name: &str) -> Result<i64, SessionError> {
+            Ok(self.sessions.lock().unwrap().len() as i64)
+        }
+
+        async fn store(
+            &self,
+            id: &str,
+            session: &str,
+            expires: i64,
+            _table_name: &str,
+        ) -> Result<(), SessionError> {
+            self.stores.fetch_add(1, Ordering::SeqCst);
+            self.sessions
+                .lock()
+                .unwrap()
+                .insert(id.to_owned(), (session.to_owned(), expires));
+            Ok(())
+        }
+
+        async fn load(&self, id: &str, _table_name: &str) -> Result<Option<String>, SessionError> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            let delay = *self.load_delay.lock().unwrap();
+            if let Some(delay) = delay {
+                tokio::time::sleep(delay).await;
+            }
+            if self.fail_loads.load(Ordering::SeqCst) {
+                return Err(SessionError::GenericSelectError(
+                    "database unreachable".to_owned(),
+                ));
+            }
+            Ok(self
+                .sessions
+                .lock()
+                .unwrap()
+                .get(id)
+                .map(|(session, _)| session.clone()))
+        }
+
+        async fn load_with_expiry(
+            &self,
+            id: &str,
+            table_name: &str,
+        ) -> Result<Option<(String, Option<i64>)>, SessionError> {
+            let session = self.load(id, table_name).await?;
+            let expires = self
+                .sessions
+                .lock()
+                .unwrap()
+                .get(id)
+                .map(|(_, expires)| *expires);
+            Ok(session.map(|session| (session, expires)))
+        }
+
+        async fn delete_one_by_id(&self, id: &str, _table_name: &str) -> Result<(), SessionError> {
+            self.sessions.lock().unwrap().remove(id);
+            Ok(())
+        }
+
+        async fn exists(&self, id: &str, _table_name: &str) -> Result<bool, SessionError> {
+            Ok(self.sessions.lock().unwrap().contains_key(id))
+        }
+
+        async fn delete_by_expiry(&self, table_name: &str) -> Result<Vec<String>, SessionError> {
+            self.delete_expired_before(chrono::Utc::now().timestamp(), table_name)
+                .await
+        }
+
+        async fn delete_expired_before(
+            &self,
+            before: i64,
+            _table_name: &str,
+        ) -> Result<Vec<String>, SessionError> {
+            let mut sessions = self.sessions.lock().unwrap();
+            let expired: Vec<String> = sessions
+                .iter()
+                .filter(|(_, (_, expires))| *expires < before)
+                .map(|(id, _)| id.clone())
+                .collect();
+
+            expired.iter().for_each(|id| {
+                sessions.remove(id);
+            });
+            Ok(expired)
+        }
+
+        async fn delete_expired_batch(
+            &self,
+            before: i64,
+            limit: usize,
+            _table_name: &str,
+        ) -> Result<Vec<String>, SessionError> {
+            self.cleanup_batches.fetch_add(1, Ordering::SeqCst);
+            let mut sessions = self.sessions.lock().unwrap();
+            let expired: Vec<String> = sessions
+                .iter()
+                .filter(|(_, (_, expires))| *expires < before)
+                .map(|(id, _)| id.clone())
+                .take(limit)
+                .collect();
+
+            expired.iter().for_each(|id| {
+                sessions.remove(id);
+            });
+            Ok(expired)
+        }
+
+        async fn delete_all(&self, _table_name: &str) -> Result<(), SessionError> {
+            self.sessions.lock().unwrap().clear();
+            Ok(())
+        }
+
+        async fn get_ids(&self, _table_name: &str) -> Result<Vec<String>, SessionError> {
+            Ok(self.sessions.lock().unwrap().keys().cloned().collect())
+        }
+
+        fn auto_handles_expiry(&self) -> bool {
+            false
+        }
+
+        async fn touch(
+            &self,
+            id: &str,
+            expires: i64,
+            _table_name: &str,
+        ) -> Result<bool, SessionError> {
+            self.touches.fetch_add(1, Ordering::SeqCst);
+            if let Some((_, stored)) = self.sessions.lock().unwrap().get_mut(id) {
+                *stored = expires;
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        }
+
+        async fn publish_invalidation(
+            &self,
+            message: &str,
+            _table_name: &str,
+        ) -> Result<(), SessionError> {
+            self.subscribers
+                .lock()
+                .unwrap()
+                .retain(|subscriber| subscriber.send(message.to_owned()).is_ok());
+            Ok(())
+        }
+
+        async fn subscribe_invalidations(
+            &self,
+            _table_name: &str,
+        ) -> Result<Option<tokio::sync::mpsc::UnboundedReceiver<String>>, SessionError> {
+            let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+            self.subscribers.lock().unwrap().push(sender);
+            Ok(Some(receiver))
+        }
+    }
+
+    /// A tracing Subscriber that records the message of every event.
+    #[derive(Clone, Default)]
+    struct EventRecorder {
+        messages: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl tracing::Subscriber for EventRecorder {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            tracing::span::Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            struct Message(String);
+
+            impl tracing::field::Visit for Message {
+                fn record_debug(
+                    &mut self,
+                    field: &tracing::field::Field,
+                    value: &dyn std::fmt::Debug,
+                ) {
+                    if field.name() == "message" {
+                        self.0 = format!("{value:?}");
+                    }
+                }
+            }
+
+            let mut message = Message(String::new());
+            event.record(&mut message);
+            self.messages.lock().unwrap().push(message.0);
+        }
+
+        fn enter(&self, _span: &tracing::span::Id) {}
+
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
+    /// Routes used to set, get and destroy a value within the Session.
+    fn session_routes<T>(session_store: SessionStore<T>) -> Router
+    where
+        T: DatabasePool + Clone + std::fmt::Debug + Sync + Send + 'static,
+    {
+        session_handlers::<T>().layer(SessionLayer::new(session_store))
+    }
+
+    /// The routes of session_routes without the SessionLayer so tests can add their own
+    /// routes before the layer is applied.
+    fn session_handlers<T>() -> Router
+    where
+        T: DatabasePool + Clone + std::fmt::Debug + Sync + Send + 'static,
+    {
+        Router::new()
+            .route(
+                "/set",
+                get(|session: Session<T>| async move {
+                    session.set("value", 42);
+                }),
+            )
+            .route(
+                "/get",
+                get(|session: Session<T>| async move {
+                    session.get::<i32>("value").unwrap_or_default().to_string()
+                }),
+            )
+            .route(
+                "/destroy",
+                get(|session: Session<T>| async move {
+                    session.destroy();
+                }),
+            )
+            .route(
+                "/renew",
+                get(|session: Session<T>| async move {
+                    session.renew();
+                }),
+            )
+            .route(
+                "/remember",
+                get(|session: Session<T>| async move {
+                    session.set("value", 42);
+                    session.set_lifespan(chrono::Duration::try_days(30));
+                }),
+            )
+    }
+
+    fn request(uri: &str, cookie: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().uri(uri);
+
+        if let Some(cookie) = cookie {
+            builder = builder.header(header::COOKIE, cookie);
+        }
+
+        builder.body(Body::empty()).unwrap()
+    }
+
+    /// Returns the Set-Cookie header for the given cookie name.
+    fn set_cookie(response: &axum::response::Response, name: &str) -> Option<String> {
+        let prefix = format!("{}=", name);
+
+        response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find(|value| value.starts_with(&prefix))
+            .map(|value| value.to_owned())
+    }
+
+    /// Converts a Set-Cookie header into the name=value pair sent back by the browser.
+    fn cookie_pair(set_cookie: &str) -> String {
+        set_cookie.split(';').next().unwrap_or_default().to_owned()
+    }
+
+    async fn body_string(response: axum::response::Response) -> String {
+        let bytes = response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    /// Runs the same checks against any DatabasePool so every backend behaves the same way.
+    async fn database_pool_suite<P: DatabasePool + Sync>(pool: P) {
+        let table = "sessions";
+        let expires = chrono::Utc::now().timestamp() + 60;
+        pool.initiate(table).await.unwrap();
+        pool.delete_all(table).await.unwrap();
+
+        pool.store("first", "one", expires, table).await.unwrap();
+        pool.store("second", "two", expires, table).await.unwrap();
+        // Storing an existing id replaces the session.
+        pool.store("first", "three", expires, table).await.unwrap();
+
+        assert_eq!(pool.count(table).await.unwrap(), 2);
+        assert_eq!(
+            pool.load("first", table).await.unwrap(),
+            Some("three".to_owned())
+        );
+        assert_eq!(pool.load("missing", table).await.unwrap(), None);
+        assert_eq!(
+            pool.load_with_expiry("first", table).await.unwrap(),
+            Some(("three".to_owned(), Some(expires)))
+        );
+        assert!(pool.exists("second", table).await.unwrap());
+        // A missing session can not be touched so it gets stored in full instead.
+        assert!(!pool.touch("missing", expires, table).await.unwrap());
+
+        let mut ids = pool.get_ids(table).await.unwrap();
+        ids.sort();
+        assert_eq!(ids, vec!["first", "second"]);
+
+        pool.delete_one_by_id("second", table).await.unwrap();
+        assert!(!pool.exists("second", table).await.unwrap());
+
+        pool.store("expired", "old", expires - 120, table)
+            .await
+            .unwrap();
+        assert_eq!(pool.delete_by_expiry(table).await.unwrap(), vec!["expired"]);
+        assert_eq!(pool.count(table).await.unwrap(), 1);
+
+        pool.delete_all(table).await.unwrap();
+        assert_eq!(pool.count(table).await.unwrap(), 0);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
     #[tokio::test]
     async fn basic() {
         let config = SessionConfig::new()
@@ -143,4 +513,3410 @@ mod tests {
         let body = String::from_utf8(bytes).unwrap();
         assert_eq!(body, "Success");
     }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[test]
+    fn current_thread_cache_miss() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let session_store =
+                SessionStore::<SessionNullPool>::new(Some(SessionNullPool), SessionConfig::new())
+                    .await
+                    .unwrap();
+
+            let app = Router::new()
+                .route("/", get(|| async { "Success" }))
+                .layer(SessionLayer::new(session_store));
+
+            // An id that is not in memory forces the session to be loaded from the database.
+            let request = Request::builder()
+                .uri("/")
+                .header(header::COOKIE, format!("session={}", uuid::Uuid::new_v4()))
+                .body(Body::empty())
+                .unwrap();
+
+            let response =
+                tokio::time::timeout(std::time::Duration::from_secs(5), app.oneshot(request))
+                    .await
+                    .expect("session load blocked the runtime")
+                    .unwrap();
+            assert!(response.status().is_success());
+        });
+    }
+
+    #[tokio::test]
+    async fn failing_database_store() {
+        #[derive(Debug, Clone)]
+        struct FailingPool;
+
+        #[async_trait::async_trait]
+        impl DatabasePool for FailingPool {
+            async fn initiate(&self, _table_name: &str) -> Result<(), SessionError> {
+                Ok(())
+            }
+
+            async fn count(&self, _table_name: &str) -> Result<i64, SessionError> {
+                Ok(0)
+            }
+
+            async fn store(
+                &self,
+                _id: &str,
+                _session: &str,
+                _expires: i64,
+                _table_name: &str,
+            ) -> Result<(), SessionError> {
+                Err(SessionError::GenericInsertError(
+                    "database is offline".to_owned(),
+                ))
+            }
+
+            async fn load(
+                &self,
+                _id: &str,
+                _table_name: &str,
+            ) -> Result<Option<String>, SessionError> {
+                Ok(None)
+            }
+
+            async fn delete_one_by_id(
+                &self,
+                _id: &str,
+                _table_name: &str,
+            ) -> Result<(), SessionError> {
+                Ok(())
+            }
+
+            async fn exists(&self, _id: &str, _table_name: &str) -> Result<bool, SessionError> {
+                Ok(false)
+            }
+
+            async fn delete_by_expiry(
+                &self,
+                _table_name: &str,
+            ) -> Result<Vec<String>, SessionError> {
+                Ok(Vec::new())
+            }
+
+            async fn delete_all(&self, _table_name: &str) -> Result<(), SessionError> {
+                Ok(())
+            }
+
+            async fn get_ids(&self, _table_name: &str) -> Result<Vec<String>, SessionError> {
+                Ok(Vec::new())
+            }
+
+            fn auto_handles_expiry(&self) -> bool {
+                false
+            }
+        }
+
+        let session_store =
+            SessionStore::<FailingPool>::new(Some(FailingPool), SessionConfig::new())
+                .await
+                .unwrap();
+
+        let app = Router::new()
+            .route("/", get(|| async { "Success" }))
+            .layer(SessionLayer::new(session_store));
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(
+            response.status(),
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[tokio::test]
+    async fn new_session_limit_unknown_ids() {
+        let session_store = SessionStore::<SessionNullPool>::new(
+            None,
+            SessionConfig::new()
+                .with_hashed_xforward(true)
+                .with_new_session_limit(Some(3), chrono::Duration::try_minutes(1).unwrap()),
+        )
+        .await
+        .unwrap();
+        let app = session_routes(session_store.clone());
+        let from = |cookie: &str| {
+            let mut req = request("/set", Some(cookie));
+            req.headers_mut()
+                .insert("x-forwarded-for", "10.0.0.1".parse().unwrap());
+            req
+        };
+
+        // Made up Session ID's are recreated as new Sessions so they count against the limit.
+        for i in 0..8 {
+            let cookie = format!("session={}", uuid::Uuid::new_v4());
+            let response = app.clone().oneshot(from(&cookie)).await.unwrap();
+
+            if i < 3 {
+                assert_eq!(response.status(), http::StatusCode::OK);
+            } else {
+                assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+            }
+        }
+        assert_eq!(session_store.inner.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn new_session_counts_pruned() {
+        let (config, now) = fake_clock(
+            SessionConfig::new()
+                .with_hashed_xforward(true)
+                .with_new_session_limit(Some(3), chrono::Duration::try_minutes(1).unwrap()),
+        );
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+        let from = |ip: &str| {
+            let mut req = request("/set", None);
+            req.headers_mut()
+                .insert("x-forwarded-for", ip.parse().unwrap());
+            req
+        };
+
+        for ip in ["10.0.0.1", "10.0.0.2", "10.0.0.3"] {
+            app.clone().oneshot(from(ip)).await.unwrap();
+        }
+        assert_eq!(session_store.new_session_counts.len(), 3);
+
+        // Once their period ended the counts are dropped without waiting for a memory sweep.
+        *now.lock().unwrap() += chrono::Duration::try_minutes(2).unwrap();
+        app.oneshot(from("10.0.0.4")).await.unwrap();
+        assert_eq!(session_store.new_session_counts.len(), 1);
+    }
+
+    /// A clock that only moves when the test advances it.
+    fn fake_clock(
+        config: SessionConfig,
+    ) -> (SessionConfig, Arc<Mutex<chrono::DateTime<chrono::Utc>>>) {
+        let now = Arc::new(Mutex::new(chrono::Utc::now()));
+        let clock = now.clone();
+        (config.with_clock(move || *clock.lock().unwrap()), now)
+    }
+
+    #[tokio::test]
+    async fn clock_sweep() {
+        let (config, now) = fake_clock(SessionConfig::new());
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+
+        for _ in 0..3 {
+            app.clone().oneshot(request("/set", None)).await.unwrap();
+        }
+        assert_eq!(session_store.session_count(), 3);
+
+        // Before the purge interval no sweep runs.
+        *now.lock().unwrap() += chrono::Duration::try_minutes(30).unwrap();
+        app.clone().oneshot(request("/get", None)).await.unwrap();
+        assert_eq!(session_store.session_count(), 4);
+
+        // Past the purge interval and memory lifespan the next request sweeps the old Sessions.
+        *now.lock().unwrap() += chrono::Duration::try_hours(2).unwrap();
+        app.oneshot(request("/get", None)).await.unwrap();
+        assert_eq!(session_store.session_count(), 1);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn cloned_store_is_shared() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+            .await
+            .unwrap();
+        let clone = session_store.clone();
+        let app = session_routes(clone.clone());
+
+        let response = app.oneshot(request("/set", None)).await.unwrap();
+        let id = cookie_pair(&set_cookie(&response, "session").unwrap())
+            .trim_start_matches("session=")
+            .to_owned();
+
+        // A Session created through the layer's clone is seen by every other clone.
+        assert_eq!(session_store.active_session_ids(), vec![id.clone()]);
+        assert_eq!(session_store.get::<i32>(id.clone(), "value"), Some(42));
+
+        session_store.set(id.clone(), "value", 7);
+        assert_eq!(clone.get::<i32>(id, "value"), Some(7));
+
+        // The sweep timers are shared too.
+        clone.sweep_memory().await;
+        assert_eq!(
+            session_store.timers.read().await.last_expiry_sweep,
+            clone.timers.read().await.last_expiry_sweep
+        );
+
+        // A separately created store does not share its Sessions.
+        let other = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+            .await
+            .unwrap();
+        assert_eq!(other.session_count(), 0);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn with_data() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = Router::new()
+            .route(
+                "/batch",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set("guest", true);
+                    session
+                        .with_data(|data| {
+                            data.set("user-id", 1);
+                            data.set("role", "admin");
+                            data.remove("guest");
+                            data.keys().len()
+                        })
+                        .unwrap_or_default()
+                        .to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let response = app.oneshot(request("/batch", None)).await.unwrap();
+        let id = cookie_pair(&set_cookie(&response, "session").unwrap())
+            .trim_start_matches("session=")
+            .to_owned();
+        assert_eq!(body_string(response).await, "2");
+        assert_eq!(pool.stores.load(Ordering::SeqCst), 1);
+
+        // Every change made within the closure was saved to the database.
+        let stored = session_store.load_session(id).await.unwrap().unwrap();
+        assert_eq!(stored.get::<i32>("user-id"), Some(1));
+        assert_eq!(stored.get::<String>("role"), Some("admin".to_owned()));
+        assert!(!stored.contains_key("guest"));
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn with_locked() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+            .await
+            .unwrap();
+        let expires = chrono::Utc::now() + chrono::Duration::try_days(2).unwrap();
+        let app = session_handlers::<SessionNullPool>()
+            .route(
+                "/extend",
+                get(move |session: SessionNullSession| async move {
+                    session.with_locked(|data| data.expires = expires);
+                }),
+            )
+            .route(
+                "/expires",
+                get(|session: SessionNullSession| async move {
+                    session
+                        .with_locked(|data| data.expires_at().timestamp())
+                        .unwrap_or_default()
+                        .to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let response = app.clone().oneshot(request("/extend", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+        let response = app
+            .oneshot(request("/expires", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, expires.timestamp().to_string());
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn authenticated() {
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(MemoryPool::default()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = session_handlers::<MemoryPool>()
+            .route(
+                "/login",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set_authenticated(true);
+                }),
+            )
+            .route(
+                "/logout",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set_authenticated(false);
+                }),
+            )
+            .route(
+                "/status",
+                get(|session: ReadOnlySession<MemoryPool>| async move {
+                    session.is_authenticated().to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        // An anonymous Session keeps its data and ID when the user logs in.
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let status = |app: Router, cookie: String| async move {
+            body_string(
+                app.oneshot(request("/status", Some(&cookie)))
+                    .await
+                    .unwrap(),
+            )
+            .await
+        };
+        assert_eq!(status(app.clone(), cookie.clone()).await, "false");
+
+        app.clone()
+            .oneshot(request("/login", Some(&cookie)))
+            .await
+            .unwrap();
+        // Reload it from the database to check the flag was saved.
+        session_store.clone().clear().await;
+        assert_eq!(status(app.clone(), cookie.clone()).await, "true");
+
+        let response = app
+            .clone()
+            .oneshot(request("/get", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "42");
+
+        app.clone()
+            .oneshot(request("/logout", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(status(app, cookie).await, "false");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn spawn_sweeper() {
+        let (config, now) = fake_clock(
+            SessionConfig::new().with_memory_lifetime(chrono::Duration::try_minutes(5).unwrap()),
+        );
+        let pool = MemoryPool::default();
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+
+        app.oneshot(request("/set", None)).await.unwrap();
+        assert_eq!(session_store.session_count(), 1);
+
+        let sweeper = session_store.spawn_sweeper(chrono::Duration::try_seconds(30).unwrap());
+        // The first sweep runs right away and finds nothing expired.
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        assert_eq!(session_store.session_count(), 1);
+
+        // Unloaded from memory and then removed from the database without any requests.
+        *now.lock().unwrap() += chrono::Duration::try_minutes(10).unwrap();
+        tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+        assert_eq!(session_store.session_count(), 0);
+        assert_eq!(pool.sessions.lock().unwrap().len(), 1);
+
+        *now.lock().unwrap() += chrono::Duration::try_hours(7).unwrap();
+        tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+        assert!(pool.sessions.lock().unwrap().is_empty());
+
+        sweeper.abort();
+    }
+
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    /// Fails to compile if a field makes a public type unusable within shared State or across threads.
+    #[test]
+    fn public_types_are_send_sync() {
+        assert_send_sync::<SessionStore<SessionNullPool>>();
+        assert_send_sync::<SessionStore<MemoryPool>>();
+        assert_send_sync::<Session<SessionNullPool>>();
+        assert_send_sync::<ReadOnlySession<SessionNullPool>>();
+        assert_send_sync::<SessionData>();
+        assert_send_sync::<SessionConfig>();
+        assert_send_sync::<SessionLayer<SessionNullPool>>();
+        assert_send_sync::<TieredStore<SessionNullPool>>();
+        assert_send_sync::<SessionError>();
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn evict_and_reload() {
+        let pool = MemoryPool::default();
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let hook = evicted.clone();
+        let (config, now) = fake_clock(
+            SessionConfig::new().with_on_evict(move |id| hook.lock().unwrap().push(id.to_owned())),
+        );
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool), config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let mut ids = Vec::new();
+
+        for _ in 0..3 {
+            let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+            ids.push(
+                cookie_pair(&set_cookie(&response, "session").unwrap())
+                    .trim_start_matches("session=")
+                    .to_owned(),
+            );
+        }
+
+        *now.lock().unwrap() += chrono::Duration::try_hours(2).unwrap();
+        assert_eq!(session_store.sweep_memory().await, 3);
+        assert_eq!(session_store.session_count(), 0);
+
+        let mut swept = evicted.lock().unwrap().clone();
+        swept.sort();
+        ids.sort();
+        assert_eq!(swept, ids);
+
+        // The swept Session is still in the database so it can be loaded again.
+        assert!(session_store.reload(&ids[0]).await.unwrap());
+        assert_eq!(session_store.session_count(), 1);
+        assert_eq!(session_store.get::<i32>(ids[0].clone(), "value"), Some(42));
+
+        assert!(!session_store
+            .reload(&uuid::Uuid::new_v4().to_string())
+            .await
+            .unwrap());
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn expires_at() {
+        let (config, now) = fake_clock(SessionConfig::new());
+        let start = *now.lock().unwrap();
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        let app = session_handlers::<SessionNullPool>()
+            .route(
+                "/expires",
+                get(|session: SessionNullSession| async move {
+                    format!(
+                        "{} {}",
+                        session.expires_at().unwrap().timestamp(),
+                        session.expires_in().unwrap().num_minutes()
+                    )
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+        // The Session was saved at the start with the default 6 hour lifespan.
+        *now.lock().unwrap() += chrono::Duration::try_minutes(30).unwrap();
+        let response = app
+            .clone()
+            .oneshot(request("/expires", Some(&cookie)))
+            .await
+            .unwrap();
+        let expires = start + chrono::Duration::try_hours(6).unwrap();
+        assert_eq!(
+            body_string(response).await,
+            format!("{} {}", expires.timestamp(), 5 * 60 + 30)
+        );
+
+        // Past the expiration the time left does not go negative.
+        *now.lock().unwrap() += chrono::Duration::try_hours(6).unwrap();
+        let response = app
+            .oneshot(request("/expires", Some(&cookie)))
+            .await
+            .unwrap();
+        assert!(body_string(response).await.ends_with(" 0"));
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn created_at_and_age() {
+        let (config, now) = fake_clock(SessionConfig::new());
+        let start = *now.lock().unwrap();
+        let session_store = SessionStore::<MemoryPool>::new(Some(MemoryPool::default()), config)
+            .await
+            .unwrap();
+        let app = session_handlers::<MemoryPool>()
+            .route(
+                "/age",
+                get(|session: Session<MemoryPool>| async move {
+                    // Changing the Session saves it which extends its expiration.
+                    session.set("seen", true);
+                    format!(
+                        "{} {} {}",
+                        session.created_at().unwrap().timestamp(),
+                        session.age().unwrap().num_minutes(),
+                        session.expires_at().unwrap().timestamp()
+                    )
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+        *now.lock().unwrap() += chrono::Duration::try_minutes(30).unwrap();
+        let response = app
+            .clone()
+            .oneshot(request("/age", Some(&cookie)))
+            .await
+            .unwrap();
+        let expires = start + chrono::Duration::try_hours(6).unwrap();
+        assert_eq!(
+            body_string(response).await,
+            format!("{} 30 {}", start.timestamp(), expires.timestamp())
+        );
+
+        // The expiration moved with the last request but the creation time did not.
+        *now.lock().unwrap() += chrono::Duration::try_minutes(30).unwrap();
+        let response = app.oneshot(request("/age", Some(&cookie))).await.unwrap();
+        let expires = expires + chrono::Duration::try_minutes(30).unwrap();
+        assert_eq!(
+            body_string(response).await,
+            format!("{} 60 {}", start.timestamp(), expires.timestamp())
+        );
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn id_encoding() {
+        for (encoding, len) in [
+            (IdEncoding::Hyphenated, 36),
+            (IdEncoding::Hex, 32),
+            (IdEncoding::Base64Url, 22),
+        ] {
+            let session_store = SessionStore::<SessionNullPool>::new(
+                None,
+                SessionConfig::new().with_id_encoding(encoding),
+            )
+            .await
+            .unwrap();
+            let app = session_routes(session_store.clone());
+
+            let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+            let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+            let value = cookie.trim_start_matches("session=");
+            assert_eq!(value.len(), len);
+
+            // The Session is kept in memory by its hyphenated Session ID.
+            let id = session_store.active_session_ids().pop().unwrap();
+            assert_eq!(encoding.decode(value).unwrap().to_string(), id);
+
+            let response = app
+                .clone()
+                .oneshot(request("/get", Some(&cookie)))
+                .await
+                .unwrap();
+            assert_eq!(
+                set_cookie(&response, "session").map(|c| cookie_pair(&c)),
+                Some(cookie.clone())
+            );
+            assert_eq!(body_string(response).await, "42");
+
+            // A malformed value is ignored and a new Session is created.
+            let response = app
+                .oneshot(request("/get", Some("session=not-a-session-id!!")))
+                .await
+                .unwrap();
+            assert_ne!(
+                set_cookie(&response, "session").map(|c| cookie_pair(&c)),
+                Some(cookie)
+            );
+            assert_eq!(body_string(response).await, "0");
+        }
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn clear_all() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = session_handlers::<MemoryPool>()
+            .route(
+                "/logout-everyone",
+                get(|store: SessionStore<MemoryPool>| async move {
+                    store.clear_all().await.unwrap();
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let mut cookies = Vec::new();
+
+        for _ in 0..3 {
+            let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+            cookies.push(cookie_pair(&set_cookie(&response, "session").unwrap()));
+        }
+        assert_eq!(pool.sessions.lock().unwrap().len(), 3);
+
+        // The Session clearing everything is in use so it is destroyed by its own response.
+        let response = app
+            .clone()
+            .oneshot(request("/logout-everyone", Some(&cookies[0])))
+            .await
+            .unwrap();
+        assert!(set_cookie(&response, "session")
+            .unwrap()
+            .contains("Max-Age=0"));
+        assert_eq!(session_store.session_count(), 0);
+        assert!(pool.sessions.lock().unwrap().is_empty());
+
+        // The old Session ID's are not reused.
+        for cookie in cookies {
+            let response = app
+                .clone()
+                .oneshot(request("/get", Some(&cookie)))
+                .await
+                .unwrap();
+            let new_cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+            assert_ne!(new_cookie, cookie);
+            assert_eq!(body_string(response).await, "0");
+        }
+    }
+
+    #[tokio::test]
+    async fn invalidation_listener_stops_on_drop() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new().with_invalidation(true);
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let clone = session_store.clone();
+
+        drop(session_store);
+        tokio::task::yield_now().await;
+        assert!(!pool.subscribers.lock().unwrap()[0].is_closed());
+
+        // Dropping the last clone stops the listener so the pool's sender sees it closed.
+        drop(clone);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(pool.subscribers.lock().unwrap()[0].is_closed());
+    }
+
+    #[cfg(any(feature = "postgres-rustls", feature = "postgres-native"))]
+    #[test]
+    fn postgres_invalidation_channel() {
+        assert_eq!(
+            crate::databases::invalidation_channel("sessions"),
+            "sessions_invalidation"
+        );
+
+        // Long table names fit within the 63 bytes Postgres allows and stay apart.
+        let first = crate::databases::invalidation_channel(&format!("{}_first", "a".repeat(60)));
+        let second = crate::databases::invalidation_channel(&format!("{}_second", "a".repeat(60)));
+        assert_eq!(first.len(), 63);
+        assert_eq!(second.len(), 63);
+        assert_ne!(first, second);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn clear_all_invalidates_other_servers() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new().with_invalidation(true);
+        let first_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config.clone())
+            .await
+            .unwrap();
+        let second_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let second = session_routes(second_store.clone());
+
+        let response = second.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        assert_eq!(second_store.session_count(), 1);
+
+        first_store.clear_all().await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(second_store.session_count(), 0);
+
+        let response = second
+            .oneshot(request("/get", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_ne!(
+            cookie_pair(&set_cookie(&response, "session").unwrap()),
+            cookie
+        );
+        assert_eq!(body_string(response).await, "0");
+    }
+
+    #[tokio::test]
+    async fn cleanup_batches() {
+        let pool = MemoryPool::default();
+        let session_store = SessionStore::<MemoryPool>::new(
+            Some(pool.clone()),
+            SessionConfig::new().with_cleanup_batch_size(Some(2), chrono::Duration::zero()),
+        )
+        .await
+        .unwrap();
+
+        let expired = chrono::Utc::now().timestamp() - 60;
+        let alive = chrono::Utc::now().timestamp() + 3600;
+        {
+            let mut sessions = pool.sessions.lock().unwrap();
+            for i in 0..5 {
+                sessions.insert(format!("expired-{i}"), (String::new(), expired));
+            }
+            sessions.insert("alive".to_owned(), (String::new(), alive));
+        }
+
+        // 5 expired sessions take two full batches and a short one that ends the cleanup.
+        let mut removed = session_store.cleanup().await.unwrap();
+        removed.sort();
+        assert_eq!(
+            removed,
+            (0..5).map(|i| format!("expired-{i}")).collect::<Vec<_>>()
+        );
+        assert_eq!(pool.cleanup_batches.load(Ordering::SeqCst), 3);
+        assert_eq!(pool.sessions.lock().unwrap().len(), 1);
+
+        // Nothing left to remove ends after a single empty batch.
+        assert!(session_store.cleanup().await.unwrap().is_empty());
+        assert_eq!(pool.cleanup_batches.load(Ordering::SeqCst), 4);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn backend_timeout() {
+        let pool = MemoryPool::default();
+        let session_store = SessionStore::<MemoryPool>::new(
+            Some(pool.clone()),
+            SessionConfig::new()
+                .with_backend_timeout(chrono::Duration::try_milliseconds(50))
+                .with_slow_backend_threshold(chrono::Duration::try_milliseconds(10)),
+        )
+        .await
+        .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+        session_store.clone().clear().await;
+
+        // A hung database fails the load instead of holding the request.
+        *pool.load_delay.lock().unwrap() = Some(std::time::Duration::from_secs(60));
+        assert!(matches!(
+            session_store.load_session(id.clone()).await,
+            Err(SessionError::BackendTimeout("load"))
+        ));
+
+        // The default LoadErrorPolicy gives the request a new Session instead of waiting.
+        let response = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            app.oneshot(request("/get", Some(&cookie))),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(body_string(response).await, "0");
+
+        // The timed out Session was not saved over the stored one.
+        let (data, _) = pool.sessions.lock().unwrap().get(&id).cloned().unwrap();
+        assert!(data.contains("42"));
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn skip_session() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = Router::new()
+            .route(
+                "/health",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set("value", 7);
+                    (SkipSession, "ok")
+                }),
+            )
+            .route("/metrics", get(|| async { "ok" }))
+            .route(
+                "/set",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set("value", 42);
+                }),
+            )
+            .route(
+                "/get",
+                get(|session: Session<MemoryPool>| async move {
+                    session.get::<i32>("value").unwrap_or_default().to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()))
+            .layer(axum::middleware::map_request(
+                |mut request: Request<Body>| async move {
+                    if request.uri().path() == "/metrics" {
+                        request.extensions_mut().insert(SkipSession);
+                    }
+                    request
+                },
+            ));
+
+        // A route that marks its response neither sets a cookie nor saves the new Session.
+        let response = app.clone().oneshot(request("/health", None)).await.unwrap();
+        assert!(set_cookie(&response, "session").is_none());
+        assert_eq!(pool.stores.load(Ordering::SeqCst), 0);
+        assert!(session_store.inner.is_empty());
+
+        // A request marked before the SessionLayer skips the Session entirely.
+        let response = app
+            .clone()
+            .oneshot(request("/metrics", None))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "ok");
+        assert_eq!(pool.stores.load(Ordering::SeqCst), 0);
+        assert!(session_store.inner.is_empty());
+
+        // An existing Session is left as it was.
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let stores = pool.stores.load(Ordering::SeqCst);
+        let response = app
+            .clone()
+            .oneshot(request("/health", Some(&cookie)))
+            .await
+            .unwrap();
+        assert!(set_cookie(&response, "session").is_none());
+        assert_eq!(pool.stores.load(Ordering::SeqCst), stores);
+
+        // The handler's change to the existing Session is undone.
+        let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "42");
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn migrate_sessions_between_stores() {
+        let old_pool = MemoryPool::default();
+        let old_store =
+            SessionStore::<MemoryPool>::new(Some(old_pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let new_pool = MemoryPool::default();
+        let new_store =
+            SessionStore::<MemoryPool>::new(Some(new_pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let old_app = session_routes(old_store.clone());
+        let new_app = session_routes(new_store.clone());
+
+        let mut cookies = Vec::new();
+
+        for _ in 0..3 {
+            let response = old_app
+                .clone()
+                .oneshot(request("/set", None))
+                .await
+                .unwrap();
+            cookies.push(cookie_pair(&set_cookie(&response, "session").unwrap()));
+        }
+
+        // Unload one so it is only copied from the database.
+        old_store
+            .inner
+            .remove(cookies[0].trim_start_matches("session="));
+
+        assert_eq!(old_store.all_ids().await.unwrap().len(), 3);
+        assert_eq!(migrate_sessions(&old_store, &new_store).await.unwrap(), 3);
+        assert_eq!(new_pool.sessions.lock().unwrap().len(), 3);
+
+        for cookie in cookies {
+            let response = new_app
+                .clone()
+                .oneshot(request("/get", Some(&cookie)))
+                .await
+                .unwrap();
+            assert_eq!(body_string(response).await, "42");
+        }
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[cfg(feature = "redis-db")]
+    #[tokio::test]
+    async fn redis_round_trip() {
+        let client = redis::Client::open("redis://default@127.0.0.1:6379/0").unwrap();
+        let pool = redis_pool::RedisPool::from(client);
+
+        let session_store =
+            SessionStore::<SessionRedisPool>::new(Some(pool.into()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+        // Unload the memory store so the session must come back from redis.
+        session_store.clone().clear().await;
+
+        let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "42");
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn custom_database_pool() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        assert_eq!(pool.sessions.lock().unwrap().len(), 1);
+
+        // Unload the memory store so the session must come back from the pool.
+        session_store.clone().clear().await;
+
+        let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "42");
+        assert_eq!(pool.loads.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn memory_only_session() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+            .await
+            .unwrap();
+        assert!(!session_store.is_persistent());
+        let app = session_routes(session_store.clone());
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+        let response = app
+            .clone()
+            .oneshot(request("/get", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "42");
+
+        // Expire the session so its data is cleared on the next load.
+        for mut session_data in session_store.inner.iter_mut() {
+            session_data.expires = chrono::Utc::now() - chrono::Duration::try_seconds(1).unwrap();
+            session_data.autoremove = session_data.expires;
+        }
+
+        let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "0");
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn renew_session_id() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = session_routes(session_store);
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+        let response = app
+            .clone()
+            .oneshot(request("/renew", Some(&cookie)))
+            .await
+            .unwrap();
+        let renewed = cookie_pair(&set_cookie(&response, "session").unwrap());
+        assert_ne!(cookie, renewed);
+
+        // The old id must no longer exist in the database.
+        let old_id = cookie.trim_start_matches("session=");
+        assert!(!pool.sessions.lock().unwrap().contains_key(old_id));
+
+        let response = app.oneshot(request("/get", Some(&renewed))).await.unwrap();
+        assert_eq!(body_string(response).await, "42");
+    }
+
+    #[tokio::test]
+    async fn sweep_intervals() {
+        let memory_interval = chrono::Duration::try_minutes(5).unwrap();
+        let database_interval = chrono::Duration::try_minutes(10).unwrap();
+        let config = SessionConfig::new()
+            .with_purge_update(memory_interval)
+            .with_purge_database_update(database_interval);
+        let session_store = SessionStore::<SessionNullPool>::new(Some(SessionNullPool), config)
+            .await
+            .unwrap();
+
+        let start = chrono::Utc::now();
+        {
+            let timers = session_store.timers.read().await;
+            assert!(
+                timers.last_expiry_sweep
+                    >= start + memory_interval - chrono::Duration::try_seconds(5).unwrap()
+            );
+            assert!(timers.last_expiry_sweep <= start + memory_interval);
+        }
+
+        // Force both sweeps to run on the next request.
+        {
+            let mut timers = session_store.timers.write().await;
+            timers.last_expiry_sweep = start;
+            timers.last_database_expiry_sweep = start;
+        }
+
+        let app = session_routes(session_store.clone());
+        app.oneshot(request("/get", None)).await.unwrap();
+
+        let timers = session_store.timers.read().await;
+        assert!(timers.last_expiry_sweep >= start + memory_interval);
+        assert!(timers.last_expiry_sweep < start + database_interval);
+        assert!(timers.last_database_expiry_sweep >= start + database_interval);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn database_sweep_keeps_memory_sessions() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+
+        // The database row expired while the memory copy is still valid.
+        let expired = chrono::Utc::now() - chrono::Duration::try_seconds(1).unwrap();
+        pool.sessions.lock().unwrap().get_mut(&id).unwrap().1 = expired.timestamp();
+        session_store
+            .timers
+            .write()
+            .await
+            .last_database_expiry_sweep = expired;
+
+        let response = app
+            .clone()
+            .oneshot(request("/get", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "42");
+        assert!(!pool.sessions.lock().unwrap().contains_key(&id));
+
+        // The sweep removed the database row and the memory copy is saved again on its next change.
+        app.oneshot(request("/set", Some(&cookie))).await.unwrap();
+        let (_, expires) = pool.sessions.lock().unwrap().get(&id).cloned().unwrap();
+        assert!(expires > chrono::Utc::now().timestamp());
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn read_only_request_skips_store() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = session_routes(session_store);
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        assert_eq!(pool.stores.load(Ordering::SeqCst), 1);
+
+        let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "42");
+        assert_eq!(pool.stores.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn lazy_loading() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new().with_lazy_loading(true);
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route("/", get(|| async { "Success" }))
+            .route(
+                "/set",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set("value", 42);
+                }),
+            )
+            .route(
+                "/load",
+                get(|session: Session<MemoryPool>| async move {
+                    session.load().await;
+                    session.get::<i32>("value").unwrap_or_default().to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+        // Unload the memory store so the session must come back from the database.
+        session_store.clone().clear().await;
+
+        let response = app
+            .clone()
+            .oneshot(request("/", Some(&cookie)))
+            .await
+            .unwrap();
+        assert!(set_cookie(&response, "session").is_some());
+        assert_eq!(pool.loads.load(Ordering::SeqCst), 0);
+
+        let response = app.oneshot(request("/load", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "42");
+        assert_eq!(pool.loads.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn lazy_loading_keeps_changes_before_load() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new().with_lazy_loading(true);
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/set",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set("value", 42);
+                }),
+            )
+            .route(
+                "/change",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set("other", 7);
+                }),
+            )
+            .route(
+                "/load",
+                get(|session: Session<MemoryPool>| async move {
+                    session.load().await;
+                    format!(
+                        "{} {}",
+                        session.get::<i32>("value").unwrap_or_default(),
+                        session.get::<i32>("other").unwrap_or_default()
+                    )
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        session_store.clone().clear().await;
+
+        // The handler never loaded the session but its change must still be stored.
+        app.clone()
+            .oneshot(request("/change", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(pool.loads.load(Ordering::SeqCst), 1);
+        assert!(session_store.pending_changes.is_empty());
+        session_store.clone().clear().await;
+
+        let response = app.oneshot(request("/load", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "42 7");
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn lazy_loading_touch_before_load() {
+        let pool = MemoryPool::default();
+        let (config, now) = fake_clock(SessionConfig::new().with_lazy_loading(true));
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/set",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set("value", 42);
+                }),
+            )
+            .route(
+                "/touch",
+                get(|session: Session<MemoryPool>| async move {
+                    // Reads need the session loaded first so this sees an empty session.
+                    let value = session.get::<i32>("value").unwrap_or_default();
+                    session.touch();
+                    value.to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+        let (_, stored) = pool.sessions.lock().unwrap().get(&id).cloned().unwrap();
+        session_store.clone().clear().await;
+        *now.lock().unwrap() += chrono::Duration::try_minutes(30).unwrap();
+
+        // The touch is kept until the session is loaded after the handler.
+        let response = app.oneshot(request("/touch", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "0");
+        assert!(session_store.pending_changes.is_empty());
+
+        let (_, expires) = pool.sessions.lock().unwrap().get(&id).cloned().unwrap();
+        assert_eq!(expires, stored + 30 * 60);
+    }
+
+    #[tokio::test]
+    async fn session_extractor() {
+        async fn handler(session: Session<SessionNullPool>) -> String {
+            session.get::<i32>("value").unwrap_or_default().to_string()
+        }
+
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+            .await
+            .unwrap();
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(SessionLayer::new(session_store));
+
+        let response = app.oneshot(request("/", None)).await.unwrap();
+        assert_eq!(body_string(response).await, "0");
+
+        // Without the SessionLayer the extractor rejects the request.
+        let app = Router::new().route("/", get(handler));
+        let response = app.oneshot(request("/", None)).await.unwrap();
+        assert_eq!(
+            response.status(),
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert!(body_string(response).await.contains("SessionLayer"));
+
+        // A SessionLayer for a different DatabasePool type can not be extracted either.
+        let session_store = SessionStore::<MemoryPool>::new(None, SessionConfig::new())
+            .await
+            .unwrap();
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(SessionLayer::new(session_store));
+        let response = app.oneshot(request("/", None)).await.unwrap();
+        assert_eq!(
+            response.status(),
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert!(body_string(response).await.contains("DatabasePool"));
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn custom_lifespan() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new().with_lifetime(chrono::Duration::try_hours(1).unwrap());
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let response = app
+            .clone()
+            .oneshot(request("/remember", None))
+            .await
+            .unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+
+        let outlives_config =
+            (chrono::Utc::now() + chrono::Duration::try_days(29).unwrap()).timestamp();
+        assert!(pool.sessions.lock().unwrap()[&id].1 > outlives_config);
+
+        // The custom lifespan is kept when the session is reloaded from the database.
+        session_store.inner.remove(&id);
+        let response = app
+            .clone()
+            .oneshot(request("/get", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "42");
+        assert_eq!(
+            session_store.inner.get(&id).unwrap().lifespan,
+            Some(chrono::Duration::try_days(30).unwrap().num_seconds())
+        );
+
+        // Without a database the session is kept in memory for its custom lifespan.
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+        let response = app.oneshot(request("/remember", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=");
+        assert!(session_store.inner.get(id).unwrap().autoremove.timestamp() > outlives_config);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn absolute_expiration() {
+        let pool = MemoryPool::default();
+        let max = chrono::Duration::try_hours(1).unwrap();
+        let config = SessionConfig::new().with_expiration(Expiration::Absolute(max));
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+        let created_at = session_store.inner.get(&id).unwrap().created_at;
+
+        // Requests keep updating the session but never extend it past the absolute expiration.
+        for _ in 0..3 {
+            let response = app
+                .clone()
+                .oneshot(request("/set", Some(&cookie)))
+                .await
+                .unwrap();
+            assert_eq!(body_string(response).await, "");
+            assert_eq!(
+                pool.sessions.lock().unwrap()[&id].1,
+                (created_at + max).timestamp()
+            );
+        }
+
+        // Once the absolute expiration is reached the session starts over with a new id.
+        session_store.inner.get_mut(&id).unwrap().created_at =
+            chrono::Utc::now() - max - chrono::Duration::try_seconds(1).unwrap();
+        let response = app
+            .clone()
+            .oneshot(request("/get", Some(&cookie)))
+            .await
+            .unwrap();
+        let renewed = cookie_pair(&set_cookie(&response, "session").unwrap());
+        assert_eq!(body_string(response).await, "0");
+        assert_ne!(renewed, cookie);
+        assert!(!pool.sessions.lock().unwrap().contains_key(&id));
+
+        // The old id can not be used to get back into the session.
+        let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "0");
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn signed_cookie() {
+        let config = SessionConfig::new().with_key(Key::generate());
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let value = cookie.trim_start_matches("session=");
+        let (signature, id) = value.split_at(value.len() - 36);
+        assert!(session_store.inner.contains_key(id));
+
+        let response = app
+            .clone()
+            .oneshot(request("/get", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "42");
+
+        // A different id with the old signature or without any signature gets a new session.
+        let other_id = uuid::Uuid::new_v4().to_string();
+        for tampered in [
+            format!("session={signature}{other_id}"),
+            format!("session={other_id}"),
+            format!("session={id}"),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(request("/get", Some(&tampered)))
+                .await
+                .unwrap();
+            let renewed = cookie_pair(&set_cookie(&response, "session").unwrap());
+            assert_ne!(renewed, tampered);
+            assert!(!renewed.ends_with(&other_id));
+            assert!(!renewed.ends_with(id));
+            assert_eq!(body_string(response).await, "0");
+        }
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn encrypted_cookie() {
+        let config = SessionConfig::new()
+            .with_key(Key::generate())
+            .with_encrypted_cookies(true);
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = session_store.inner.iter().next().unwrap().key().clone();
+        assert!(!cookie.contains(&id));
+
+        let response = app
+            .clone()
+            .oneshot(request("/get", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "42");
+
+        // A value that fails to decrypt gets a new session.
+        for tampered in [format!("session={id}"), format!("{cookie}A")] {
+            let response = app
+                .clone()
+                .oneshot(request("/get", Some(&tampered)))
+                .await
+                .unwrap();
+            assert_eq!(body_string(response).await, "0");
+        }
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn renew_expiry() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = Router::new()
+            .route(
+                "/get",
+                get(|session: Session<MemoryPool>| async move {
+                    session.get::<i32>("value").unwrap_or_default().to_string()
+                }),
+            )
+            .route(
+                "/renew_expiry",
+                get(|session: Session<MemoryPool>| async move {
+                    session.renew_expiry();
+                }),
+            )
+            .route(
+                "/renew_expiry_read_only",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set_read_only(true);
+                    session.renew_expiry();
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let response = app.clone().oneshot(request("/get", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+
+        let soon = chrono::Utc::now() + chrono::Duration::try_minutes(1).unwrap();
+        session_store.inner.get_mut(&id).unwrap().expires = soon;
+        pool.sessions.lock().unwrap().get_mut(&id).unwrap().1 = soon.timestamp();
+
+        // Reading the session does not extend it.
+        app.clone()
+            .oneshot(request("/get", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(pool.sessions.lock().unwrap()[&id].1, soon.timestamp());
+
+        app.clone()
+            .oneshot(request("/renew_expiry", Some(&cookie)))
+            .await
+            .unwrap();
+        let renewed = chrono::Utc::now() + chrono::Duration::try_hours(5).unwrap();
+        assert!(session_store.inner.get(&id).unwrap().expires > renewed);
+        assert!(pool.sessions.lock().unwrap()[&id].1 > renewed.timestamp());
+
+        // A read only request only extends the session in memory.
+        session_store.inner.get_mut(&id).unwrap().expires = soon;
+        pool.sessions.lock().unwrap().get_mut(&id).unwrap().1 = soon.timestamp();
+        app.clone()
+            .oneshot(request("/renew_expiry_read_only", Some(&cookie)))
+            .await
+            .unwrap();
+        assert!(session_store.inner.get(&id).unwrap().expires > renewed);
+        assert_eq!(pool.sessions.lock().unwrap()[&id].1, soon.timestamp());
+
+        // The next request that is not read only saves it.
+        app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+        assert!(pool.sessions.lock().unwrap()[&id].1 > renewed.timestamp());
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn read_only_request() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = Router::new()
+            .route(
+                "/set",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set("value", 42);
+                }),
+            )
+            .route(
+                "/read_only",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set_read_only(true);
+                    session.set("value", 7);
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+        let stores = pool.stores.load(Ordering::SeqCst);
+        let expires = pool.sessions.lock().unwrap()[&id].1;
+
+        // An expired session would normally be extended and saved.
+        let expired = chrono::Utc::now() - chrono::Duration::try_seconds(1).unwrap();
+        session_store.inner.get_mut(&id).unwrap().expires = expired;
+
+        app.oneshot(request("/read_only", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(pool.stores.load(Ordering::SeqCst), stores);
+        assert_eq!(pool.sessions.lock().unwrap()[&id].1, expires);
+        assert_eq!(session_store.inner.get(&id).unwrap().expires, expired);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn enforce_session_limit() {
+        let (config, now) = fake_clock(SessionConfig::new());
+        let pool = MemoryPool::default();
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let app = session_handlers::<MemoryPool>()
+            .route(
+                "/login",
+                get(
+                    |session: Session<MemoryPool>, store: SessionStore<MemoryPool>| async move {
+                        session.set("value", 42);
+                        session.set_owner(Some("user-1".to_owned()));
+                        store
+                            .enforce_session_limit("user-1", 3)
+                            .await
+                            .unwrap()
+                            .to_string()
+                    },
+                ),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let mut cookies = Vec::new();
+        for _ in 0..3 {
+            let response = app.clone().oneshot(request("/login", None)).await.unwrap();
+            cookies.push(cookie_pair(&set_cookie(&response, "session").unwrap()));
+            *now.lock().unwrap() += chrono::Duration::try_minutes(1).unwrap();
+        }
+
+        // One of the sessions is only left within the database.
+        session_store
+            .inner
+            .remove(cookies[1].trim_start_matches("session="));
+
+        // The fourth login destroys the oldest session.
+        let response = app.clone().oneshot(request("/login", None)).await.unwrap();
+        cookies.push(cookie_pair(&set_cookie(&response, "session").unwrap()));
+        assert_eq!(body_string(response).await, "1");
+        assert_eq!(pool.sessions.lock().unwrap().len(), 3);
+
+        let mut values = Vec::new();
+        for cookie in &cookies {
+            let response = app
+                .clone()
+                .oneshot(request("/get", Some(cookie)))
+                .await
+                .unwrap();
+            values.push(body_string(response).await);
+        }
+        assert_eq!(values, ["0", "42", "42", "42"]);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn enforce_session_limit_keeps_latest_login() {
+        let (config, now) = fake_clock(SessionConfig::new());
+        let pool = MemoryPool::default();
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let app = session_handlers::<MemoryPool>()
+            .route(
+                "/login",
+                get(
+                    |session: Session<MemoryPool>, store: SessionStore<MemoryPool>| async move {
+                        session.set_owner(Some("user-1".to_owned()));
+                        store
+                            .enforce_session_limit("user-1", 1)
+                            .await
+                            .unwrap()
+                            .to_string()
+                    },
+                ),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        // An anonymous session is created before the owner logs in elsewhere.
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let anonymous = cookie_pair(&set_cookie(&response, "session").unwrap());
+        *now.lock().unwrap() += chrono::Duration::try_minutes(1).unwrap();
+        let response = app.clone().oneshot(request("/login", None)).await.unwrap();
+        let elsewhere = cookie_pair(&set_cookie(&response, "session").unwrap());
+        *now.lock().unwrap() += chrono::Duration::try_minutes(1).unwrap();
+
+        // A row that can not be decoded is skipped instead of failing the limit.
+        pool.sessions
+            .lock()
+            .unwrap()
+            .insert("bad-row".to_owned(), ("not json".to_owned(), i64::MAX));
+
+        // Logging in on the older anonymous session keeps it over the newer one.
+        let response = app
+            .clone()
+            .oneshot(request("/login", Some(&anonymous)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "1");
+
+        let mut values = Vec::new();
+        for cookie in [&anonymous, &elsewhere] {
+            let response = app
+                .clone()
+                .oneshot(request("/get", Some(cookie)))
+                .await
+                .unwrap();
+            values.push(body_string(response).await);
+        }
+        assert_eq!(values, ["42", "0"]);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn enforce_session_limit_skips_expired() {
+        let (config, now) = fake_clock(SessionConfig::new().with_expiration(Expiration::Absolute(
+            chrono::Duration::try_minutes(10).unwrap(),
+        )));
+        let pool = MemoryPool::default();
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let app = session_handlers::<MemoryPool>()
+            .route(
+                "/login",
+                get(
+                    |session: Session<MemoryPool>, store: SessionStore<MemoryPool>| async move {
+                        session.set_owner(Some("user-1".to_owned()));
+                        store
+                            .enforce_session_limit("user-1", 3)
+                            .await
+                            .unwrap()
+                            .to_string()
+                    },
+                ),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let mut cookies = Vec::new();
+        for _ in 0..3 {
+            let response = app.clone().oneshot(request("/login", None)).await.unwrap();
+            cookies.push(cookie_pair(&set_cookie(&response, "session").unwrap()));
+        }
+
+        // One of the sessions is only left within the database.
+        session_store
+            .inner
+            .remove(cookies[0].trim_start_matches("session="));
+
+        // Every earlier session has expired so none of them count towards the limit.
+        *now.lock().unwrap() += chrono::Duration::try_minutes(20).unwrap();
+        let response = app.clone().oneshot(request("/login", None)).await.unwrap();
+        assert_eq!(body_string(response).await, "0");
+        assert_eq!(pool.sessions.lock().unwrap().len(), 4);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn destroy_all_by_owner() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = Router::new()
+            .route(
+                "/login",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set("value", 42);
+                    session.set_owner(Some("user-1".to_owned()));
+                }),
+            )
+            .route(
+                "/get",
+                get(|session: Session<MemoryPool>| async move {
+                    session.get::<i32>("value").unwrap_or_default().to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+        let other = session_routes(session_store.clone());
+
+        let mut owned = Vec::new();
+        for _ in 0..2 {
+            let response = app.clone().oneshot(request("/login", None)).await.unwrap();
+            owned.push(cookie_pair(&set_cookie(&response, "session").unwrap()));
+        }
+        let response = other.oneshot(request("/set", None)).await.unwrap();
+        let not_owned = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+        // One of the owned sessions is only left within the database.
+        session_store
+            .inner
+            .remove(owned[0].trim_start_matches("session="));
+
+        // A row that can not be decoded is skipped instead of stopping the others being destroyed.
+        pool.sessions
+            .lock()
+            .unwrap()
+            .insert("bad-row".to_owned(), ("not json".to_owned(), i64::MAX));
+
+        session_store.destroy_all_by_owner("user-1").await.unwrap();
+        assert_eq!(pool.sessions.lock().unwrap().len(), 2);
+
+        for cookie in owned {
+            let response = app
+                .clone()
+                .oneshot(request("/get", Some(&cookie)))
+                .await
+                .unwrap();
+            assert_eq!(body_string(response).await, "0");
+        }
+
+        let response = app
+            .oneshot(request("/get", Some(&not_owned)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "42");
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn tracing_events() {
+        let recorder = EventRecorder::default();
+        let _guard = tracing::subscriber::set_default(recorder.clone());
+
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        assert!(recorder
+            .messages
+            .lock()
+            .unwrap()
+            .contains(&"Session created".to_owned()));
+
+        // Force a memory sweep on the next request.
+        session_store.timers.write().await.last_expiry_sweep = chrono::Utc::now();
+        app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+
+        let messages = recorder.messages.lock().unwrap();
+        assert!(messages.contains(&"Session memory cache checked".to_owned()));
+        assert!(messages.contains(&"Session Memory Cleaning Finished".to_owned()));
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn event_callback() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let config = SessionConfig::new()
+            .with_event_callback(move |event| recorded.lock().unwrap().push(event));
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        app.clone().oneshot(request("/set", None)).await.unwrap();
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![SessionEvent::Created, SessionEvent::Created]
+        );
+
+        // Expire one of the sessions and force a memory sweep on the next request.
+        for mut session_data in session_store.inner.iter_mut() {
+            if !cookie.ends_with(session_data.key().as_str()) {
+                session_data.autoremove = chrono::Utc::now();
+            }
+        }
+        session_store.timers.write().await.last_expiry_sweep = chrono::Utc::now();
+        app.clone()
+            .oneshot(request("/get", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(
+            events.lock().unwrap().last(),
+            Some(&SessionEvent::MemorySweep { removed: 1 })
+        );
+
+        app.oneshot(request("/destroy", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(
+            events.lock().unwrap().last(),
+            Some(&SessionEvent::Destroyed)
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn compressed_session_data() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new().with_compression_threshold(Some(256));
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config.clone())
+            .await
+            .unwrap();
+        let stored = |id: uuid::Uuid| pool.sessions.lock().unwrap()[&id.to_string()].0.clone();
+
+        // Below the threshold the data is stored as plain Json.
+        let mut small = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        small.set("value", 42);
+        session_store.store_session(&small).await.unwrap();
+        assert!(stored(small.id).starts_with('{'));
+
+        // Above the threshold the data is compressed.
+        let mut large = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        large.set("value", "a".repeat(4096));
+        session_store.store_session(&large).await.unwrap();
+        assert!(stored(large.id).starts_with("gz:"));
+        assert!(stored(large.id).len() < 4096);
+
+        let loaded = session_store
+            .load_session(small.id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.get::<i32>("value"), Some(42));
+
+        let loaded = session_store
+            .load_session(large.id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.get::<String>("value"), Some("a".repeat(4096)));
+    }
+
+    #[cfg(feature = "messagepack")]
+    #[tokio::test]
+    async fn messagepack_serializer() {
+        let pool = MemoryPool::default();
+        let json_config = SessionConfig::new();
+        let msgpack_config = SessionConfig::new().with_serializer(SessionSerializer::MessagePack);
+        let json_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), json_config.clone())
+            .await
+            .unwrap();
+        let msgpack_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), msgpack_config.clone())
+                .await
+                .unwrap();
+        let stored = |id: uuid::Uuid| pool.sessions.lock().unwrap()[&id.to_string()].0.clone();
+
+        let mut packed = SessionData::new(uuid::Uuid::new_v4(), true, &msgpack_config);
+        packed.set("value", 42);
+        msgpack_store.store_session(&packed).await.unwrap();
+        assert!(stored(packed.id).starts_with("mp:"));
+
+        let mut json = SessionData::new(uuid::Uuid::new_v4(), true, &json_config);
+        json.set("value", "json");
+        json_store.store_session(&json).await.unwrap();
+        assert!(stored(json.id).starts_with('{'));
+
+        // Round trip with the MessagePack serializer.
+        let loaded = msgpack_store
+            .load_session(packed.id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.get::<i32>("value"), Some(42));
+
+        // The marker picks the decoder, so each store reads the other's format.
+        let loaded = json_store
+            .load_session(packed.id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.get::<i32>("value"), Some(42));
+
+        let loaded = msgpack_store
+            .load_session(json.id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.get::<String>("value"), Some("json".to_owned()));
+
+        // Saving again rewrites the Session in the configured format.
+        msgpack_store.store_session(&loaded).await.unwrap();
+        assert!(stored(json.id).starts_with("mp:"));
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn testing_harness() {
+        let store = testing::memory_store(SessionConfig::default()).await;
+        let app = testing::router_with_session(
+            Router::new().route(
+                "/count",
+                get(|session: SessionNullSession| async move {
+                    session.increment("count", 1).to_string()
+                }),
+            ),
+            store,
+        );
+
+        let response = app.clone().oneshot(request("/count", None)).await.unwrap();
+        let cookie = testing::cookie_header(&response).unwrap();
+        assert_eq!(body_string(response).await, "1");
+
+        let response = app.oneshot(request("/count", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "2");
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn file_pool() {
+        let path = std::env::temp_dir().join(format!("axum_session_{}", uuid::Uuid::new_v4()));
+        let session_store = SessionStore::<SessionFilePool>::new(
+            Some(SessionFilePool::new(&path)),
+            SessionConfig::new(),
+        )
+        .await
+        .unwrap();
+        let app = session_routes(session_store);
+
+        let response = app.oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+        // A new store has nothing in memory so the session must come back from its file.
+        let pool = SessionFilePool::new(&path);
+        let session_store =
+            SessionStore::<SessionFilePool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = session_routes(session_store);
+
+        let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "42");
+        assert_eq!(pool.count("sessions").await.unwrap(), 1);
+
+        // Expired files are removed by the sweep.
+        let expires = chrono::Utc::now().timestamp() - 1;
+        pool.store("expired", "{}", expires, "sessions")
+            .await
+            .unwrap();
+        assert!(!pool.exists("expired", "sessions").await.unwrap());
+        assert_eq!(
+            pool.delete_by_expiry("sessions").await.unwrap(),
+            vec!["expired".to_owned()]
+        );
+        assert_eq!(pool.count("sessions").await.unwrap(), 1);
+
+        // A file that can not be read is skipped instead of stopping the sweep.
+        std::fs::write(path.join("sessions").join("broken.json"), "not json").unwrap();
+        pool.store("expired", "{}", expires, "sessions")
+            .await
+            .unwrap();
+        assert_eq!(
+            pool.delete_by_expiry("sessions").await.unwrap(),
+            vec!["expired".to_owned()]
+        );
+        assert_eq!(pool.count("sessions").await.unwrap(), 2);
+
+        pool.delete_all("sessions").await.unwrap();
+        assert_eq!(pool.count("sessions").await.unwrap(), 0);
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[cfg(feature = "mongo")]
+    #[tokio::test]
+    async fn mongo_pool() {
+        let client = mongodb::Client::with_uri_str("mongodb://localhost:27017/axum_session_test")
+            .await
+            .unwrap();
+        let session_store = SessionStore::<SessionMongoPool>::new(
+            Some(client.clone().into()),
+            SessionConfig::new(),
+        )
+        .await
+        .unwrap();
+
+        let indexes = client
+            .default_database()
+            .unwrap()
+            .collection::<mongodb::bson::Document>("sessions")
+            .list_index_names()
+            .await
+            .unwrap();
+        assert!(indexes.contains(&"session_expire_at".to_string()));
+
+        let app = session_routes(session_store.clone());
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+        // Unload the memory store so the session must come back from Mongodb.
+        session_store.clone().clear().await;
+
+        let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "42");
+    }
+
+    #[tokio::test]
+    async fn memory_pool_suite() {
+        database_pool_suite(MemoryPool::default()).await;
+    }
+
+    #[tokio::test]
+    async fn file_pool_suite() {
+        let path = std::env::temp_dir().join(format!("axum_session_{}", uuid::Uuid::new_v4()));
+        database_pool_suite(SessionFilePool::new(&path)).await;
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[cfg(any(feature = "sqlite-rustls", feature = "sqlite-native"))]
+    #[tokio::test]
+    async fn sqlite_pool_suite() {
+        // A single connection so every query sees the same in memory database.
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        database_pool_suite(SessionSqlitePool::from(pool)).await;
+    }
+
+    #[tokio::test]
+    async fn postgres_pool_suite() {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect_with(
+                PgConnectOptions::new()
+                    .database("postgres")
+                    .username("postgres")
+                    .password("password")
+                    .host("localhost")
+                    .port(5432),
+            )
+            .await
+            .unwrap();
+        database_pool_suite(SessionPgPool::from(pool)).await;
+    }
+
+    #[tokio::test]
+    async fn postgres_invalidation() {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect_with(
+                PgConnectOptions::new()
+                    .database("postgres")
+                    .username("postgres")
+                    .password("password")
+                    .host("localhost")
+                    .port(5432),
+            )
+            .await
+            .unwrap();
+        let pool = SessionPgPool::from(pool);
+
+        // Every listener on the table's channel gets the message, including our own.
+        let mut receiver = pool
+            .subscribe_invalidations("invalidation_sessions")
+            .await
+            .unwrap()
+            .unwrap();
+        pool.publish_invalidation("instance:id", "invalidation_sessions")
+            .await
+            .unwrap();
+
+        let message = tokio::time::timeout(std::time::Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap();
+        assert_eq!(message.as_deref(), Some("instance:id"));
+    }
+
+    #[cfg(any(feature = "sqlite-rustls", feature = "sqlite-native"))]
+    #[tokio::test]
+    async fn sqlite_initiate() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let session_store =
+            SessionStore::<SessionSqlitePool>::new(Some(pool.clone().into()), SessionConfig::new())
+                .await
+                .unwrap();
+
+        // The table is created again after it was removed and initiating twice is harmless.
+        sqlx::query("DROP TABLE sessions")
+            .execute(&pool)
+            .await
+            .unwrap();
+        session_store.initiate().await.unwrap();
+        session_store.initiate().await.unwrap();
+
+        let mut session = SessionData::new(uuid::Uuid::new_v4(), true, &session_store.config);
+        session.set("value", 42);
+        session_store.store_session(&session).await.unwrap();
+
+        let loaded = session_store
+            .load_session(session.id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.get::<i32>("value"), Some(42));
+    }
+
+    #[cfg(any(feature = "postgres-rustls", feature = "postgres-native"))]
+    #[tokio::test]
+    async fn invalid_table_name() {
+        for table_name in [
+            "sessions; DROP TABLE users",
+            "1sessions",
+            "a.b.c",
+            "auth.",
+            "my-sessions",
+            "",
+        ] {
+            assert!(!is_sql_table_name(table_name));
+
+            // The name is checked before the database is used so a lazy pool never connects.
+            let pool = PgPoolOptions::new()
+                .connect_lazy("postgres://localhost")
+                .unwrap();
+            let result = SessionStore::<SessionPgPool>::new(
+                Some(pool.into()),
+                SessionConfig::new().with_table_name(table_name),
+            )
+            .await;
+            assert!(matches!(result, Err(SessionError::InvalidTableName(_))));
+        }
+
+        for table_name in ["my_sessions", "auth.sessions", "_sessions"] {
+            assert!(is_sql_table_name(table_name));
+        }
+    }
+
+    #[tokio::test]
+    async fn other_databases_table_name() {
+        // Databases that do not place the name within a query, like Redis key prefixes, allow any name.
+        for table_name in ["myapp:sessions", "my-sessions", ""] {
+            assert!(SessionStore::<MemoryPool>::new(
+                Some(MemoryPool::default()),
+                SessionConfig::new().with_table_name(table_name),
+            )
+            .await
+            .is_ok());
+        }
+    }
+
+    #[cfg(any(feature = "sqlite-rustls", feature = "sqlite-native"))]
+    #[tokio::test]
+    async fn custom_table_name() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let session_store = SessionStore::<SessionSqlitePool>::new(
+            Some(pool.clone().into()),
+            SessionConfig::new().with_table_name("my_sessions"),
+        )
+        .await
+        .unwrap();
+        let app = session_routes(session_store);
+
+        app.oneshot(request("/set", None)).await.unwrap();
+
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM my_sessions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[cfg(feature = "rest_mode")]
+    #[tokio::test]
+    async fn rest_mode_headers() {
+        let session_store = SessionStore::<SessionNullPool>::new(
+            None,
+            SessionConfig::new().with_bearer_token(true),
+        )
+        .await
+        .unwrap();
+        let app = session_routes(session_store);
+        let header_request = |uri: &str, name: header::HeaderName, value: &str| {
+            Request::builder()
+                .uri(uri)
+                .header(name, value)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        assert!(response.headers().get(header::SET_COOKIE).is_none());
+        let id = response.headers()["session"].to_str().unwrap().to_owned();
+
+        let response = app
+            .clone()
+            .oneshot(header_request(
+                "/get",
+                header::HeaderName::from_static("session"),
+                &id,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["session"], id.as_str());
+        assert_eq!(body_string(response).await, "42");
+
+        for scheme in ["Bearer", "bearer"] {
+            let response = app
+                .clone()
+                .oneshot(header_request(
+                    "/get",
+                    header::AUTHORIZATION,
+                    &format!("{} {}", scheme, id),
+                ))
+                .await
+                .unwrap();
+            assert_eq!(response.headers()["session"], id.as_str());
+            assert_eq!(body_string(response).await, "42");
+        }
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn purge_expired() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = session_routes(session_store.clone());
+
+        app.clone().oneshot(request("/set", None)).await.unwrap();
+        let response = app.oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        assert_eq!(session_store.inner.len(), 2);
+        assert_eq!(pool.sessions.lock().unwrap().len(), 2);
+
+        // Expire every session but the last one.
+        let expired = chrono::Utc::now() - chrono::Duration::try_seconds(1).unwrap();
+        for mut session_data in session_store.inner.iter_mut() {
+            if !cookie.ends_with(session_data.key().as_str()) {
+                session_data.expires = expired;
+                session_data.autoremove = expired;
+                pool.sessions
+                    .lock()
+                    .unwrap()
+                    .get_mut(session_data.key())
+                    .unwrap()
+                    .1 = expired.timestamp();
+            }
+        }
+
+        session_store.purge_expired().await.unwrap();
+        assert_eq!(session_store.inner.len(), 1);
+        assert_eq!(pool.sessions.lock().unwrap().len(), 1);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn sweep_on_returning_session() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = session_routes(session_store.clone());
+
+        app.clone().oneshot(request("/set", None)).await.unwrap();
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+        // Expire the other session and force both sweeps to run on the next request.
+        let expired = chrono::Utc::now() - chrono::Duration::try_seconds(1).unwrap();
+        for mut session_data in session_store.inner.iter_mut() {
+            if !cookie.ends_with(session_data.key().as_str()) {
+                session_data.expires = expired;
+                session_data.autoremove = expired;
+                pool.sessions
+                    .lock()
+                    .unwrap()
+                    .get_mut(session_data.key())
+                    .unwrap()
+                    .1 = expired.timestamp();
+            }
+        }
+        {
+            let mut timers = session_store.timers.write().await;
+            timers.last_expiry_sweep = expired;
+            timers.last_database_expiry_sweep = expired;
+        }
+
+        // Only the returning session makes a request, no new session id is created.
+        let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "42");
+        assert_eq!(session_store.inner.len(), 1);
+        assert_eq!(pool.sessions.lock().unwrap().len(), 1);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn invalidation() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new().with_invalidation(true);
+        let first_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config.clone())
+            .await
+            .unwrap();
+        let second_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let first = session_routes(first_store.clone());
+        let second = session_routes(second_store.clone());
+
+        let response = first.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+
+        let response = second
+            .clone()
+            .oneshot(request("/get", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "42");
+        assert!(second_store.inner.contains_key(&id));
+
+        // A session still used by a request on the second server is reloaded once it is unused.
+        second_store.inner.get_mut(&id).unwrap().requests = 1;
+        first
+            .clone()
+            .oneshot(request("/set", Some(&cookie)))
+            .await
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(second_store.inner.get(&id).unwrap().invalidated);
+        second_store.inner.get_mut(&id).unwrap().requests = 0;
+
+        let loads = pool.loads.load(Ordering::SeqCst);
+        let response = second
+            .clone()
+            .oneshot(request("/get", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "42");
+        assert_eq!(pool.loads.load(Ordering::SeqCst), loads + 1);
+
+        // Destroying the session on the first server drops the cached copy on the second.
+        first
+            .oneshot(request("/destroy", Some(&cookie)))
+            .await
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!second_store.inner.contains_key(&id));
+
+        let response = second
+            .oneshot(request("/get", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "0");
+    }
+
+    #[tokio::test]
+    async fn max_session_size() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new().with_max_session_size(Some(1024));
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config.clone())
+            .await
+            .unwrap();
+
+        let mut session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        session.set("value", "a".repeat(100));
+        session_store.store_session(&session).await.unwrap();
+
+        session.set("value", "a".repeat(2048));
+        assert!(matches!(
+            session_store.store_session(&session).await,
+            Err(SessionError::SessionTooLarge(_, 1024))
+        ));
+        // The oversized data was not written.
+        let stored = session_store
+            .load_session(session.id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.get::<String>("value"), Some("a".repeat(100)));
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn touch() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = Router::new()
+            .route(
+                "/set",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set("value", 42);
+                }),
+            )
+            .route(
+                "/touch",
+                get(|session: Session<MemoryPool>| async move {
+                    session.touch();
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+        assert_eq!(pool.stores.load(Ordering::SeqCst), 1);
+
+        // Move the expiration back so the touch has something to extend.
+        let old = chrono::Utc::now() + chrono::Duration::try_minutes(1).unwrap();
+        session_store.inner.get_mut(&id).unwrap().expires = old;
+        pool.sessions.lock().unwrap().get_mut(&id).unwrap().1 = old.timestamp();
+
+        app.oneshot(request("/touch", Some(&cookie))).await.unwrap();
+        assert_eq!(pool.stores.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.touches.load(Ordering::SeqCst), 1);
+
+        let expires = session_store.inner.get(&id).unwrap().expires;
+        assert!(expires > old);
+        assert_eq!(pool.sessions.lock().unwrap()[&id].1, expires.timestamp());
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn load_error_policy() {
+        for policy in [
+            LoadErrorPolicy::NewSession,
+            LoadErrorPolicy::Fail,
+            LoadErrorPolicy::MemoryOnly,
+        ] {
+            let pool = MemoryPool::default();
+            let config = SessionConfig::new().with_load_error_policy(policy);
+            let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+                .await
+                .unwrap();
+            let app = session_routes(session_store.clone());
+
+            let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+            let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+            let id = cookie.trim_start_matches("session=").to_owned();
+
+            // A session missing from the database is not an error for any policy.
+            let response = app.clone().oneshot(request("/get", None)).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK);
+
+            session_store.clone().clear().await;
+            pool.fail_loads.store(true, Ordering::SeqCst);
+            let response = app
+                .clone()
+                .oneshot(request("/get", Some(&cookie)))
+                .await
+                .unwrap();
+
+            match policy {
+                LoadErrorPolicy::NewSession => {
+                    assert_eq!(body_string(response).await, "0");
+                    // The empty session was saved over the stored one.
+                    pool.fail_loads.store(false, Ordering::SeqCst);
+                    session_store.clone().clear().await;
+                    let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+                    assert_eq!(body_string(response).await, "0");
+                }
+                LoadErrorPolicy::Fail => {
+                    assert_eq!(
+                        response.status(),
+                        axum::http::StatusCode::SERVICE_UNAVAILABLE
+                    );
+                    pool.fail_loads.store(false, Ordering::SeqCst);
+                    let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+                    assert_eq!(body_string(response).await, "42");
+                }
+                LoadErrorPolicy::MemoryOnly => {
+                    assert_eq!(body_string(response).await, "0");
+                    assert!(pool.sessions.lock().unwrap()[&id].0.contains("42"));
+                    // The stored session is loaded again once the database is back.
+                    pool.fail_loads.store(false, Ordering::SeqCst);
+                    let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+                    assert_eq!(body_string(response).await, "42");
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn encrypted_session_data() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new().with_database_key(Key::generate());
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config.clone())
+            .await
+            .unwrap();
+
+        let mut session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        session.set("secret", "plaintext value");
+        session_store.store_session(&session).await.unwrap();
+
+        let stored = pool.sessions.lock().unwrap()[&session.id.to_string()]
+            .0
+            .clone();
+        assert!(!stored.contains("plaintext value"));
+        assert!(serde_json::from_str::<serde_json::Value>(&stored).is_err());
+
+        let loaded = session_store
+            .load_session(session.id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            loaded.get::<String>("secret"),
+            Some("plaintext value".to_owned())
+        );
+
+        // Another key can not read the stored session.
+        let other_store = SessionStore::<MemoryPool>::new(
+            Some(pool.clone()),
+            SessionConfig::new().with_database_key(Key::generate()),
+        )
+        .await
+        .unwrap();
+        assert!(other_store
+            .load_session(session.id.to_string())
+            .await
+            .is_err());
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn max_memory_sessions() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new()
+            .with_max_memory_sessions(Some(2))
+            .with_eviction_policy(EvictionPolicy::LeastRecentlyUsed);
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let mut cookies = Vec::new();
+
+        for _ in 0..2 {
+            let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+            cookies.push(cookie_pair(&set_cookie(&response, "session").unwrap()));
+        }
+
+        // Sessions in memory are served without reading the database.
+        let response = app
+            .clone()
+            .oneshot(request("/get", Some(&cookies[0])))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "42");
+        assert_eq!(pool.loads.load(Ordering::SeqCst), 0);
+
+        // The second session is now the least recently used so it is unloaded.
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        cookies.push(cookie_pair(&set_cookie(&response, "session").unwrap()));
+        assert_eq!(session_store.inner.len(), 2);
+
+        let evicted = cookies[1].trim_start_matches("session=");
+        assert!(!session_store.inner.contains_key(evicted));
+        assert!(session_store
+            .inner
+            .contains_key(cookies[0].trim_start_matches("session=")));
+
+        // It stays in the database and is loaded again when used.
+        let response = app
+            .clone()
+            .oneshot(request("/get", Some(&cookies[1])))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "42");
+        assert_eq!(pool.loads.load(Ordering::SeqCst), 1);
+        assert_eq!(session_store.inner.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn max_memory_sessions_keeps_unstorable() {
+        let config = SessionConfig::new()
+            .with_mode(SessionMode::OptIn)
+            .with_max_memory_sessions(Some(2));
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(MemoryPool::default()), config.clone())
+                .await
+                .unwrap();
+
+        let mut unstored = Vec::new();
+        for _ in 0..3 {
+            let mut session = SessionData::new(uuid::Uuid::new_v4(), false, &config);
+            session.requests = 0;
+            unstored.push(session.id.to_string());
+            session_store.inner.insert(session.id.to_string(), session);
+        }
+
+        // Sessions that can not be stored are only in memory so they are not unloaded.
+        assert_eq!(session_store.evict_memory().await, 0);
+        assert_eq!(session_store.inner.len(), 3);
+
+        let mut session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        session.requests = 0;
+        session_store.inner.insert(session.id.to_string(), session);
+        assert_eq!(session_store.evict_memory().await, 1);
+        assert!(unstored
+            .iter()
+            .all(|id| session_store.inner.contains_key(id)));
+    }
+
+    #[tokio::test]
+    async fn eviction_batch() {
+        let config = SessionConfig::new().with_max_memory_sessions(Some(20));
+        let session_store = SessionStore::<MemoryPool>::new(Some(MemoryPool::default()), config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+
+        for _ in 0..20 {
+            app.clone().oneshot(request("/set", None)).await.unwrap();
+        }
+        assert_eq!(session_store.inner.len(), 20);
+
+        // Going over the cap unloads down to 90% of it so the next new sessions do not scan again.
+        app.clone().oneshot(request("/set", None)).await.unwrap();
+        assert_eq!(session_store.inner.len(), 18);
+    }
+
+    #[tokio::test]
+    async fn tiered_store() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new()
+            .with_max_memory_sessions(Some(2))
+            .with_eviction_policy(EvictionPolicy::LeastRecentlyUsed);
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config.clone())
+            .await
+            .unwrap();
+        let tiered = TieredStore::new(session_store.clone());
+
+        let mut ids = Vec::new();
+
+        for value in 0..2 {
+            let mut session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+            session.set("value", value);
+            tiered.store(&session).await.unwrap();
+            ids.push(session.id.to_string());
+        }
+
+        // Writes go through to the database and reads are served from memory.
+        assert_eq!(pool.stores.load(Ordering::SeqCst), 2);
+        let session = tiered.load(&ids[0]).await.unwrap().unwrap();
+        assert_eq!(session.get::<i32>("value"), Some(0));
+        assert_eq!(pool.loads.load(Ordering::SeqCst), 0);
+
+        // The second session is now the least recently used so storing a third unloads it.
+        let session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        tiered.store(&session).await.unwrap();
+        assert_eq!(session_store.inner.len(), 2);
+        assert!(!session_store.inner.contains_key(&ids[1]));
+
+        // A miss loads from the database and keeps the session in memory.
+        let session = tiered.load(&ids[1]).await.unwrap().unwrap();
+        assert_eq!(session.get::<i32>("value"), Some(1));
+        assert!(session.validate(&config));
+        assert_eq!(pool.loads.load(Ordering::SeqCst), 1);
+        tiered.load(&ids[1]).await.unwrap().unwrap();
+        assert_eq!(pool.loads.load(Ordering::SeqCst), 1);
+
+        assert!(tiered
+            .load(&uuid::Uuid::new_v4().to_string())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn tiered_store_expired_cache() {
+        let (config, now) = fake_clock(SessionConfig::new());
+        let pool = MemoryPool::default();
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config.clone())
+            .await
+            .unwrap();
+        let tiered = TieredStore::new(session_store.clone());
+
+        let mut session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        session.set("value", 42);
+        tiered.store(&session).await.unwrap();
+        let id = session.id.to_string();
+
+        // Another server extends the session after the copy in memory expired.
+        *now.lock().unwrap() += config.lifespan + chrono::Duration::try_minutes(1).unwrap();
+        let expires = config.now() + config.lifespan;
+        pool.sessions.lock().unwrap().get_mut(&id).unwrap().1 = expires.timestamp();
+
+        let session = tiered.load(&id).await.unwrap().unwrap();
+        assert!(session.validate(&config));
+        assert_eq!(session.get::<i32>("value"), Some(42));
+        assert_eq!(pool.loads.load(Ordering::SeqCst), 1);
+        assert!(session_store.inner.get(&id).unwrap().validate(&config));
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn eviction_policy() {
+        let pool = MemoryPool::default();
+        // Soonest autoremove is the default policy.
+        let config = SessionConfig::new().with_max_memory_sessions(Some(2));
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let mut cookies = Vec::new();
+
+        for _ in 0..2 {
+            let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+            cookies.push(cookie_pair(&set_cookie(&response, "session").unwrap()));
+        }
+
+        // The first session was used last but leaves memory the soonest.
+        app.clone()
+            .oneshot(request("/get", Some(&cookies[0])))
+            .await
+            .unwrap();
+        let first = cookies[0].trim_start_matches("session=").to_owned();
+        session_store.inner.get_mut(&first).unwrap().autoremove =
+            chrono::Utc::now() + chrono::Duration::try_minutes(1).unwrap();
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        cookies.push(cookie_pair(&set_cookie(&response, "session").unwrap()));
+        assert_eq!(session_store.inner.len(), 2);
+        assert!(!session_store.inner.contains_key(&first));
+
+        let response = app
+            .clone()
+            .oneshot(request("/get", Some(&cookies[0])))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "42");
+        assert_eq!(pool.loads.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn remember_me() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new().with_max_age(chrono::Duration::try_days(1));
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set("value", 42);
+                }),
+            )
+            .route(
+                "/remember",
+                get(|session: Session<MemoryPool>| async move {
+                    session.remember(chrono::Duration::try_days(30).unwrap());
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+        let max_age = |response: &axum::response::Response| {
+            cookie::Cookie::parse(set_cookie(response, "session").unwrap())
+                .unwrap()
+                .max_age()
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("/", None)).await.unwrap();
+        assert_eq!(max_age(&response), cookie::time::Duration::days(1));
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+
+        let response = app
+            .clone()
+            .oneshot(request("/remember", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(max_age(&response), cookie::time::Duration::days(30));
+
+        let outlives_cookie =
+            (chrono::Utc::now() + chrono::Duration::try_days(29).unwrap()).timestamp();
+        assert!(pool.sessions.lock().unwrap()[&id].1 > outlives_cookie);
+
+        // The session stays remembered when it is reloaded from the database.
+        session_store.inner.remove(&id);
+        let response = app.oneshot(request("/", Some(&cookie))).await.unwrap();
+        assert_eq!(max_age(&response), cookie::time::Duration::days(30));
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn consistent_cookie_attributes() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new()
+            .with_cookie_domain("example.com")
+            .with_cookie_same_site(SameSite::Strict);
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+        let attributes = |response: &axum::response::Response| {
+            let cookie = cookie::Cookie::parse(set_cookie(response, "session").unwrap()).unwrap();
+            (
+                cookie.path().map(str::to_owned),
+                cookie.domain().map(str::to_owned),
+                cookie.secure(),
+                cookie.http_only(),
+                cookie.same_site(),
+                cookie.max_age(),
+            )
+        };
+
+        // A new session.
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let created = attributes(&response);
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+
+        // A session found in memory.
+        let response = app
+            .clone()
+            .oneshot(request("/get", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(attributes(&response), created);
+
+        // A session loaded from the database.
+        session_store.inner.remove(&id);
+        let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+        assert_eq!(attributes(&response), created);
+        assert_eq!(pool.loads.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn database_load_expiry() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = session_handlers::<MemoryPool>()
+            .route(
+                "/expires",
+                get(|session: Session<MemoryPool>| async move {
+                    session.expires_at().unwrap().timestamp().to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store.clone()));
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+
+        // A session loaded from the database keeps the expiration stored with it.
+        let expires = (chrono::Utc::now() + chrono::Duration::try_minutes(10).unwrap()).timestamp();
+        pool.sessions.lock().unwrap().get_mut(&id).unwrap().1 = expires;
+        session_store.clone().clear().await;
+
+        let response = app
+            .oneshot(request("/expires", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, expires.to_string());
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn database_miss_lifespan() {
+        let pool = MemoryPool::default();
+        let lifespan = chrono::Duration::try_hours(2).unwrap();
+        let config = SessionConfig::new().with_lifetime(lifespan);
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+        let near_lifespan = |expires: chrono::DateTime<chrono::Utc>| {
+            let diff = expires - (chrono::Utc::now() + lifespan);
+            diff.num_seconds().abs() <= 5
+        };
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+        assert!(near_lifespan(session_store.inner.get(&id).unwrap().expires));
+
+        // The session is missing from memory and the database so it is recreated with a new id.
+        session_store.inner.remove(&id);
+        pool.sessions.lock().unwrap().remove(&id);
+
+        let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+        assert_eq!(body_string(response).await, "0");
+        assert!(near_lifespan(session_store.inner.get(&id).unwrap().expires));
+        assert!(near_lifespan(
+            chrono::DateTime::from_timestamp(pool.sessions.lock().unwrap()[&id].1, 0).unwrap()
+        ));
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn extend_on_request() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new().with_extend_on_request(false);
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+        let expires = session_store.inner.get(&id).unwrap().expires;
+
+        for _ in 0..2 {
+            app.clone()
+                .oneshot(request("/set", Some(&cookie)))
+                .await
+                .unwrap();
+            assert_eq!(session_store.inner.get(&id).unwrap().expires, expires);
+        }
+
+        assert_eq!(pool.stores.load(Ordering::SeqCst), 3);
+        assert_eq!(pool.sessions.lock().unwrap()[&id].1, expires.timestamp());
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn extend_on_request_fixed_window() {
+        let pool = MemoryPool::default();
+        let (config, now) = fake_clock(
+            SessionConfig::new()
+                .with_extend_on_request(false)
+                .with_lifetime(chrono::Duration::try_hours(1).unwrap())
+                .with_memory_lifetime(chrono::Duration::try_days(1).unwrap()),
+        );
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        let id = cookie.trim_start_matches("session=").to_owned();
+        let expires = pool.sessions.lock().unwrap()[&id].1;
+
+        // Loading the Session from the database keeps its expiration.
+        *now.lock().unwrap() += chrono::Duration::try_minutes(30).unwrap();
+        session_store.clone().clear().await;
+        app.clone()
+            .oneshot(request("/set", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(pool.sessions.lock().unwrap()[&id].1, expires);
+
+        // The window ends even though the Session is still kept in memory.
+        *now.lock().unwrap() += chrono::Duration::try_minutes(45).unwrap();
+        let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "0");
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn flush_all() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let mut ids = Vec::new();
+
+        for _ in 0..2 {
+            let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+            let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+            ids.push(cookie.trim_start_matches("session=").to_owned());
+        }
+
+        // Change the sessions in memory only, as a request still running at shutdown would.
+        for id in &ids {
+            session_store.set(id.clone(), "value", 7);
+        }
+
+        assert_eq!(session_store.flush_all().await.unwrap(), 2);
+        session_store.clone().clear().await;
+
+        for id in &ids {
+            let session = session_store
+                .load_session(id.clone())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(session.get::<i32>("value"), Some(7));
+        }
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn session_errors() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let id = uuid::Uuid::new_v4().to_string();
+
+        // Stored data that is not a session.
+        pool.sessions
+            .lock()
+            .unwrap()
+            .insert(id.clone(), ("not json".to_owned(), i64::MAX));
+        assert!(matches!(
+            session_store.load_session(id.clone()).await,
+            Err(SessionError::SerdeJson(_))
+        ));
+
+        // The database failing is passed on as its own error.
+        pool.fail_loads.store(true, Ordering::SeqCst);
+        assert!(matches!(
+            session_store.load_session(id.clone()).await,
+            Err(SessionError::GenericSelectError(_))
+        ));
+
+        // Ids that are not a Uuid are never loaded.
+        pool.fail_loads.store(false, Ordering::SeqCst);
+        assert!(session_store
+            .load_session("not-a-uuid".to_owned())
+            .await
+            .unwrap()
+            .is_none());
+
+        // A negative max age expires the cookie instead of panicking.
+        let session_store = SessionStore::<SessionNullPool>::new(
+            None,
+            SessionConfig::new().with_max_age(chrono::Duration::try_days(-1)),
+        )
+        .await
+        .unwrap();
+        let response = session_routes(session_store)
+            .oneshot(request("/set", None))
+            .await
+            .unwrap();
+        let cookie = cookie::Cookie::parse(set_cookie(&response, "session").unwrap()).unwrap();
+        assert_eq!(cookie.max_age(), Some(cookie::time::Duration::ZERO));
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn multiple_stores() {
+        let store = |name: &'static str| async move {
+            let config = SessionConfig::new()
+                .with_session_name(name)
+                .with_store_name(format!("{}_store", name));
+            SessionStore::<MemoryPool>::new(Some(MemoryPool::default()), config)
+                .await
+                .unwrap()
+        };
+        let admin_store = store("admin_session").await;
+        let user_store = store("user_session").await;
+        let app = Router::new()
+            .nest("/admin", session_routes(admin_store.clone()))
+            .nest("/user", session_routes(user_store.clone()));
+
+        let response = app
+            .clone()
+            .oneshot(request("/admin/set", None))
+            .await
+            .unwrap();
+        assert!(set_cookie(&response, "user_session").is_none());
+        let admin_cookie = cookie_pair(&set_cookie(&response, "admin_session").unwrap());
+
+        // The user store does not see the admin session.
+        let response = app
+            .clone()
+            .oneshot(request("/user/get", Some(&admin_cookie)))
+            .await
+            .unwrap();
+        assert!(set_cookie(&response, "admin_session").is_none());
+        let user_cookie = cookie_pair(&set_cookie(&response, "user_session").unwrap());
+        assert_eq!(body_string(response).await, "0");
+
+        let cookies = format!("{}; {}", admin_cookie, user_cookie);
+        let response = app
+            .clone()
+            .oneshot(request("/admin/get", Some(&cookies)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "42");
+        assert_eq!(admin_store.inner.len(), 1);
+        assert_eq!(user_store.inner.len(), 1);
+
+        // Destroying the admin session leaves the user session alone.
+        app.clone()
+            .oneshot(request("/admin/destroy", Some(&cookies)))
+            .await
+            .unwrap();
+        assert_eq!(admin_store.inner.len(), 0);
+        assert_eq!(user_store.inner.len(), 1);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn data_snapshot() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = Router::new()
+            .route(
+                "/",
+                get(|session: Session<MemoryPool>| async move {
+                    session.set("count", 1);
+                    session.set("name", "admin");
+                    let snapshot = session.data_snapshot();
+
+                    session.set("count", 2);
+                    session.remove("name");
+                    assert_eq!(snapshot.len(), 2);
+                    assert_eq!(snapshot["count"], "1");
+                    assert_eq!(snapshot["name"], r#""admin""#);
+                    assert_eq!(session.data_snapshot().len(), 1);
+                }),
+            )
+            .route(
+                "/snapshot",
+                get(|session: Session<MemoryPool>| async move {
+                    session.data_snapshot().len().to_string()
+                }),
+            )
+            .layer(SessionLayer::new(session_store));
+
+        let response = app.clone().oneshot(request("/", None)).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        assert_eq!(pool.stores.load(Ordering::SeqCst), 1);
+
+        // Taking a snapshot does not mark the session for saving.
+        let response = app
+            .oneshot(request("/snapshot", Some(&cookie)))
+            .await
+            .unwrap();
+        assert_eq!(body_string(response).await, "1");
+        assert_eq!(pool.stores.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn tower_service_builder() {
+        let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+            .await
+            .unwrap();
+        let service: SessionService<_, SessionNullPool> = tower::ServiceBuilder::new()
+            .layer(SessionLayer::new(session_store.clone()))
+            .service(tower::service_fn(|req: Request<Body>| async move {
+                let session = req
+                    .extensions()
+                    .get::<Session<SessionNullPool>>()
+                    .cloned()
+                    .unwrap();
+                let count = session.increment("count", 1);
+                Ok::<_, std::convert::Infallible>(axum::response::Response::new(Body::from(
+                    count.to_string(),
+                )))
+            }));
+        let service = tower::ServiceBuilder::new()
+            .map_response(|mut response: axum::response::Response| {
+                response
+                    .headers_mut()
+                    .insert("x-outer", header::HeaderValue::from_static("1"));
+                response
+            })
+            .service(service);
+
+        let response = service.clone().oneshot(request("/", None)).await.unwrap();
+        assert_eq!(response.headers()["x-outer"], "1");
+        let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+        assert_eq!(body_string(response).await, "1");
+
+        let response = service.oneshot(request("/", Some(&cookie))).await.unwrap();
+        assert_eq!(body_string(response).await, "2");
+        assert_eq!(session_store.inner.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn store_and_load_hooks() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new()
+            .with_on_store(|data| data.remove("secret"))
+            .with_on_load(|data| {
+                if !data.contains_key("schema") {
+                    data.set("schema", 2);
+                }
+            });
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config.clone())
+            .await
+            .unwrap();
+
+        let mut session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        session.set("value", 42);
+        session.set("secret", "hunter2");
+        session_store.store_session(&session).await.unwrap();
+
+        // The hook only changes what is saved.
+        assert!(session.contains_key("secret"));
+        let stored = pool.sessions.lock().unwrap()[&session.id.to_string()]
+            .0
+            .clone();
+        assert!(!stored.contains("hunter2"));
+
+        let loaded = session_store
+            .load_session(session.id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.get::<i32>("schema"), Some(2));
+        assert_eq!(loaded.get::<i32>("value"), Some(42));
+        assert!(!loaded.contains_key("secret"));
+    }
+
+    #[cfg(not(feature = "rest_mode"))]
+    #[tokio::test]
+    async fn active_sessions() {
+        let pool = MemoryPool::default();
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        let app = session_routes(session_store.clone());
+
+        let mut ids = Vec::new();
+
+        for _ in 0..2 {
+            let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+            let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+            ids.push(cookie.trim_start_matches("session=").to_owned());
+        }
+
+        let mut active = session_store.active_session_ids();
+        active.sort();
+        ids.sort();
+        assert_eq!(active, ids);
+        assert_eq!(session_store.session_count(), 2);
+
+        // Sessions whose memory lifespan ended are dropped by the sweep but stay in the database.
+        for mut session in session_store.inner.iter_mut() {
+            session.autoremove = chrono::Utc::now() - chrono::Duration::try_seconds(1).unwrap();
+        }
+        session_store.purge_expired().await.unwrap();
+
+        assert!(session_store.active_session_ids().is_empty());
+        assert_eq!(session_store.session_count(), 0);
+        assert_eq!(session_store.count().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn cleanup_grace() {
+        let pool = MemoryPool::default();
+        let config =
+            SessionConfig::new().with_cleanup_grace(chrono::Duration::try_minutes(5).unwrap());
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config)
+            .await
+            .unwrap();
+        let now = chrono::Utc::now().timestamp();
+
+        {
+            let mut sessions = pool.sessions.lock().unwrap();
+            // Expired a minute ago so it could still be getting extended by a request.
+            sessions.insert("recent".to_owned(), ("{}".to_owned(), now - 60));
+            sessions.insert("old".to_owned(), ("{}".to_owned(), now - 600));
+        }
+
+        assert_eq!(
+            session_store.cleanup().await.unwrap(),
+            vec!["old".to_owned()]
+        );
+        assert!(pool.sessions.lock().unwrap().contains_key("recent"));
+
+        // Without a grace period both would have been removed.
+        let session_store =
+            SessionStore::<MemoryPool>::new(Some(pool.clone()), SessionConfig::new())
+                .await
+                .unwrap();
+        assert_eq!(
+            session_store.cleanup().await.unwrap(),
+            vec!["recent".to_owned()]
+        );
+    }
+
+    #[tokio::test]
+    async fn json_serializer() {
+        let pool = MemoryPool::default();
+        let config = SessionConfig::new().with_serializer(SessionSerializer::Json);
+        let session_store = SessionStore::<MemoryPool>::new(Some(pool.clone()), config.clone())
+            .await
+            .unwrap();
+
+        let mut session = SessionData::new(uuid::Uuid::new_v4(), true, &config);
+        session.set("value", 42);
+        session_store.store_session(&session).await.unwrap();
+        assert!(pool.sessions.lock().unwrap()[&session.id.to_string()]
+            .0
+            .starts_with('{'));
+
+        let loaded = session_store
+            .load_session(session.id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.get::<i32>("value"), Some(42));
+    }
 }
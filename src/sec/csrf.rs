@@ -0,0 +1,23 @@
+use base64::{engine::general_purpose, Engine as _};
+use rand::RngCore;
+
+pub(crate) const TOKEN_LEN: usize = 32;
+
+///Used to generate a random url safe CSRF token.
+pub(crate) fn generate_token() -> String {
+    let mut token = [0u8; TOKEN_LEN];
+    rand::thread_rng().fill_bytes(&mut token);
+    general_purpose::URL_SAFE_NO_PAD.encode(token)
+}
+
+///Used to compare CSRF tokens in constant time so the comparison leaks no timing information.
+pub(crate) fn verify_token(expected: &str, token: &str) -> bool {
+    let (expected, token) = (expected.as_bytes(), token.as_bytes());
+
+    expected.len() == token.len()
+        && expected
+            .iter()
+            .zip(token)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
@@ -0,0 +1,36 @@
+use crate::{SessionData, SessionError, SessionSerializer};
+#[cfg(feature = "messagepack")]
+use base64::{engine::general_purpose, Engine as _};
+
+/// Marks stored Session data as MessagePack. Json always starts with `{` so Session data
+/// stored before the serializer was changed still loads.
+#[cfg(feature = "messagepack")]
+pub(crate) const MESSAGEPACK_PREFIX: &str = "mp:";
+
+///Used to serialize the Session data into the format the database Value is stored in.
+pub(crate) fn serialize(
+    session: &SessionData,
+    serializer: SessionSerializer,
+) -> Result<String, SessionError> {
+    match serializer {
+        SessionSerializer::Json => Ok(serde_json::to_string(session)?),
+        #[cfg(feature = "messagepack")]
+        SessionSerializer::MessagePack => Ok(format!(
+            "{}{}",
+            MESSAGEPACK_PREFIX,
+            general_purpose::STANDARD.encode(rmp_serde::to_vec_named(session)?)
+        )),
+    }
+}
+
+///Used to deserialize the database Values. The format is picked by the Value's marker
+/// and not the configured serializer so either format can be read during a migration.
+pub(crate) fn deserialize(value: &str) -> Result<SessionData, SessionError> {
+    #[cfg(feature = "messagepack")]
+    if let Some(data) = value.strip_prefix(MESSAGEPACK_PREFIX) {
+        let data = general_purpose::STANDARD.decode(data)?;
+        return Ok(rmp_serde::from_slice(&data)?);
+    }
+
+    Ok(serde_json::from_str(value)?)
+}
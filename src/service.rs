@@ -1,11 +1,15 @@
-use crate::{headers::*, DatabasePool, Session, SessionData, SessionError, SessionStore};
-use axum::{response::Response, BoxError};
+use crate::{
+    headers::*, DatabasePool, Session, SessionData, SessionError, SessionEvent, SessionStore,
+};
+use axum::{
+    response::{IntoResponseParts, Response, ResponseParts},
+    BoxError,
+};
 use bytes::Bytes;
-use chrono::Utc;
 #[cfg(feature = "key-store")]
 use fastbloom_rs::Deletable;
 use futures::future::BoxFuture;
-use http::Request;
+use http::{HeaderName, HeaderValue, Request};
 use http_body::Body as HttpBody;
 use std::{
     convert::Infallible,
@@ -14,6 +18,41 @@ use std::{
 };
 use tower_service::Service;
 
+/// The response header the Session's CSRF token is sent in when enabled.
+const CSRF_HEADER: HeaderName = HeaderName::from_static("x-csrf-token");
+
+/// Marks a request or response that the Session should not touch.
+///
+/// When it is in the request's extensions, the SessionLayer passes the request straight
+/// to the inner Service without loading a Session. When it is in the response's extensions,
+/// the Session is neither saved nor has its expiration extended, and no cookies are set.
+/// A Session created for that request is discarded and changes the handler made to an
+/// existing Session are undone. When another request is using the Session at the same time
+/// the changes are kept, as they can not be told apart from that request's changes.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::SkipSession;
+///
+/// async fn health(session: Session<SessionNullPool>) -> (SkipSession, &'static str) {
+///     (SkipSession, "ok")
+/// }
+/// ```
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SkipSession;
+
+impl IntoResponseParts for SkipSession {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        res.extensions_mut().insert(self);
+        Ok(res)
+    }
+}
+
+/// The tower Service created by SessionLayer. It loads the Session before the inner Service
+/// is called and saves it and sets the cookies or headers once the inner Service responds.
 #[derive(Clone)]
 pub struct SessionService<S, T>
 where
@@ -27,16 +66,42 @@ pub(crate) fn trace_error<ResBody>(
     err: SessionError,
     msg: &str,
 ) -> Result<Response<ResBody>, Infallible>
+where
+    ResBody: HttpBody<Data = Bytes> + Default + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    trace_error_status(err, msg, http::StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+pub(crate) fn trace_error_status<ResBody>(
+    err: SessionError,
+    msg: &str,
+    status: http::StatusCode,
+) -> Result<Response<ResBody>, Infallible>
 where
     ResBody: HttpBody<Data = Bytes> + Default + Send + 'static,
     ResBody::Error: Into<BoxError>,
 {
     tracing::error!(err = %err, msg);
     let mut res = Response::default();
-    *res.status_mut() = http::StatusCode::INTERNAL_SERVER_ERROR;
+    *res.status_mut() = status;
     Ok(res)
 }
 
+/// The response for a request refused because its ip is over the new_session_limit.
+pub(crate) fn too_many_new_sessions<ResBody>() -> Response<ResBody>
+where
+    ResBody: HttpBody<Data = Bytes> + Default + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    tracing::warn!(
+        "Too many new Sessions were created from the same ip so the request was refused"
+    );
+    let mut res = Response::default();
+    *res.status_mut() = http::StatusCode::TOO_MANY_REQUESTS;
+    res
+}
+
 impl<S, T, ReqBody, ResBody> Service<Request<ReqBody>> for SessionService<S, T>
 where
     S: Service<Request<ReqBody>, Response = Response<ResBody>, Error = Infallible>
@@ -64,10 +129,20 @@ where
         let mut ready_inner = std::mem::replace(&mut self.inner, not_ready_inner);
 
         Box::pin(async move {
+            if req.extensions().get::<SkipSession>().is_some() {
+                return ready_inner.call(req).await;
+            }
+
             let ip_user_agent = get_ips_hash(&req, &store);
 
             #[cfg(not(feature = "rest_mode"))]
-            let cookies = get_cookies(req.headers());
+            let mut cookies = get_cookies(req.headers());
+
+            #[cfg(not(feature = "rest_mode"))]
+            let from_query = add_query_session_id(&req, &store.config, &mut cookies);
+
+            #[cfg(feature = "rest_mode")]
+            let from_query = false;
 
             #[cfg(not(feature = "rest_mode"))]
             let (session_uuid, storable) =
@@ -80,45 +155,97 @@ where
             let (session_uuid, storable) =
                 get_headers_and_key(&store, headers, &ip_user_agent).await;
 
-            let (mut session, is_new) = match Session::new(store, session_uuid).await {
+            let limit_ip = get_limit_ip(&req, &store.config);
+
+            // Refuse to create more new Sessions for an ip that is over its new_session_limit.
+            if session_uuid.is_none()
+                && !store.config.session_mode.is_manual()
+                && !store.allow_new_session(limit_ip.clone()).await
+            {
+                return Ok(too_many_new_sessions());
+            }
+
+            let (mut session, is_new) = match Session::new(store, session_uuid, storable).await {
                 Ok(v) => v,
                 Err(err) => {
                     return trace_error(err, "failed to generate Session ID");
                 }
             };
+            session.limit_ip = limit_ip.clone();
 
             // Check if the session id exists if not lets check if it exists in the database or generate a new session.
             // If manual mode is enabled then do not check for a Session unless the UUID is not new.
             let check_database: bool = if is_new && !session.store.config.session_mode.is_manual() {
                 let sess = SessionData::new(session.id.0, storable, &session.store.config);
-                session.store.inner.insert(session.id.inner(), sess);
+                session.store.insert_new_session(sess).await;
                 false
             } else if !is_new || !session.store.config.session_mode.is_manual() {
-                !session.store.service_session_data(&session)
+                let hit = session.store.service_session_data(&session);
+                tracing::trace!(session.id = %session.id, hit, "Session memory cache checked");
+                !hit
             } else {
                 false
             };
 
-            if check_database {
-                let mut sess = session
-                    .store
-                    .load_session(session.id.inner())
-                    .await
-                    .ok()
-                    .flatten()
-                    .unwrap_or_else(|| {
-                        tracing::info!(
-                            "Session {} did not exist in Database. So it was Recreated.",
-                            session.id
+            // When lazy loading the handler will load the session using session.load() if it needs it.
+            // Ip and user agent binding need the session so it can not be lazily loaded.
+            let lazy_loading = session.store.is_lazy_loading();
+
+            if check_database && !lazy_loading {
+                match session.store.load_or_create_session(&session).await {
+                    Ok(()) => {}
+                    Err(SessionError::TooManyNewSessions) => return Ok(too_many_new_sessions()),
+                    Err(err) => {
+                        return trace_error_status(
+                            err,
+                            "failed to load session from database",
+                            http::StatusCode::SERVICE_UNAVAILABLE,
                         );
-                        SessionData::new(session.id.0, storable, &session.store.config)
-                    });
-
-                sess.autoremove = Utc::now() + session.store.config.memory.memory_lifespan;
-                sess.store = storable;
-                sess.update = true;
-                sess.requests = 1;
-                session.store.inner.insert(session.id.inner(), sess);
+                    }
+                }
+            }
+
+            // Give the request a new Session if it is from a different ip or user agent than the Session is bound to.
+            if session.store.config.ip_user_agent.is_binding() {
+                let ip_binding = get_ip_binding(&req, &session.store.config);
+                let user_agent_binding = get_user_agent_binding(&req, &session.store.config);
+
+                if !session.store.verify_bindings(
+                    session.id.inner(),
+                    ip_binding.as_deref(),
+                    user_agent_binding.as_deref(),
+                ) {
+                    tracing::warn!(
+                        session.id = %session.id,
+                        "Session was used from a different ip or user agent than it was bound to so a new Session was created"
+                    );
+                    session.store.remove_session_request(session.id.inner());
+
+                    if !session.store.allow_new_session(limit_ip.clone()).await {
+                        return Ok(too_many_new_sessions());
+                    }
+
+                    session = match Session::new(session.store.clone(), None, storable).await {
+                        Ok((session, _)) => session,
+                        Err(err) => {
+                            return trace_error(err, "failed to generate Session ID");
+                        }
+                    };
+
+                    session.limit_ip = limit_ip.clone();
+
+                    let mut sess = SessionData::new(session.id.0, storable, &session.store.config);
+                    sess.ip_binding = ip_binding;
+                    sess.user_agent_binding = user_agent_binding;
+                    session.store.insert_new_session(sess).await;
+                }
+            }
+
+            // A Session ID from a link could have been chosen by someone else so give the Session a new one.
+            if from_query && (lazy_loading || session.store.inner.contains_key(&session.id.inner()))
+            {
+                tracing::debug!(session.id = %session.id, "Session ID from the query parameter will be renewed");
+                session.renew();
             }
 
             let (last_sweep, last_database_sweep) = {
@@ -130,49 +257,23 @@ where
             // let's check if any sessions expired. We don't want to hog memory
             // forever by abandoned sessions (e.g. when a client lost their cookie)
             // throttle by memory lifespan - e.g. sweep every hour
-            let current_time = Utc::now();
+            let current_time = session.store.config.now();
 
             if last_sweep <= current_time && !session.store.config.memory.memory_lifespan.is_zero()
             {
-                tracing::info!("Session id {}: Session Memory Cleaning Started", session.id);
-                // Only unload these from filter if the Client is None as this means no database.
-                // Otherwise only unload from the filter if removed from the Database.
-                #[cfg(feature = "key-store")]
-                if session.store.is_persistent()
-                    && session.store.auto_handles_expiry()
-                    && session.store.config.memory.use_bloom_filters
-                {
-                    let mut filter = session.store.filter.write().await;
-                    session
-                        .store
-                        .inner
-                        .iter()
-                        .filter(|r| r.autoremove < current_time)
-                        .for_each(|r| filter.remove(r.key().as_bytes()));
-                }
-
-                session
-                    .store
-                    .inner
-                    .retain(|_k, v| v.autoremove > current_time);
-
-                session.store.timers.write().await.last_expiry_sweep =
-                    Utc::now() + session.store.config.memory.purge_update;
+                tracing::info!(session.id = %session.id, "Session Memory Cleaning Started");
+                let removed = session.store.sweep_memory().await;
                 tracing::info!(
-                    "Session id {}: Session Memory Cleaning Finished",
-                    session.id
+                    session.id = %session.id,
+                    sweep.removed = removed,
+                    "Session Memory Cleaning Finished"
                 );
             }
 
             // Throttle by database lifespan - e.g. sweep every 6 hours
             if last_database_sweep <= current_time && session.store.is_persistent() {
-                tracing::info!(
-                    "Session id {}: Session Database Cleaning Started",
-                    session.id
-                );
-                //Remove any old keys that expired and Remove them from our loaded filter.
-                #[cfg(feature = "key-store")]
-                let expired = match session.store.cleanup().await {
+                tracing::info!(session.id = %session.id, "Session Database Cleaning Started");
+                let removed = match session.store.sweep_database().await {
                     Ok(v) => v,
                     Err(err) => {
                         return trace_error(
@@ -181,28 +282,10 @@ where
                         );
                     }
                 };
-
-                #[cfg(not(feature = "key-store"))]
-                if let Err(err) = session.store.cleanup().await {
-                    return trace_error(err, "failed to remove expired session's from database");
-                }
-
-                #[cfg(feature = "key-store")]
-                if !session.store.auto_handles_expiry() {
-                    let mut filter = session.store.filter.write().await;
-                    expired.iter().for_each(|id| filter.remove(id.as_bytes()));
-                }
-
-                session
-                    .store
-                    .timers
-                    .write()
-                    .await
-                    .last_database_expiry_sweep =
-                    Utc::now() + session.store.config.database.purge_database_update;
                 tracing::info!(
-                    "Session id {}: Session Database Cleaning Finished",
-                    session.id
+                    session.id = %session.id,
+                    sweep.removed = removed,
+                    "Session Database Cleaning Finished"
                 );
             }
 
@@ -210,8 +293,64 @@ where
             //req.extensions_mut().insert(store.clone());
             req.extensions_mut().insert(session.clone());
 
+            // Kept so a response marked with SkipSession can undo the handler's changes.
+            let snapshot = if session.is_new() {
+                None
+            } else {
+                session
+                    .store
+                    .inner
+                    .get(&session.id.inner())
+                    .map(|data| data.clone())
+            };
+
             let mut response = ready_inner.call(req).await?;
 
+            // The route asked us to leave the Session alone so skip saving and setting cookies.
+            if response.extensions().get::<SkipSession>().is_some() {
+                session.store.pending_changes.remove(&session.id.inner());
+                let loaded = session.store.inner.contains_key(&session.id.inner());
+
+                if loaded {
+                    session.remove_request();
+                }
+
+                if loaded && !session.is_parallel() {
+                    if let Some(mut snapshot) = snapshot {
+                        snapshot.remove_request();
+                        session.store.inner.insert(session.id.inner(), snapshot);
+                    } else {
+                        // New or lazily loaded within this request so nothing in memory needs keeping.
+                        #[cfg(feature = "key-store")]
+                        if session.is_new() && session.store.config.memory.use_bloom_filters {
+                            let mut filter = session.store.filter.write().await;
+                            filter.remove(session.id.inner().as_bytes());
+                        }
+
+                        session.store.inner.remove(&session.id.inner());
+                    }
+                }
+
+                return Ok(response);
+            }
+
+            // The handler changed the Session without loading it so load it now to keep those changes.
+            if lazy_loading && session.store.has_pending_changes(&session.id.inner()) {
+                if let Err(err) = session.store.load_or_create_session(&session).await {
+                    session.store.pending_changes.remove(&session.id.inner());
+
+                    if matches!(err, SessionError::TooManyNewSessions) {
+                        return Ok(too_many_new_sessions());
+                    }
+
+                    return trace_error_status(
+                        err,
+                        "failed to load session from database",
+                        http::StatusCode::SERVICE_UNAVAILABLE,
+                    );
+                }
+            }
+
             let (renew, storable, destroy, loaded) =
                 if let Some(session_data) = session.store.inner.get(&session.id.inner()) {
                     (
@@ -221,7 +360,8 @@ where
                         true,
                     )
                 } else {
-                    (false, false, false, false)
+                    // A lazily loaded session that was never used keeps what the request sent us.
+                    (false, lazy_loading && storable, false, false)
                 };
 
             tracing::trace!(
@@ -265,37 +405,64 @@ where
                 {
                     session_data.id = session_id.0;
                     session_data.renew = false;
+                    session_data.last_rotated = session.store.config.now();
                     session.id = session_id;
                     session.store.inner.insert(session.id.inner(), session_data);
                 }
             }
 
+            // Flash messages the request did not take are removed before saving.
+            if loaded && !destroy {
+                session
+                    .store
+                    .expire_flashes(session.id.inner(), session.request);
+            }
+
+            // Send the CSRF token before saving so a newly created token is saved with the Session.
+            if session.store.config.cookie_and_header.csrf_header && loaded && !destroy {
+                let token = session.store.csrf_token(session.id.inner());
+
+                if let Ok(value) = HeaderValue::from_str(&token) {
+                    response.headers_mut().insert(CSRF_HEADER, value);
+                }
+            }
+
             // Add the Session ID so it can link back to a Session if one exists.
+            // Read only requests neither extend the Session's expiration nor save it.
             if (!session.store.config.session_mode.is_opt_in() || storable)
                 && session.store.is_persistent()
                 && !destroy
+                && !session.is_read_only()
+                && !session.store.load_failed(session.id.inner())
             {
-                let clone_session = if let Some(mut sess) =
-                    session.store.inner.get_mut(&session.id.inner())
-                {
-                    // Check if Database needs to be updated or not. TODO: Make updatable based on a timer for in memory only.
-                    if session.store.config.database.always_save || sess.update || !sess.validate()
-                    {
-                        if sess.longterm {
-                            sess.expires = Utc::now() + session.store.config.max_lifespan;
+                let now = session.store.config.now();
+                let (clone_session, touched) =
+                    if let Some(mut sess) = session.store.inner.get_mut(&session.id.inner()) {
+                        // Check if Database needs to be updated or not. TODO: Make updatable based on a timer for in memory only.
+                        if session.store.config.database.always_save
+                            || sess.update
+                            || !sess.validate_at(now)
+                        {
+                            // Fixed window sessions keep their expiration unless they expired and started over.
+                            if session.store.config.extend_on_request || !sess.validate_at(now) {
+                                sess.set_expires(&session.store.config);
+                            }
+
+                            sess.update = false;
+                            sess.touched = false;
+
+                            (Some(sess.clone()), None)
+                        } else if sess.touched {
+                            // Only the expiration changed so skip saving the data.
+                            sess.touched = false;
+
+                            (None, Some(sess.expires))
                         } else {
-                            sess.expires = Utc::now() + session.store.config.lifespan;
-                        };
-
-                        sess.update = false;
-
-                        Some(sess.clone())
+                            (None, None)
+                        }
                     } else {
-                        None
-                    }
-                } else {
-                    None
-                };
+                        (None, None)
+                    };
 
                 if let Some(sess) = clone_session {
                     if let Err(err) = session.store.store_session(&sess).await {
@@ -304,11 +471,26 @@ where
                         tracing::info!("Session id {}: was saved to the database.", session.id);
                     }
                 }
+
+                if let Some(expires) = touched {
+                    if let Err(err) = session
+                        .store
+                        .touch_session(session.id.inner(), expires)
+                        .await
+                    {
+                        return trace_error(err, "failed to update session expiration in database");
+                    }
+                }
             }
 
+            // Get the cookie's Max-Age before the session might be unloaded from memory.
+            let cookie_max_age = session.store.cookie_max_age(session.id.inner());
+
             //lets tell the system we can unload this request now.
             //If there are still more left the bottom wont unload anything.
-            session.remove_request();
+            if loaded {
+                session.remove_request();
+            }
 
             if ((session.store.config.session_mode.is_opt_in() && !storable) || destroy)
                 && !session.is_parallel()
@@ -330,6 +512,10 @@ where
                         return trace_error(err, "failed to remove session from database");
                     }
                 }
+
+                if destroy {
+                    session.store.emit_event(SessionEvent::Destroyed);
+                }
             }
 
             // We will Deleted the data in memory as it should be stored in the database instead.
@@ -350,6 +536,7 @@ where
                 &ip_user_agent,
                 destroy,
                 storable,
+                cookie_max_age,
             );
 
             Ok(response)
@@ -1,12 +1,21 @@
 use crate::{DatabasePool, SessionData, SessionError, SessionID, SessionStore};
 use async_trait::async_trait;
 use axum::extract::FromRequestParts;
+use chrono::{DateTime, Duration, Utc};
 
 #[cfg(feature = "key-store")]
 use fastbloom_rs::Membership;
 use http::{request::Parts, StatusCode};
-use serde::Serialize;
-use std::fmt::Debug;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 use uuid::Uuid;
 
 /// A Session Store.
@@ -23,10 +32,34 @@ where
     pub(crate) store: SessionStore<T>,
     /// The Sessions current ID for lookng up its store.
     pub(crate) id: SessionID,
+    /// If the request allowed the Session to be stored. Used when lazily loading the data.
+    pub(crate) storable: bool,
+    /// Shared across the request so the Session Layer knows to skip saving the Session.
+    pub(crate) read_only: Arc<AtomicBool>,
+    /// If the Session ID was created for this request.
+    pub(crate) is_new: bool,
+    /// The ip new Sessions created for this request are counted against. Used when lazily loading the data.
+    pub(crate) limit_ip: Option<String>,
+    /// Numbers the request so its flash messages are aged apart from other requests using the Session.
+    pub(crate) request: u64,
 }
 
 /// Adds FromRequestParts<B> for Session
 ///
+/// The Rejection used when a Session can not be found in the request extensions.
+/// This happens when the `SessionLayer` was not added for the route or was created
+/// with a different DatabasePool type than the one being extracted.
+pub(crate) fn missing_session_layer() -> (StatusCode, &'static str) {
+    tracing::error!(
+        "Session missing from the request. Add `SessionLayer` to the Router after the routes that use it, using the same DatabasePool type as the extractor."
+    );
+
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Can't extract Axum `Session`. Is `SessionLayer` enabled for this route with the same DatabasePool type as the extractor?",
+    )
+}
+
 /// Returns the Session from Axums request extensions state.
 #[async_trait]
 impl<T, S> FromRequestParts<S> for Session<T>
@@ -37,10 +70,11 @@ where
     type Rejection = (http::StatusCode, &'static str);
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        parts.extensions.get::<Session<T>>().cloned().ok_or((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Can't extract Axum `Session`. Is `SessionLayer` enabled?",
-        ))
+        parts
+            .extensions
+            .get::<Session<T>>()
+            .cloned()
+            .ok_or_else(missing_session_layer)
     }
 }
 
@@ -52,6 +86,7 @@ where
     pub(crate) async fn new(
         store: SessionStore<S>,
         value: Option<Uuid>,
+        storable: bool,
     ) -> Result<(Self, bool), SessionError> {
         let (id, is_new) = match value {
             Some(v) => (SessionID(v), false),
@@ -71,13 +106,26 @@ where
             }
         }
 
-        Ok((Self { id, store }, is_new))
+        let request = store.request_counter.fetch_add(1, Ordering::Relaxed);
+
+        Ok((
+            Self {
+                id,
+                store,
+                storable,
+                read_only: Arc::new(AtomicBool::new(false)),
+                is_new,
+                limit_ip: None,
+                request,
+            },
+            is_new,
+        ))
     }
 
     #[cfg(feature = "key-store")]
     pub(crate) async fn generate_uuid(store: &SessionStore<S>) -> Result<SessionID, SessionError> {
         loop {
-            let token = Uuid::new_v4();
+            let token = store.config.generate_id();
 
             if (!store.config.memory.use_bloom_filters || store.auto_handles_expiry())
                 && !store.inner.contains_key(&token.to_string())
@@ -108,7 +156,7 @@ where
     #[cfg(not(feature = "key-store"))]
     pub(crate) async fn generate_uuid(store: &SessionStore<S>) -> Result<SessionID, SessionError> {
         loop {
-            let token = Uuid::new_v4();
+            let token = store.config.generate_id();
 
             if !store.inner.contains_key(&token.to_string()) {
                 //This fixes an already used but in database issue.
@@ -148,6 +196,35 @@ where
         self.store.inner.insert(self.id.inner(), sess);
     }
 
+    /// Loads the SessionData from the database if it is not already in memory.
+    ///
+    /// This is only needed when lazy loading is enabled using `with_lazy_loading(true)`,
+    /// otherwise the SessionData is loaded before the request reaches the handler.
+    ///
+    /// Methods that only change the Session, such as `set`, `set_raw`, `remove`, `clear`, `flash`,
+    /// `renew`, `renew_expiry`, `touch`, `update`, `destroy`, `set_owner`, `set_authenticated`,
+    /// `set_longterm`, `set_lifespan`, `remember` and `set_store`, are safe to use before loading
+    /// as their changes are kept until the Session is loaded.
+    /// Methods that read the Session, such as `get`, `get_raw`, `get_remove`, `contains_key`, `keys`,
+    /// `data_snapshot`, `get_or_insert_with`, `increment`, `take_flash`, `csrf_token`, `verify_csrf`,
+    /// `with_data`, `with_locked`, `get_owner`, `is_authenticated` and the expiration getters,
+    /// need the Session loaded first. Used before loading they log an error and act as if the Session is empty.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.load().await;
+    /// let id = session.get("user-id").unwrap_or(0);
+    /// ```
+    ///
+    #[inline]
+    pub async fn load(&self) {
+        if !self.data_exists() {
+            if let Err(err) = self.store.load_or_create_session(self).await {
+                tracing::error!(err = %err, "Session failed to load from the Database.");
+            }
+        }
+    }
+
     /// Checks if the SessionData was created or not.
     ///
     /// # Examples
@@ -185,7 +262,7 @@ where
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.renew();
+    /// session.update();
     /// ```
     ///
     #[inline]
@@ -193,8 +270,82 @@ where
         self.store.update(self.id.inner());
     }
 
+    /// Sets the Current Request to be read only.
+    /// The Session's expiration will not be extended and it will not
+    /// be saved to the database upon the Response Phase of this request.
+    /// Changes made to the Session are still kept in memory and will be saved by the next request that updates it.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_read_only(true);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    /// Checks if the Current Request was set to be read only.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let read_only = session.is_read_only();
+    /// ```
+    ///
+    #[inline]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    /// Checks if the Session was created for this request, such as for a first time visitor
+    /// or a request whose Session was replaced because it did not match its ip or user agent binding.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// if session.is_new() {
+    ///     session.set("welcomed", true);
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn is_new(&self) -> bool {
+        self.is_new
+    }
+
+    /// Extends the Session's expiration by its lifespan and its memory
+    /// lifespan right away. This will also update the database on Response Phase.
+    /// Useful after sensitive actions. Use renew() to change the Session ID instead.
+    /// On a read only request the new expiration is only kept in memory and is saved
+    /// to the database by the next request that is not read only.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.renew_expiry();
+    /// ```
+    ///
+    #[inline]
+    pub fn renew_expiry(&self) {
+        self.store.renew_expiry(self.id.inner());
+    }
+
+    /// Extends the Current Session's expiration and memory lifespan without saving its data.
+    /// Only the new expiration is written to the database on Response Phase, which is
+    /// cheaper than a full save for requests that only read the Session. Like `renew_expiry`
+    /// it is only kept in memory on a read only request until a later request saves it.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.touch();
+    /// ```
+    ///
+    #[inline]
+    pub fn touch(&self) {
+        self.store.touch(self.id.inner());
+    }
+
     /// Sets the Current Session to be Destroyed.
-    /// This will Deleted the Session and Cookies upon Response Phase.
+    /// This will Clear the Sessions data instantly and Delete
+    /// the Session and Cookies upon Response Phase.
     ///
     /// # Examples
     /// ```rust ignore
@@ -219,6 +370,180 @@ where
         self.store.set_longterm(self.id.inner(), longterm);
     }
 
+    /// Sets a custom lifespan for the Current Session overriding the configs lifespan
+    /// and max_lifespan. Setting this to None will return to using the configs lifespans.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_lifespan(Some(Duration::days(30)));
+    /// ```
+    ///
+    #[inline]
+    pub fn set_lifespan(&self, lifespan: Option<Duration>) {
+        self.store.set_lifespan(self.id.inner(), lifespan);
+    }
+
+    /// Remembers the Current Session for the given Duration. Useful for Remember Me setups.
+    /// The Session becomes longterm with the Duration as its lifespan and its cookie is
+    /// reissued with a Max-Age of the Duration instead of the configs max_age.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.remember(Duration::days(30));
+    /// ```
+    ///
+    #[inline]
+    pub fn remember(&self, duration: Duration) {
+        self.store.remember(self.id.inner(), duration);
+    }
+
+    /// Sets the owner of the Current Session such as a user's ID.
+    /// Used to Destroy all of an owner's Sessions with `SessionStore::destroy_all_by_owner`.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_owner(Some("user-1".to_owned()));
+    /// ```
+    ///
+    #[inline]
+    pub fn set_owner(&self, owner: Option<String>) {
+        self.store.set_owner(self.id.inner(), owner);
+    }
+
+    /// Gets the owner of the Current Session if one was set.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let owner = session.get_owner();
+    /// ```
+    ///
+    #[inline]
+    pub fn get_owner(&self) -> Option<String> {
+        self.store.get_owner(self.id.inner())
+    }
+
+    /// Sets if the Current Session belongs to a logged in user.
+    /// The Session's ID and data are kept, so call `renew` as well when logging in.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_authenticated(true);
+    /// session.renew();
+    /// ```
+    ///
+    #[inline]
+    pub fn set_authenticated(&self, authenticated: bool) {
+        self.store.set_authenticated(self.id.inner(), authenticated);
+    }
+
+    /// Checks if the Current Session belongs to a logged in user.
+    /// Anonymous Sessions and destroyed Sessions return false.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// if !session.is_authenticated() {
+    ///     return Redirect::to("/login");
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn is_authenticated(&self) -> bool {
+        self.store.is_authenticated(self.id.inner())
+    }
+
+    /// Checks if the Current Session has expired.
+    ///
+    /// The `expires` time is when the Session's data gets wiped. It is extended at the end
+    /// of every request. Expired data is cleared when the Session is loaded, so this is
+    /// normally false unless the Session expired while the request was being handled.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let expired = session.is_expired();
+    /// ```
+    ///
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        self.store.is_expired(self.id.inner())
+    }
+
+    /// Gets when the Current Session expires.
+    ///
+    /// This is the expiration before the current request is saved. Saving a changed Session at the
+    /// end of the request extends it by its lifespan unless `with_extend_on_request(false)` is set.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let expires = session.expires_at();
+    /// ```
+    ///
+    #[inline]
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.store.expires_at(self.id.inner())
+    }
+
+    /// Gets how long until the Current Session expires. Zero once it has expired.
+    ///
+    /// This is measured from the expiration before the current request is saved, see `expires_at`.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let seconds_left = session.expires_in().map(|left| left.num_seconds());
+    /// ```
+    ///
+    #[inline]
+    pub fn expires_in(&self) -> Option<Duration> {
+        self.expires_at()
+            .map(|expires| (expires - self.store.config.now()).max(Duration::zero()))
+    }
+
+    /// Gets when the Current Session was created.
+    ///
+    /// This is set once when the Session is first created and is kept when the Session is
+    /// extended or its ID is renewed.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let created_at = session.created_at();
+    /// ```
+    ///
+    #[inline]
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        self.store.created_at(self.id.inner())
+    }
+
+    /// Gets how long ago the Current Session was created.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let minutes_old = session.age().map(|age| age.num_minutes());
+    /// ```
+    ///
+    #[inline]
+    pub fn age(&self) -> Option<Duration> {
+        self.created_at()
+            .map(|created_at| (self.store.config.now() - created_at).max(Duration::zero()))
+    }
+
+    /// Gets when the Current Session will be unloaded from memory.
+    ///
+    /// The `autoremove` time only evicts the Session from the memory store. The Session is
+    /// loaded again from the database on its next request unless it has also expired.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let autoremove = session.will_autoremove_at();
+    /// ```
+    ///
+    #[inline]
+    pub fn will_autoremove_at(&self) -> Option<DateTime<Utc>> {
+        self.store.will_autoremove_at(self.id.inner())
+    }
+
     /// Allows the Current Session to store.
     /// This will also update the database on Response Phase.
     ///
@@ -253,6 +578,133 @@ where
         self.store.get(self.id.inner(), key)
     }
 
+    /// Gets the String stored for a Key within the Session's HashMap as is without deserializing it.
+    /// Values Set with `set` are returned as their Json.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let token = session.get_raw("token");
+    /// ```
+    ///
+    #[inline]
+    pub fn get_raw(&self, key: &str) -> Option<String> {
+        self.store.get_raw(self.id.inner(), key)
+    }
+
+    /// Checks if a Key exists within the Session's HashMap without deserializing it.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let logged_in = session.contains_key("user-id");
+    /// ```
+    ///
+    #[inline]
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.store.contains_key(self.id.inner(), key)
+    }
+
+    /// Gets all the Keys within the Session's HashMap in no particular order.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let keys = session.keys();
+    /// ```
+    ///
+    #[inline]
+    pub fn keys(&self) -> Vec<String> {
+        self.store.keys(self.id.inner())
+    }
+
+    /// Gets a copy of every Key and its Json encoded value within the Session's HashMap.
+    /// This does not change the Session. Later changes to the Session do not change the copy.
+    /// Useful for debugging or admin pages.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let data = session.data_snapshot();
+    /// ```
+    ///
+    #[inline]
+    pub fn data_snapshot(&self) -> HashMap<String, String> {
+        self.store.data_snapshot(self.id.inner())
+    }
+
+    /// Gets data from the Session's HashMap or inserts the value returned by `f` if the Key
+    /// does not exist or failed to deserialize.
+    /// This will only update the database on Response Phase if the value was inserted.
+    ///
+    /// `f` is called while the Session is locked so it must not use the Session.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let first_visit: i64 = session.get_or_insert_with("first-visit", || Utc::now().timestamp());
+    /// ```
+    ///
+    #[inline]
+    pub fn get_or_insert_with<T, F>(&self, key: &str, f: F) -> T
+    where
+        T: Serialize + serde::de::DeserializeOwned,
+        F: FnOnce() -> T,
+    {
+        self.store.get_or_insert_with(self.id.inner(), key, f)
+    }
+
+    /// Adds `by` to the integer stored at the Key and returns the new value.
+    /// A missing or non integer value counts as zero. The value saturates instead of overflowing.
+    /// This will also update the database on Response Phase.
+    ///
+    /// The read and write happen while the Session is locked, so parallel requests
+    /// of the same Session can not lose each others increments.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let attempts = session.increment("failed-logins", 1);
+    /// ```
+    ///
+    #[inline]
+    pub fn increment(&self, key: &str, by: i64) -> i64 {
+        self.store.increment(self.id.inner(), key, by)
+    }
+
+    /// Gives the closure the Session's data to change it all at once while the Session
+    /// is locked, so parallel requests of the same Session see either none or all of the changes.
+    /// Returns the closure's result or None if the Session's data is missing.
+    /// This will also update the database on Response Phase.
+    ///
+    /// The closure must not use this Session as it is already locked.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.with_data(|data| {
+    ///     data.set("user-id", 1);
+    ///     data.set("role", "admin");
+    ///     data.remove("guest-cart");
+    /// });
+    /// ```
+    ///
+    #[inline]
+    pub fn with_data<R>(&self, f: impl FnOnce(&mut SessionData) -> R) -> Option<R> {
+        self.store.with_data(self.id.inner(), f)
+    }
+
+    /// Gives the closure the Session's data while its entry in the memory store is locked.
+    /// Returns the closure's result or None if the Session is not in memory.
+    ///
+    /// Unlike `with_data` this does not mark the Session to be saved. Call `data.update()`
+    /// within the closure if the changes need to be saved on Response Phase.
+    ///
+    /// The closure must not use this Session or the SessionStore as the entry is already locked.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let is_admin = session.with_locked(|data| data.get::<String>("role").as_deref() == Some("admin"));
+    /// ```
+    ///
+    #[inline]
+    pub fn with_locked<R>(&self, f: impl FnOnce(&mut SessionData) -> R) -> Option<R> {
+        self.store.with_locked(self.id.inner(), f)
+    }
+
     /// Removes a Key from the Current Session's HashMap returning it.
     ///
     /// Provides an Option<T> that returns the requested data from the Sessions store.
@@ -283,6 +735,79 @@ where
         self.store.set(self.id.inner(), key, value);
     }
 
+    /// Sets a String to the Current Session's HashMap as is without serializing it.
+    /// Useful for values that are already serialized such as tokens. `get` only reads
+    /// the value back if it is valid Json for the requested type, otherwise use `get_raw`.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_raw("token", token);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_raw(&self, key: &str, value: String) {
+        self.store.set_raw(self.id.inner(), key, value);
+    }
+
+    /// Sets a flash message to the Current Session's HashMap.
+    /// Flash messages are kept apart from other keys and are removed once taken
+    /// with `take_flash`, so they only show on the next request that reads them.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.flash("notice", "Profile saved");
+    /// ```
+    ///
+    #[inline]
+    pub fn flash(&self, key: &str, value: impl Serialize) {
+        self.store.flash(self.id.inner(), self.request, key, value);
+    }
+
+    /// Removes a flash message from the Current Session's HashMap returning it.
+    /// Returns None if the flash message does not exist or if serdes_json failed to deserialize.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let notice: Option<String> = session.take_flash("notice");
+    /// ```
+    ///
+    #[inline]
+    pub fn take_flash<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.store.take_flash(self.id.inner(), key)
+    }
+
+    /// Gets the Current Session's CSRF token, generating and storing a random one if it does not exist.
+    /// The token stays the same across requests until the Session's data is cleared.
+    /// This will update the database on Response Phase if the token was generated.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let token = session.csrf_token();
+    /// ```
+    ///
+    #[inline]
+    pub fn csrf_token(&self) -> String {
+        self.store.csrf_token(self.id.inner())
+    }
+
+    /// Checks the token against the Current Session's CSRF token using a constant time comparison.
+    /// Returns false if the Session has no CSRF token.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// if !session.verify_csrf(&form.csrf_token) {
+    ///     return StatusCode::FORBIDDEN;
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn verify_csrf(&self, token: &str) -> bool {
+        self.store.verify_csrf(self.id.inner(), token)
+    }
+
     /// Removes a Key from the Current Session's HashMap.
     /// Does not process the String into a Type, Just removes it.
     /// This will also update the database on Response Phase.
@@ -298,6 +823,7 @@ where
     }
 
     /// Clears all data from the Current Session's HashMap instantly.
+    /// The Session is also no longer authenticated and loses its owner.
     /// This will also update the database on Response Phase.
     ///
     /// # Examples
@@ -392,7 +918,8 @@ where
     ///
     #[inline]
     pub(crate) fn set_request(&self) {
-        self.store.set_session_request(self.id.inner());
+        self.store
+            .set_session_request(self.id.inner(), self.request);
     }
 
     /// checks if a session has more than one request.
@@ -423,6 +950,7 @@ where
     /// Updates the sessions stored database expire time.
     /// Use this before forcing a update to the database store.
     /// will update the database expires based on
+    /// the sessions custom lifespan if one was set.
     /// if the session is longterm then configs max_lifespan.
     /// if not then configs lifespan.
     ///
@@ -534,10 +1062,11 @@ where
     type Rejection = (http::StatusCode, &'static str);
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        let session = parts.extensions.get::<Session<T>>().cloned().ok_or((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Can't extract Axum `Session`. Is `SessionLayer` enabled?",
-        ))?;
+        let session = parts
+            .extensions
+            .get::<Session<T>>()
+            .cloned()
+            .ok_or_else(missing_session_layer)?;
 
         Ok(session.into())
     }
@@ -564,6 +1093,69 @@ where
         self.store.get(self.id.inner(), key)
     }
 
+    /// Gets the String stored for a Key within the Session's HashMap as is without deserializing it.
+    /// Values Set with `set` are returned as their Json.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let token = session.get_raw("token");
+    /// ```
+    ///
+    #[inline]
+    pub fn get_raw(&self, key: &str) -> Option<String> {
+        self.store.get_raw(self.id.inner(), key)
+    }
+
+    /// Checks if a Key exists within the Session's HashMap without deserializing it.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let logged_in = session.contains_key("user-id");
+    /// ```
+    ///
+    #[inline]
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.store.contains_key(self.id.inner(), key)
+    }
+
+    /// Gets all the Keys within the Session's HashMap in no particular order.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let keys = session.keys();
+    /// ```
+    ///
+    #[inline]
+    pub fn keys(&self) -> Vec<String> {
+        self.store.keys(self.id.inner())
+    }
+
+    /// Gets a copy of every Key and its Json encoded value within the Session's HashMap.
+    /// This does not change the Session. Later changes to the Session do not change the copy.
+    /// Useful for debugging or admin pages.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let data = session.data_snapshot();
+    /// ```
+    ///
+    #[inline]
+    pub fn data_snapshot(&self) -> HashMap<String, String> {
+        self.store.data_snapshot(self.id.inner())
+    }
+
+    /// Checks if the Session belongs to a logged in user.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let logged_in = session.is_authenticated();
+    /// ```
+    ///
+    #[inline]
+    pub fn is_authenticated(&self) -> bool {
+        self.store.is_authenticated(self.id.inner())
+    }
+
     /// Returns a i64 count of how many Sessions exist.
     ///
     /// If the Session is persistant it will return all sessions within the database.
@@ -579,3 +1171,166 @@ where
         self.store.count_sessions().await
     }
 }
+
+/// Data stored within the Session under a single well known Key.
+/// Used by TypedSession to give handlers a strongly typed view of the Session.
+///
+/// # Examples
+/// ```rust
+/// use axum_session::TypedSessionData;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Cart {
+///     items: Vec<u64>,
+/// }
+///
+/// impl TypedSessionData for Cart {
+///     const KEY: &'static str = "cart";
+/// }
+/// ```
+///
+pub trait TypedSessionData: Serialize + DeserializeOwned {
+    /// The Key the data is stored under within the Session.
+    /// Changing this will make the data stored under the old Key unreachable.
+    const KEY: &'static str;
+}
+
+/// A strongly typed view of the Session that reads and writes a single TypedSessionData.
+///
+/// # Examples
+/// ```rust ignore
+/// async fn handler(cart: TypedSession<Cart, SessionPgPool>) {
+///     let mut data = cart.get().unwrap_or_default();
+///     data.items.push(1);
+///     cart.set(&data);
+/// }
+/// ```
+///
+pub struct TypedSession<D, T>
+where
+    D: TypedSessionData,
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    pub(crate) session: Session<T>,
+    phantom: PhantomData<fn() -> D>,
+}
+
+impl<D, T> Clone for TypedSession<D, T>
+where
+    D: TypedSessionData,
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            session: self.session.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<D, T> Debug for TypedSession<D, T>
+where
+    D: TypedSessionData,
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedSession")
+            .field("key", &D::KEY)
+            .field("session", &self.session)
+            .finish()
+    }
+}
+
+impl<D, T> From<Session<T>> for TypedSession<D, T>
+where
+    D: TypedSessionData,
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    fn from(session: Session<T>) -> Self {
+        TypedSession {
+            session,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Adds FromRequestParts<B> for TypedSession
+///
+/// Returns the TypedSession from Axums request extensions state.
+#[async_trait]
+impl<D, T, S> FromRequestParts<S> for TypedSession<D, T>
+where
+    D: TypedSessionData,
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+    S: Send + Sync,
+{
+    type Rejection = (http::StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let session = parts
+            .extensions
+            .get::<Session<T>>()
+            .cloned()
+            .ok_or_else(missing_session_layer)?;
+
+        Ok(session.into())
+    }
+}
+
+impl<D, T> TypedSession<D, T>
+where
+    D: TypedSessionData,
+    T: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    /// Gets the data from the Session.
+    /// Returns None if it was never Set or failed to deserialize.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let cart = typed.get().unwrap_or_default();
+    /// ```
+    ///
+    #[inline]
+    pub fn get(&self) -> Option<D> {
+        self.session.get(D::KEY)
+    }
+
+    /// Sets the data within the Session.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// typed.set(&cart);
+    /// ```
+    ///
+    #[inline]
+    pub fn set(&self, value: &D) {
+        self.session.set(D::KEY, value);
+    }
+
+    /// Removes the data from the Session returning it.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let cart = typed.remove();
+    /// ```
+    ///
+    #[inline]
+    pub fn remove(&self) -> Option<D> {
+        self.session.get_remove(D::KEY)
+    }
+
+    /// Gets the untyped Session for everything else.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// typed.session().renew();
+    /// ```
+    ///
+    #[inline]
+    pub fn session(&self) -> &Session<T> {
+        &self.session
+    }
+}
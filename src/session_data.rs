@@ -1,4 +1,4 @@
-use crate::SessionConfig;
+use crate::{sec::csrf, Expiration, SessionConfig};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::{
@@ -7,8 +7,27 @@ use std::{
 };
 use uuid::Uuid;
 
+/// Prefix of the keys within the Session's HashMap that hold flash messages.
+/// Keeps flash messages from colliding with keys set by `set`.
+pub(crate) const FLASH_PREFIX: &str = "__flash:";
+
+/// Key within the Session's HashMap that holds the Session's CSRF token.
+pub(crate) const CSRF_KEY: &str = "__csrf_token";
+
+/// Checks if the Key is used by the Session itself, such as for flash messages or the CSRF token.
+/// These are left out of `keys` and `data_snapshot`.
+#[inline]
+pub(crate) fn is_reserved_key(key: &str) -> bool {
+    key.starts_with(FLASH_PREFIX) || key == CSRF_KEY
+}
+
 /// The Store and Configured Data for a Session.
 ///
+/// Serializes to the Json saved within the database. Timestamps are RFC 3339 strings and values set
+/// with `set` are kept as Json strings. The id, expiration and per request state are not serialized
+/// as the database keeps the id and expiration separately. Fields added later have serde defaults
+/// so older saved data still loads.
+///
 /// # Examples
 /// ```rust ignore
 /// use axum_session::{SessionConfig, SessionData};
@@ -23,6 +42,7 @@ use uuid::Uuid;
 pub struct SessionData {
     #[serde(skip)]
     pub(crate) id: Uuid,
+    #[serde(default)]
     pub(crate) data: HashMap<String, String>,
     #[serde(skip)]
     pub(crate) expires: DateTime<Utc>,
@@ -32,13 +52,57 @@ pub struct SessionData {
     pub(crate) destroy: bool,
     #[serde(skip)]
     pub(crate) renew: bool,
+    #[serde(default)]
     pub(crate) longterm: bool,
+    #[serde(default)]
+    pub(crate) lifespan: Option<i64>,
+    /// Set by remember so the Session's cookie lives as long as the Session's lifespan.
+    #[serde(default)]
+    pub(crate) remember: bool,
+    #[serde(default = "Utc::now")]
+    pub(crate) created_at: DateTime<Utc>,
+    /// When the Session ID was created or last renewed. Used by the rotation interval.
+    #[serde(default = "Utc::now")]
+    pub(crate) last_rotated: DateTime<Utc>,
+    #[serde(default)]
+    pub(crate) owner: Option<String>,
+    /// When the owner was last set. Used by the session limit so the owner's newest login is kept.
+    #[serde(default)]
+    pub(crate) owned_at: Option<DateTime<Utc>>,
+    /// Set once the user logs in so apps do not need to keep their own flag within the data.
+    #[serde(default)]
+    pub(crate) authenticated: bool,
+    #[serde(default)]
+    pub(crate) ip_binding: Option<String>,
+    #[serde(default)]
+    pub(crate) user_agent_binding: Option<String>,
     #[serde(skip)]
     pub(crate) store: bool,
     #[serde(skip)]
     pub(crate) update: bool,
     #[serde(skip)]
+    pub(crate) touched: bool,
+    /// Set when the Session failed to load and LoadErrorPolicy::MemoryOnly is used.
+    /// The data is then never saved and is dropped once unused so it gets loaded again.
+    #[serde(skip)]
+    pub(crate) load_failed: bool,
+    /// Set when another server changed the Session while a request was using it.
+    /// It is then dropped once unused so it gets loaded again.
+    #[serde(skip)]
+    pub(crate) invalidated: bool,
+    #[serde(skip)]
     pub(crate) requests: usize,
+    /// When the Session was last used by a request. Used to evict the least recently used Sessions from memory.
+    #[serde(skip)]
+    pub(crate) accessed: DateTime<Utc>,
+    /// Flash messages set before each running request started, by request.
+    /// They are removed once that request ends so overlapping requests do not expire each others flash messages.
+    #[serde(skip)]
+    pub(crate) stale_flashes: HashMap<u64, Vec<String>>,
+    /// Flash messages set by requests that are still running, with the request that set them.
+    /// Requests starting before the setting request ends do not remove them.
+    #[serde(skip)]
+    pub(crate) fresh_flashes: HashMap<String, u64>,
 }
 
 impl SessionData {
@@ -56,21 +120,40 @@ impl SessionData {
     ///
     #[inline]
     pub(crate) fn new(id: Uuid, storable: bool, config: &SessionConfig) -> Self {
+        let now = config.now();
+
         Self {
             id,
             data: HashMap::new(),
-            expires: Utc::now() + config.lifespan,
+            expires: now + config.lifespan,
             destroy: false,
             renew: false,
-            autoremove: Utc::now() + config.memory.memory_lifespan,
+            autoremove: now + config.memory.memory_lifespan,
             longterm: false,
+            lifespan: None,
+            remember: false,
+            created_at: now,
+            last_rotated: now,
+            owner: None,
+            owned_at: None,
+            authenticated: false,
+            ip_binding: None,
+            user_agent_binding: None,
             store: storable,
             update: true,
+            touched: false,
+            load_failed: false,
+            invalidated: false,
             requests: 1,
+            accessed: now,
+            stale_flashes: HashMap::new(),
+            fresh_flashes: HashMap::new(),
         }
     }
 
     /// Validates if the Session is to expire.
+    /// Returns true while the Session has not yet expired at the config's current time,
+    /// so a clock set with `with_clock` is used.
     ///
     /// # Examples
     /// ```rust ignore
@@ -80,16 +163,81 @@ impl SessionData {
     /// let config = SessionConfig::default();
     /// let token = Uuid::new_v4();
     /// let session_data = SessionData::new(token, true, &config);
-    /// let expired = session_data.validate();
+    /// let expired = session_data.validate(&config);
+    /// ```
+    ///
+    #[inline]
+    pub fn validate(&self, config: &SessionConfig) -> bool {
+        self.validate_at(config.now())
+    }
+
+    /// Validates if the Session is to expire at the given time.
+    #[inline]
+    pub(crate) fn validate_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires >= now
+    }
+
+    /// When the owner was last set, or when the Session was created if it was stored
+    /// before that was kept.
+    #[inline]
+    pub(crate) fn owned_since(&self) -> DateTime<Utc> {
+        self.owned_at.unwrap_or(self.created_at)
+    }
+
+    /// Checks if the Session has expired at the config's current time.
+    /// Once expired the Session's data gets cleared when it is next loaded.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let expired = session_data.is_expired(&config);
     /// ```
     ///
     #[inline]
-    pub(crate) fn validate(&self) -> bool {
-        self.expires >= Utc::now()
+    pub fn is_expired(&self, config: &SessionConfig) -> bool {
+        !self.validate(config)
+    }
+
+    /// Gets when the Session expires.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let expires = session_data.expires_at();
+    /// ```
+    ///
+    #[inline]
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.expires
+    }
+
+    /// Gets when the Session was created. Extending the Session does not change it.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let created_at = session_data.created_at();
+    /// ```
+    ///
+    #[inline]
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    /// Gets when the Session will be unloaded from memory.
+    /// This is not when the Session expires. An unloaded Session is loaded
+    /// again from the database on its next request if it has not yet expired.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let autoremove = session_data.will_autoremove_at();
+    /// ```
+    ///
+    #[inline]
+    pub fn will_autoremove_at(&self) -> DateTime<Utc> {
+        self.autoremove
     }
 
     /// Validates and checks if the Session is to be destroyed.
-    /// If so the Sessions Data is Cleared.
+    /// If so the Sessions Data is Cleared and true is returned. An expired Session is
+    /// cleared even while it is still kept in memory so its expiration is always enforced.
     /// autoremove is then updated for the session regardless.
     ///
     /// # Examples
@@ -100,20 +248,108 @@ impl SessionData {
     /// let config = SessionConfig::default();
     /// let token = Uuid::new_v4();
     /// let mut session_data = SessionData::new(token, true, &config);
-    /// let expired = session_data.service_clear(Duration::days(5));
+    /// let expired = session_data.service_clear(Duration::days(5), true, Utc::now());
     /// ```
     ///
     #[inline]
-    pub(crate) fn service_clear(&mut self, memory_lifespan: Duration, clear_check: bool) {
-        if clear_check && self.autoremove < Utc::now() {
+    pub(crate) fn service_clear(
+        &mut self,
+        memory_lifespan: Duration,
+        clear_check: bool,
+        now: DateTime<Utc>,
+    ) -> bool {
+        let mut cleared = false;
+
+        if clear_check && self.autoremove < now {
             self.update = true;
+        }
+
+        if clear_check && !self.validate_at(now) {
+            tracing::debug!(session.id = %self.id, "Session data cleared as the session expired");
+            self.clear_data();
+            self.update = true;
+            cleared = true;
+        }
+
+        self.autoremove = now + memory_lifespan;
+        cleared
+    }
 
-            if !self.validate() {
-                self.data.clear();
+    /// Extends the Session's expiration by its lifespan.
+    /// When the expiration is Absolute it will not be extended past the
+    /// Session's creation time plus the absolute Duration.
+    #[inline]
+    pub(crate) fn set_expires(&mut self, config: &SessionConfig) {
+        let expires = config.now() + self.get_lifespan(config);
+
+        self.expires = match config.expiration {
+            Expiration::Sliding => expires,
+            Expiration::Absolute(max) => expires.min(self.created_at + max),
+        };
+    }
+
+    /// Checks if the Session ID is older than the rotation interval.
+    /// If so the Session is Set to be renewed with a new Session ID and true is returned.
+    #[inline]
+    pub(crate) fn service_rotation(&mut self, config: &SessionConfig) -> bool {
+        if let Some(interval) = config.rotation_interval {
+            if self.last_rotated + interval <= config.now() {
+                tracing::debug!(session.id = %self.id, "Session ID is due to be rotated");
+                self.renew();
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Checks if the Session reached its Absolute expiration.
+    /// If so the Sessions Data is Cleared, it starts over as a new Session with a
+    /// new Session ID and true is returned.
+    #[inline]
+    pub(crate) fn service_expiration(&mut self, config: &SessionConfig) -> bool {
+        if let Expiration::Absolute(max) = config.expiration {
+            let now = config.now();
+
+            if self.created_at + max <= now {
+                tracing::debug!(
+                    session.id = %self.id,
+                    "Session data cleared as the session reached its absolute expiration"
+                );
+                self.clear_data();
+                self.created_at = now;
+                self.update = true;
+                // The old Session ID is no longer valid past the absolute expiration.
+                self.renew = true;
+                return true;
             }
         }
 
-        self.autoremove = Utc::now() + memory_lifespan;
+        false
+    }
+
+    /// Binds the Session to the ip and user agent hashes it is not bound to yet.
+    ///
+    /// Returns false if the Session was bound to a different ip or user agent.
+    #[inline]
+    pub(crate) fn verify_bindings(&mut self, ip: Option<&str>, user_agent: Option<&str>) -> bool {
+        for (bound, value) in [
+            (&mut self.ip_binding, ip),
+            (&mut self.user_agent_binding, user_agent),
+        ] {
+            if let Some(value) = value {
+                match bound.as_deref() {
+                    Some(bound) if bound != value => return false,
+                    Some(_) => {}
+                    None => {
+                        *bound = Some(value.to_owned());
+                        self.update = true;
+                    }
+                }
+            }
+        }
+
+        true
     }
 
     /// Sets the Session to renew its Session ID.
@@ -149,7 +385,8 @@ impl SessionData {
     }
 
     /// Sets the Current Session to be Destroyed.
-    /// This will Deleted the Session and Cookies upon Response Phase.
+    /// This will Clear the Sessions data instantly and Delete
+    /// the Session and Cookies upon Response Phase.
     ///
     /// # Examples
     /// ```rust ignore
@@ -158,6 +395,7 @@ impl SessionData {
     ///
     #[inline]
     pub fn destroy(&mut self) {
+        self.clear_data();
         self.destroy = true;
     }
 
@@ -175,6 +413,113 @@ impl SessionData {
         self.update = true;
     }
 
+    /// Sets a custom lifespan for the Current Session overriding the configs lifespan
+    /// and max_lifespan. Setting this to None will return to using the configs lifespans.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_lifespan(Some(Duration::days(30)));
+    /// ```
+    ///
+    #[inline]
+    pub fn set_lifespan(&mut self, lifespan: Option<Duration>) {
+        self.lifespan = lifespan.map(|lifespan| lifespan.num_seconds());
+        self.update = true;
+    }
+
+    /// Remembers the Current Session for the given Duration. Useful for Remember Me setups.
+    /// The Session becomes longterm with the Duration as its lifespan and its cookie is
+    /// given a Max-Age of the Duration instead of the configs max_age.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.remember(Duration::days(30));
+    /// ```
+    ///
+    #[inline]
+    pub fn remember(&mut self, duration: Duration) {
+        self.longterm = true;
+        self.lifespan = Some(duration.num_seconds());
+        self.remember = true;
+        self.update = true;
+    }
+
+    /// Gets the Max-Age of the Session's cookie.
+    /// This is the Session's lifespan if it was remembered, otherwise the configs max_age.
+    #[inline]
+    pub(crate) fn cookie_max_age(&self, config: &SessionConfig) -> Option<Duration> {
+        if self.remember {
+            Some(self.get_lifespan(config))
+        } else {
+            config.cookie_and_header.cookie_max_age
+        }
+    }
+
+    /// Gets the lifespan the Session's expiration is extended by.
+    /// This is the custom lifespan if one was set, otherwise the configs
+    /// max_lifespan if the session is longterm or the configs lifespan.
+    #[inline]
+    pub(crate) fn get_lifespan(&self, config: &SessionConfig) -> Duration {
+        if let Some(lifespan) = self.lifespan.and_then(Duration::try_seconds) {
+            lifespan
+        } else if self.longterm {
+            config.max_lifespan
+        } else {
+            config.lifespan
+        }
+    }
+
+    /// Sets the owner of the Current Session such as a user's ID.
+    /// Used to Destroy all of an owner's Sessions with `SessionStore::destroy_all_by_owner`.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_owner(Some("user-1".to_owned()));
+    /// ```
+    ///
+    #[inline]
+    pub fn set_owner(&mut self, owner: Option<String>) {
+        self.set_owner_at(owner, Utc::now());
+    }
+
+    /// Sets the owner of the Current Session along with when it was set.
+    #[inline]
+    pub(crate) fn set_owner_at(&mut self, owner: Option<String>, now: DateTime<Utc>) {
+        self.owned_at = owner.as_ref().map(|_| now);
+        self.owner = owner;
+        self.update = true;
+    }
+
+    /// Sets if the Current Session belongs to a logged in user. The Session's ID and data
+    /// are kept so an anonymous cart survives logging in.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_authenticated(true);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_authenticated(&mut self, authenticated: bool) {
+        self.authenticated = authenticated;
+        self.update = true;
+    }
+
+    /// Checks if the Current Session belongs to a logged in user.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let logged_in = session.is_authenticated();
+    /// ```
+    ///
+    #[inline]
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated
+    }
+
     /// Sets the Current Session to be storable.
     /// This will also update the database on Response Phase.
     ///
@@ -211,6 +556,106 @@ impl SessionData {
         serde_json::from_str(string).ok()
     }
 
+    /// Gets the String stored for a Key within the Session's HashMap as is without deserializing it.
+    /// Values Set with `set` are returned as their Json.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let token = session.get_raw("token");
+    /// ```
+    ///
+    #[inline]
+    pub fn get_raw(&self, key: &str) -> Option<String> {
+        self.data.get(key).cloned()
+    }
+
+    /// Checks if a Key exists within the Session's HashMap without deserializing it.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let logged_in = session.contains_key("user-id");
+    /// ```
+    ///
+    #[inline]
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.data.contains_key(key)
+    }
+
+    /// Gets all the Keys within the Session's HashMap in no particular order.
+    /// Keys used by the Session itself, such as for flash messages or the CSRF token, are left out.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let keys = session.keys();
+    /// ```
+    ///
+    #[inline]
+    pub fn keys(&self) -> Vec<String> {
+        self.data
+            .keys()
+            .filter(|key| !is_reserved_key(key))
+            .cloned()
+            .collect()
+    }
+
+    /// Gets a copy of every Key and its Json encoded value within the Session's HashMap.
+    /// Later changes to the Session do not change the copy.
+    /// Keys used by the Session itself, such as for flash messages or the CSRF token, are left out.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let data = session.data_snapshot();
+    /// ```
+    ///
+    #[inline]
+    pub fn data_snapshot(&self) -> HashMap<String, String> {
+        self.data
+            .iter()
+            .filter(|(key, _)| !is_reserved_key(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Gets data from the Session's HashMap or inserts the value returned by `f` if the Key
+    /// does not exist or failed to deserialize.
+    /// This will only update the database on Response Phase if the value was inserted.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let first_visit: i64 = session.get_or_insert_with("first-visit", || Utc::now().timestamp());
+    /// ```
+    ///
+    #[inline]
+    pub fn get_or_insert_with<T, F>(&mut self, key: &str, f: F) -> T
+    where
+        T: Serialize + serde::de::DeserializeOwned,
+        F: FnOnce() -> T,
+    {
+        if let Some(value) = self.get(key) {
+            return value;
+        }
+
+        let value = f();
+        self.set(key, &value);
+        value
+    }
+
+    /// Adds `by` to the integer stored at the Key and returns the new value.
+    /// A missing or non integer value counts as zero. The value saturates instead of overflowing.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let attempts = session.increment("failed-logins", 1);
+    /// ```
+    ///
+    #[inline]
+    pub fn increment(&mut self, key: &str, by: i64) -> i64 {
+        let value = self.get::<i64>(key).unwrap_or(0).saturating_add(by);
+        self.set(key, value);
+        value
+    }
+
     /// Removes a Key from the Current Session's HashMap returning it.
     /// This will also update the database on Response Phase.
     ///
@@ -246,6 +691,22 @@ impl SessionData {
         self.update = true;
     }
 
+    /// Sets a String to the Current Session's HashMap as is without serializing it.
+    /// Useful for values that are already serialized such as tokens. `get` only reads
+    /// the value back if it is valid Json for the requested type, otherwise use `get_raw`.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set_raw("token", token);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_raw(&mut self, key: &str, value: String) {
+        let _ = self.data.insert(key.to_string(), value);
+        self.update = true;
+    }
+
     /// Removes a Key from the Current Session's HashMap.
     /// Does not process the String into a Type, Just removes it.
     /// This will also update the database on Response Phase.
@@ -261,7 +722,77 @@ impl SessionData {
         self.update = true;
     }
 
+    /// Sets a flash message to the Current Session's HashMap.
+    /// Flash messages are kept apart from other keys and are removed once taken
+    /// with `take_flash`. A flash message that is not taken by the next request is removed
+    /// once that request ends, so it never shows up on a later page.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.flash("notice", "Profile saved");
+    /// ```
+    ///
+    #[inline]
+    pub fn flash(&mut self, key: &str, value: impl Serialize) {
+        let key = format!("{}{}", FLASH_PREFIX, key);
+        self.stale_flashes
+            .values_mut()
+            .for_each(|stale| stale.retain(|stale| stale != &key));
+        self.set(&key, value);
+    }
+
+    /// Sets a flash message for the request so requests running alongside it do not remove it.
+    #[inline]
+    pub(crate) fn flash_request(&mut self, key: &str, value: impl Serialize, request: u64) {
+        self.flash(key, value);
+        self.fresh_flashes
+            .insert(format!("{}{}", FLASH_PREFIX, key), request);
+    }
+
+    /// Removes a flash message from the Current Session's HashMap returning it.
+    /// Returns None if the flash message does not exist or if serdes_json failed to deserialize.
+    /// This will also update the database on Response Phase.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let notice: Option<String> = session.take_flash("notice");
+    /// ```
+    ///
+    #[inline]
+    pub fn take_flash<T: serde::de::DeserializeOwned>(&mut self, key: &str) -> Option<T> {
+        self.get_remove(&format!("{}{}", FLASH_PREFIX, key))
+    }
+
+    /// Gets the Current Session's CSRF token, generating and storing a random one if it does not exist.
+    /// The token stays the same for the life of the Session's data.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let token = session.csrf_token();
+    /// ```
+    ///
+    #[inline]
+    pub fn csrf_token(&mut self) -> String {
+        self.get_or_insert_with(CSRF_KEY, csrf::generate_token)
+    }
+
+    /// Checks the token against the Current Session's CSRF token using a constant time comparison.
+    /// Returns false if the Session has no CSRF token.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let valid = session.verify_csrf(&token);
+    /// ```
+    ///
+    #[inline]
+    pub fn verify_csrf(&self, token: &str) -> bool {
+        self.get::<String>(CSRF_KEY)
+            .is_some_and(|expected| csrf::verify_token(&expected, token))
+    }
+
     /// Clears all data from the Current Session's HashMap.
+    /// The Session is also no longer authenticated and loses its owner.
     /// This will also update the database on Response Phase.
     ///
     /// # Examples
@@ -271,10 +802,19 @@ impl SessionData {
     ///
     #[inline]
     pub fn clear(&mut self) {
-        self.data.clear();
+        self.clear_data();
         self.update = true;
     }
 
+    /// Clears the Session's data along with its authenticated flag and owner.
+    #[inline]
+    fn clear_data(&mut self) {
+        self.data.clear();
+        self.authenticated = false;
+        self.owner = None;
+        self.owned_at = None;
+    }
+
     /// Removes a Request from the request counter
     /// used to deturmine if parallel requests exist.
     /// prevents data deletion until requests == 0.
@@ -287,6 +827,11 @@ impl SessionData {
     #[inline]
     pub(crate) fn remove_request(&mut self) {
         self.requests = self.requests.saturating_sub(1);
+
+        if self.requests == 0 {
+            self.stale_flashes.clear();
+            self.fresh_flashes.clear();
+        }
     }
 
     /// Removes a Request from the request counter
@@ -295,12 +840,39 @@ impl SessionData {
     ///
     /// # Examples
     /// ```rust ignore
-    /// session.set_request();
+    /// session.set_request(Utc::now(), request);
     /// ```
     ///
     #[inline]
-    pub(crate) fn set_request(&mut self) {
+    pub(crate) fn set_request(&mut self, now: DateTime<Utc>, request: u64) {
         self.requests = self.requests.saturating_add(1);
+        self.accessed = now;
+        self.age_flashes(request);
+    }
+
+    /// Marks the flash messages set before the request so they are removed once it ends.
+    #[inline]
+    pub(crate) fn age_flashes(&mut self, request: u64) {
+        let stale = self
+            .data
+            .keys()
+            .filter(|key| key.starts_with(FLASH_PREFIX) && !self.fresh_flashes.contains_key(*key))
+            .cloned()
+            .collect();
+        self.stale_flashes.insert(request, stale);
+    }
+
+    /// Removes the flash messages set before the request started that were not taken since.
+    /// The flash messages the request set can then be removed by the requests starting after it.
+    #[inline]
+    pub(crate) fn expire_flashes(&mut self, request: u64) {
+        self.fresh_flashes.retain(|_, setter| *setter != request);
+
+        for key in self.stale_flashes.remove(&request).unwrap_or_default() {
+            if self.data.remove(&key).is_some() {
+                self.update = true;
+            }
+        }
     }
 
     /// checks if a session has a request still.
@@ -403,4 +975,218 @@ impl Display for SessionID {
 pub(crate) struct SessionTimers {
     pub(crate) last_expiry_sweep: DateTime<Utc>,
     pub(crate) last_database_expiry_sweep: DateTime<Utc>,
+    /// When the new Session counts of ip's whose period ended are next removed.
+    pub(crate) last_new_session_prune: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{SessionConfig, SessionData};
+    use chrono::{Duration, Utc};
+    use serde::{Deserialize, Serialize};
+    use uuid::Uuid;
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+    struct Login {
+        id: u64,
+        name: String,
+    }
+
+    #[test]
+    fn increment() {
+        let mut session_data = SessionData::new(Uuid::new_v4(), true, &SessionConfig::default());
+
+        assert_eq!(session_data.increment("count", 2), 2);
+        assert_eq!(session_data.increment("count", -5), -3);
+        assert_eq!(session_data.get::<i64>("count"), Some(-3));
+
+        session_data.set("name", "admin");
+        assert_eq!(session_data.increment("name", 1), 1);
+
+        session_data.set("max", i64::MAX);
+        assert_eq!(session_data.increment("max", 1), i64::MAX);
+    }
+
+    #[test]
+    fn typed_get_set() {
+        let mut session_data = SessionData::new(Uuid::new_v4(), true, &SessionConfig::default());
+        session_data.update = false;
+
+        let login = Login {
+            id: 1,
+            name: "admin".to_owned(),
+        };
+
+        session_data.set("login", &login);
+        session_data.set("count", 5u32);
+        assert!(session_data.update);
+
+        assert_eq!(session_data.get::<Login>("login"), Some(login));
+        assert_eq!(session_data.get::<u32>("count"), Some(5));
+        // Failing to deserialize returns None rather than panicking.
+        assert_eq!(session_data.get::<Login>("count"), None);
+        assert_eq!(session_data.get::<u32>("missing"), None);
+    }
+
+    #[test]
+    fn is_expired() {
+        let config = SessionConfig::default();
+        let mut session_data = SessionData::new(Uuid::new_v4(), true, &config);
+        assert!(session_data.validate(&config));
+        assert!(!session_data.is_expired(&config));
+        assert!(session_data.will_autoremove_at() > Utc::now());
+
+        session_data.expires = Utc::now() - Duration::try_seconds(1).unwrap();
+        assert!(!session_data.validate(&config));
+        assert!(session_data.is_expired(&config));
+
+        // The config's clock decides what now is.
+        let config = config.with_clock(|| Utc::now() - Duration::try_minutes(1).unwrap());
+        assert!(session_data.validate(&config));
+        assert!(!session_data.is_expired(&config));
+    }
+
+    #[test]
+    fn csrf_token() {
+        let mut session_data = SessionData::new(Uuid::new_v4(), true, &SessionConfig::default());
+        assert!(!session_data.verify_csrf(""));
+
+        let token = session_data.csrf_token();
+        assert_eq!(session_data.csrf_token(), token);
+        assert!(session_data.verify_csrf(&token));
+        assert!(!session_data.verify_csrf("wrong"));
+        assert!(!session_data.verify_csrf(&token[1..]));
+
+        let other = SessionData::new(Uuid::new_v4(), true, &SessionConfig::default()).csrf_token();
+        assert_ne!(other, token);
+        assert!(!session_data.verify_csrf(&other));
+    }
+
+    #[test]
+    fn contains_key_and_keys() {
+        let mut session_data = SessionData::new(Uuid::new_v4(), true, &SessionConfig::default());
+        session_data.update = false;
+        assert!(!session_data.contains_key("a"));
+        assert!(session_data.keys().is_empty());
+
+        session_data.set("a", 1);
+        session_data.set("b", "two");
+        session_data.set("c", vec![3]);
+        session_data.update = false;
+
+        assert!(session_data.contains_key("b"));
+        assert!(!session_data.contains_key("d"));
+
+        let mut keys = session_data.keys();
+        keys.sort();
+        assert_eq!(keys, vec!["a", "b", "c"]);
+        // Reading keys does not mark the session to be saved.
+        assert!(!session_data.update);
+    }
+
+    #[test]
+    fn get_or_insert_with() {
+        let mut session_data = SessionData::new(Uuid::new_v4(), true, &SessionConfig::default());
+        session_data.update = false;
+        let mut calls = 0;
+
+        for _ in 0..2 {
+            let value: u32 = session_data.get_or_insert_with("count", || {
+                calls += 1;
+                5
+            });
+            assert_eq!(value, 5);
+        }
+
+        assert_eq!(calls, 1);
+        assert!(session_data.update);
+
+        // An existing value does not mark the session to be saved.
+        session_data.update = false;
+        let value: u32 = session_data.get_or_insert_with("count", || 6);
+        assert_eq!(value, 5);
+        assert!(!session_data.update);
+    }
+
+    #[test]
+    fn remove_and_clear() {
+        let id = Uuid::new_v4();
+        let mut session_data = SessionData::new(id, true, &SessionConfig::default());
+        let expires = session_data.expires;
+
+        session_data.set("a", 1);
+        session_data.set("b", 2);
+
+        session_data.remove("a");
+        assert_eq!(session_data.get::<i32>("a"), None);
+        assert_eq!(session_data.get::<i32>("b"), Some(2));
+
+        session_data.clear();
+        assert_eq!(session_data.get::<i32>("b"), None);
+        assert_eq!(session_data.id, id);
+        assert_eq!(session_data.expires, expires);
+        assert!(!session_data.destroy);
+    }
+
+    #[test]
+    fn raw_values() {
+        let config = SessionConfig::default();
+        let mut session_data = SessionData::new(Uuid::new_v4(), true, &config);
+
+        session_data.set_raw("token", "opaque.token".to_owned());
+        assert_eq!(
+            session_data.get_raw("token"),
+            Some("opaque.token".to_owned())
+        );
+        // Not valid Json so the typed get fails without panicking.
+        assert_eq!(session_data.get::<String>("token"), None);
+
+        session_data.set_raw("count", "5".to_owned());
+        assert_eq!(session_data.get::<i32>("count"), Some(5));
+
+        session_data.set("name", "value");
+        assert_eq!(session_data.get_raw("name"), Some("\"value\"".to_owned()));
+        assert_eq!(session_data.get_raw("missing"), None);
+    }
+
+    #[test]
+    fn serde_shape() {
+        let created_at = "2024-01-01T00:00:00Z"
+            .parse::<chrono::DateTime<Utc>>()
+            .unwrap();
+        let mut session_data = SessionData::new(Uuid::new_v4(), true, &SessionConfig::default());
+        session_data.created_at = created_at;
+        session_data.last_rotated = created_at;
+        session_data.set("name", "admin");
+        session_data.set_owner_at(Some("user-1".to_owned()), created_at);
+
+        let json = serde_json::to_value(&session_data).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "data": { "name": "\"admin\"" },
+                "longterm": false,
+                "lifespan": null,
+                "remember": false,
+                "created_at": "2024-01-01T00:00:00Z",
+                "last_rotated": "2024-01-01T00:00:00Z",
+                "owner": "user-1",
+                "owned_at": "2024-01-01T00:00:00Z",
+                "authenticated": false,
+                "ip_binding": null,
+                "user_agent_binding": null
+            })
+        );
+
+        let loaded: SessionData = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.get::<String>("name"), Some("admin".to_owned()));
+        assert_eq!(loaded.created_at, created_at);
+        assert_eq!(loaded.owner.as_deref(), Some("user-1"));
+
+        // Data saved before later fields were added still loads.
+        let loaded: SessionData = serde_json::from_str(r#"{"data":{"n":"1"}}"#).unwrap();
+        assert_eq!(loaded.get::<i32>("n"), Some(1));
+        assert!(!loaded.longterm);
+        assert!(loaded.owner.is_none());
+    }
 }
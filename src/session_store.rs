@@ -1,22 +1,64 @@
+#[cfg(feature = "compression")]
+use crate::compress;
 use crate::{
-    sec::encrypt, DatabasePool, Session, SessionConfig, SessionData, SessionError, SessionTimers,
+    sec::encrypt, serializer, session::missing_session_layer, DatabasePool, EvictionPolicy,
+    LoadErrorPolicy, Session, SessionConfig, SessionData, SessionError, SessionEvent,
+    SessionTimers,
 };
 use async_trait::async_trait;
 use axum::extract::FromRequestParts;
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use dashmap::DashMap;
 #[cfg(feature = "key-store")]
 use fastbloom_rs::Deletable;
 #[cfg(feature = "key-store")]
 use fastbloom_rs::{CountingBloomFilter, FilterBuilder, Membership};
-use http::{request::Parts, StatusCode};
+use http::request::Parts;
 use serde::Serialize;
-use std::{fmt::Debug, sync::Arc};
-use tokio::sync::RwLock;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fmt::Debug,
+    future::Future,
+    sync::{atomic::AtomicU64, Arc},
+    time::Instant,
+};
+use tokio::{
+    sync::{mpsc::UnboundedReceiver, RwLock},
+    task::JoinHandle,
+    time::MissedTickBehavior,
+};
 use uuid::Uuid;
 
+/// Sent in place of a Session ID to invalidate every Session, such as after clear_all.
+pub(crate) const INVALIDATE_ALL: &str = "*";
+
+/// A change to a Session's data that is applied once the lazily loaded Session is loaded.
+pub(crate) struct PendingChange(Box<dyn FnOnce(&mut SessionData) + Send + Sync>);
+
+/// Stops the invalidation listener once the last clone of the SessionStore is dropped.
+/// Its receiver is dropped along with it so the DatabasePool's listener stops too.
+#[derive(Debug)]
+pub(crate) struct InvalidationListener(JoinHandle<()>);
+
+impl Drop for InvalidationListener {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+impl Debug for PendingChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PendingChange")
+    }
+}
+
 /// Contains the main Services storage for all session's and database access for persistant Sessions.
 ///
+/// Clones share the same Sessions, timers and filter, so a clone can be handed to
+/// the SessionLayer while another is kept for direct use. Calling `SessionStore::new`
+/// twice creates two separate stores that do not see each other's Sessions.
+///
 /// # Examples
 /// ```rust ignore
 /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
@@ -33,11 +75,22 @@ where
     /// Client for the database.
     pub client: Option<T>,
     /// locked Hashmap containing UserID and their session data.
+    /// Must stay behind an Arc so every clone of the store shares it.
     pub(crate) inner: Arc<DashMap<String, SessionData>>,
     /// Session Configuration.
     pub config: SessionConfig,
     /// Session Timers used for Clearing Memory and Database.
     pub(crate) timers: Arc<RwLock<SessionTimers>>,
+    /// Identifies this store within invalidation messages so it skips its own.
+    pub(crate) instance_id: Uuid,
+    /// How many new Sessions each ip created and when their period started.
+    pub(crate) new_session_counts: Arc<DashMap<String, (DateTime<Utc>, usize)>>,
+    /// Changes made by handlers to lazily loaded Sessions before they were loaded.
+    pub(crate) pending_changes: Arc<DashMap<String, Vec<PendingChange>>>,
+    /// Numbers each request so flash messages are aged per request.
+    pub(crate) request_counter: Arc<AtomicU64>,
+    /// Listens for invalidations from other stores until every clone of the store is dropped.
+    pub(crate) invalidation_listener: Option<Arc<InvalidationListener>>,
     #[cfg(feature = "key-store")]
     /// Filter used to keep track of what uuid's exist.
     pub(crate) filter: Arc<RwLock<CountingBloomFilter>>,
@@ -52,10 +105,10 @@ where
     type Rejection = (http::StatusCode, &'static str);
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        let session = parts.extensions.get::<Session<T>>().ok_or((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Can't extract Axum `Session`. Is `SessionLayer` enabled?",
-        ))?;
+        let session = parts
+            .extensions
+            .get::<Session<T>>()
+            .ok_or_else(missing_session_layer)?;
 
         Ok(session.store.clone())
     }
@@ -68,6 +121,11 @@ where
     /// Constructs a New `SessionStore` and Creates the Database Table
     /// needed for the Session if it does not exist if client is not `None`.
     ///
+    /// # Errors
+    /// - ['SessionError::InvalidTableName'] is returned if the configured table name is not a plain identifier.
+    /// - ['SessionError::InvalidCookieName'] is returned if the session or store name can not be used as a cookie name.
+    /// - ['SessionError::InsecureSameSiteNone'] is returned if SameSite::None is used while secure is disabled.
+    ///
     /// # Examples
     /// ```rust ignore
     /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
@@ -78,7 +136,22 @@ where
     ///
     #[inline]
     pub async fn new(client: Option<T>, config: SessionConfig) -> Result<Self, SessionError> {
+        if let Some(name) = config.cookie_and_header.invalid_name() {
+            return Err(SessionError::InvalidCookieName(name.to_owned()));
+        }
+
+        if config.cookie_and_header.insecure_same_site_none() {
+            return Err(SessionError::InsecureSameSiteNone);
+        }
+
+        // Without a client the table name is never used.
         if let Some(client) = &client {
+            if !client.is_valid_table_name(&config.database.table_name) {
+                return Err(SessionError::InvalidTableName(
+                    config.database.table_name.to_string(),
+                ));
+            }
+
             client.initiate(&config.database.table_name).await?
         }
 
@@ -87,21 +160,110 @@ where
         #[cfg(feature = "key-store")]
         let filter = Self::create_filter(&client, &config).await?;
 
+        let inner: Arc<DashMap<String, SessionData>> = Default::default();
+        let instance_id = Uuid::new_v4();
+        let mut invalidation_listener = None;
+
+        if config.database.invalidation {
+            if let Some(client) = &client {
+                if let Some(receiver) = client
+                    .subscribe_invalidations(&config.database.table_name)
+                    .await?
+                {
+                    let handle =
+                        Self::spawn_invalidation_listener(receiver, inner.clone(), instance_id);
+                    invalidation_listener = Some(Arc::new(InvalidationListener(handle)));
+                } else {
+                    tracing::warn!("Session invalidation is not supported by the DatabasePool.");
+                }
+            }
+        }
+
+        let timers = SessionTimers {
+            // the first expiry sweep is scheduled one memory purge interval from start-up
+            last_expiry_sweep: config.now() + config.memory.purge_update,
+            // the first database expiry sweep is scheduled one database purge interval from start-up
+            last_database_expiry_sweep: config.now() + config.database.purge_database_update,
+            // the new session counts are first pruned one new session period from start-up
+            last_new_session_prune: config.now() + config.ip_user_agent.new_session_period,
+        };
+
         Ok(Self {
             client,
-            inner: Default::default(),
+            inner,
             config,
-            timers: Arc::new(RwLock::new(SessionTimers {
-                // the first expiry sweep is scheduled one lifetime from start-up
-                last_expiry_sweep: Utc::now() + Duration::try_hours(1).unwrap_or_default(),
-                // the first expiry sweep is scheduled one lifetime from start-up
-                last_database_expiry_sweep: Utc::now() + Duration::try_hours(6).unwrap_or_default(),
-            })),
+            timers: Arc::new(RwLock::new(timers)),
+            instance_id,
+            new_session_counts: Default::default(),
+            pending_changes: Default::default(),
+            request_counter: Default::default(),
+            invalidation_listener,
             #[cfg(feature = "key-store")]
             filter: Arc::new(RwLock::new(filter)),
         })
     }
 
+    /// Removes Sessions from memory when another store sharing the database changed or destroyed them.
+    /// They get loaded again from the database on their next request. Sessions still used by a
+    /// request are marked instead and dropped once unused.
+    fn spawn_invalidation_listener(
+        mut receiver: UnboundedReceiver<String>,
+        inner: Arc<DashMap<String, SessionData>>,
+        instance_id: Uuid,
+    ) -> JoinHandle<()> {
+        let instance_id = instance_id.to_string();
+
+        tokio::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                if let Some((sender, id)) = message.split_once(':') {
+                    if sender == instance_id {
+                        continue;
+                    }
+
+                    // Every Session was destroyed by clear_all on another server so the ones
+                    // still in use are destroyed by their request like clear_all does locally.
+                    if id == INVALIDATE_ALL {
+                        inner.retain(|_, session| {
+                            session.destroy();
+                            session.is_parallel()
+                        });
+                        tracing::debug!("All Sessions invalidated by another server");
+                        continue;
+                    }
+
+                    if inner
+                        .remove_if(id, |_, session| !session.is_parallel())
+                        .is_some()
+                    {
+                        tracing::debug!(session.id = %id, "Session invalidated by another server");
+                    } else if let Some(mut session) = inner.get_mut(id) {
+                        tracing::debug!(session.id = %id, "Session in use invalidated by another server");
+                        session.invalidated = true;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Tells the other stores sharing the database to drop their copy of the Session.
+    /// Errors are logged as the Session itself was already saved or removed.
+    pub(crate) async fn publish_invalidation(&self, id: &str) {
+        if !self.config.database.invalidation {
+            return;
+        }
+
+        if let Some(client) = &self.client {
+            let message = format!("{}:{}", self.instance_id, id);
+
+            if let Err(err) = client
+                .publish_invalidation(&message, &self.config.database.table_name)
+                .await
+            {
+                tracing::error!(err = %err, "Failed to publish Session invalidation.");
+            }
+        }
+    }
+
     /// Used to create and Fill the Filter.
     #[cfg(feature = "key-store")]
     pub(crate) async fn create_filter(
@@ -144,7 +306,8 @@ where
         self.client.is_some()
     }
 
-    /// Cleans Expired sessions from the Database based on Utc::now().
+    /// Cleans Expired sessions from the Database based on the current time minus the cleanup grace.
+    /// With a cleanup batch size set they are removed one batch at a time.
     ///
     /// If client is None it will return Ok(()).
     ///
@@ -165,14 +328,424 @@ where
     #[inline]
     pub async fn cleanup(&self) -> Result<Vec<String>, SessionError> {
         if let Some(client) = &self.client {
-            Ok(client
-                .delete_by_expiry(&self.config.database.table_name)
-                .await?)
+            let before = self.config.now() - self.config.database.cleanup_grace;
+            let table_name = &self.config.database.table_name;
+
+            let Some(batch_size) = self.config.database.cleanup_batch_size else {
+                return self
+                    .bounded(
+                        "cleanup",
+                        client.delete_expired_before(before.timestamp(), table_name),
+                    )
+                    .await;
+            };
+
+            let pause = self.config.database.cleanup_batch_pause.to_std().ok();
+            let mut expired = Vec::new();
+
+            loop {
+                let removed = self
+                    .bounded(
+                        "cleanup",
+                        client.delete_expired_batch(before.timestamp(), batch_size, table_name),
+                    )
+                    .await?;
+
+                // A short batch means nothing expired is left.
+                let finished = removed.len() < batch_size;
+                expired.extend(removed);
+
+                if finished {
+                    break;
+                }
+
+                if let Some(pause) = pause.filter(|pause| !pause.is_zero()) {
+                    tokio::time::sleep(pause).await;
+                }
+            }
+
+            Ok(expired)
         } else {
             Ok(Vec::new())
         }
     }
 
+    /// Runs a database call within the backend timeout and warns when it takes longer
+    /// than the slow backend threshold.
+    ///
+    /// # Errors
+    /// - ['SessionError::BackendTimeout'] is returned if the call did not finish within the backend timeout.
+    pub(crate) async fn bounded<R>(
+        &self,
+        call: &'static str,
+        future: impl Future<Output = Result<R, SessionError>>,
+    ) -> Result<R, SessionError> {
+        let started = Instant::now();
+        let result = match self
+            .config
+            .database
+            .backend_timeout
+            .and_then(|timeout| timeout.to_std().ok())
+        {
+            Some(timeout) => tokio::time::timeout(timeout, future)
+                .await
+                .unwrap_or(Err(SessionError::BackendTimeout(call))),
+            None => future.await,
+        };
+
+        if let Some(threshold) = self
+            .config
+            .database
+            .slow_backend_threshold
+            .and_then(|threshold| threshold.to_std().ok())
+        {
+            let elapsed = started.elapsed();
+
+            if elapsed > threshold {
+                tracing::warn!(
+                    call,
+                    elapsed_ms = elapsed.as_millis() as u64,
+                    "Session database call was slow"
+                );
+            }
+        }
+
+        result
+    }
+
+    /// Removes expired Sessions from memory and the database right away instead of waiting
+    /// for a request to trigger the next sweep. Useful to call from a background task on
+    /// low traffic sites. The next request triggered sweeps are rescheduled from now.
+    ///
+    /// Sessions are removed from memory once their memory lifespan ends and from the database
+    /// once they expire. This is safe to call while requests are being handled.
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    ///
+    /// tokio::spawn(async move {
+    ///     loop {
+    ///         tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+    ///         let _ = session_store.purge_expired().await;
+    ///     }
+    /// });
+    /// ```
+    ///
+    pub async fn purge_expired(&self) -> Result<(), SessionError> {
+        tracing::info!("Session Memory Cleaning Started");
+        let removed = self.sweep_memory().await;
+        tracing::info!(sweep.removed = removed, "Session Memory Cleaning Finished");
+
+        if self.is_persistent() {
+            tracing::info!("Session Database Cleaning Started");
+            let removed = self.sweep_database().await?;
+            tracing::info!(
+                sweep.removed = removed,
+                "Session Database Cleaning Finished"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Loads the Session from the database into memory, replacing any copy already in memory.
+    /// Useful to load a Session again after it was unloaded or changed by another server.
+    /// Returns false if the Session does not exist within the database.
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    /// - ['SessionError::SerdeJson'] is returned if it failed to deserialize the sessions data.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// let loaded = session_store.reload(session_id).await.unwrap();
+    /// ```
+    ///
+    pub async fn reload(&self, id: &str) -> Result<bool, SessionError> {
+        let Some(mut sess) = self.load_session(id.to_owned()).await? else {
+            return Ok(false);
+        };
+
+        sess.autoremove = self.config.now() + self.memory_lifespan(&sess);
+        sess.accessed = self.config.now();
+        sess.store = true;
+
+        // Keep the requests currently using the Session so it is not unloaded under them.
+        if let Some(current) = self.inner.get(id) {
+            sess.requests = current.requests;
+            sess.store = current.store;
+            sess.stale_flashes = current.stale_flashes.clone();
+        }
+
+        self.inner.insert(id.to_owned(), sess);
+        self.evict_memory().await;
+        Ok(true)
+    }
+
+    /// Saves every Session in memory to the database. Meant to be called from a graceful shutdown
+    /// hook so Session changes not yet saved are not lost when the process exits.
+    /// Returns how many Sessions were saved.
+    ///
+    /// Sessions that are destroyed, not storable or failed to load are skipped.
+    /// A Session failing to save is logged and the rest are still saved.
+    ///
+    /// # Errors
+    /// - The first error a Session failed to save with is returned once every Session was tried.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    ///
+    /// axum::serve(listener, app)
+    ///     .with_graceful_shutdown(shutdown_signal())
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// session_store.flush_all().await.unwrap();
+    /// ```
+    ///
+    pub async fn flush_all(&self) -> Result<usize, SessionError> {
+        if !self.is_persistent() {
+            return Ok(0);
+        }
+
+        // Clone them first so no DashMap locks are held while awaiting the database.
+        let sessions: Vec<SessionData> = self
+            .inner
+            .iter()
+            .filter(|session| {
+                !session.destroy
+                    && !session.load_failed
+                    && (!self.config.session_mode.is_opt_in() || session.store)
+            })
+            .map(|session| session.clone())
+            .collect();
+
+        let mut flushed = 0;
+        let mut first_error = None;
+
+        for session in sessions {
+            match self.store_session(&session).await {
+                Ok(()) => flushed += 1,
+                Err(err) => {
+                    tracing::error!(err = %err, session.id = %session.id, "Failed to flush Session to the database.");
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+
+        tracing::info!(flushed, "Sessions flushed to the database");
+
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(flushed),
+        }
+    }
+
+    /// Removes the Sessions whose memory lifespan ended from memory and schedules the next memory sweep.
+    /// Returns how many Sessions were removed.
+    pub(crate) async fn sweep_memory(&self) -> usize {
+        let current_time = self.config.now();
+
+        // Only unload these from filter if the Client is None as this means no database.
+        // Otherwise only unload from the filter if removed from the Database.
+        #[cfg(feature = "key-store")]
+        if self.is_persistent()
+            && self.auto_handles_expiry()
+            && self.config.memory.use_bloom_filters
+        {
+            let mut filter = self.filter.write().await;
+            self.inner
+                .iter()
+                .filter(|r| r.autoremove < current_time)
+                .for_each(|r| filter.remove(r.key().as_bytes()));
+        }
+
+        let mut evicted = Vec::new();
+        self.inner.retain(|k, v| {
+            let keep = v.autoremove > current_time;
+
+            if !keep {
+                evicted.push(k.clone());
+            }
+
+            keep
+        });
+        let removed = evicted.len();
+        self.notify_evicted(&evicted);
+
+        self.timers.write().await.last_expiry_sweep =
+            current_time + self.config.memory.purge_update;
+        self.emit_event(SessionEvent::MemorySweep { removed });
+        removed
+    }
+
+    /// Spawns a tokio task that sweeps expired Sessions from memory and the database every
+    /// interval, starting right away, so low traffic services are cleaned up on time and requests
+    /// do not run the sweeps themselves. Each sweep pushes back the next sweep a request would run
+    /// by the purge intervals, so with an interval shorter than them requests never sweep.
+    /// Sweeping twice only removes what already expired so the two never conflict.
+    ///
+    /// The task runs until the returned JoinHandle is aborted.
+    ///
+    /// # Panics
+    /// Panics if the interval is not positive.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    /// use chrono::Duration;
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// let sweeper = session_store.spawn_sweeper(Duration::minutes(5));
+    /// ```
+    ///
+    pub fn spawn_sweeper(&self, interval: Duration) -> JoinHandle<()> {
+        let period = interval
+            .to_std()
+            .ok()
+            .filter(|period| !period.is_zero())
+            .expect("The Session sweeper interval must be positive.");
+        let store = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+
+                if !store.config.memory.memory_lifespan.is_zero() {
+                    let removed = store.sweep_memory().await;
+                    tracing::info!(
+                        sweep.removed = removed,
+                        "Background Session Memory Cleaning Finished"
+                    );
+                }
+
+                if store.is_persistent() {
+                    match store.sweep_database().await {
+                        Ok(removed) => tracing::info!(
+                            sweep.removed = removed,
+                            "Background Session Database Cleaning Finished"
+                        ),
+                        Err(err) => tracing::error!(
+                            err = %err,
+                            "failed to remove expired session's from database"
+                        ),
+                    }
+                }
+            }
+        })
+    }
+
+    /// Once more than max_memory_sessions are in memory unloads Sessions in the order of the EvictionPolicy
+    /// until 90% of max_memory_sessions remain. Sessions in use by a request are kept, as are
+    /// opted out Sessions while a database is used since they are not stored and would be lost.
+    /// Returns how many Sessions were unloaded.
+    pub(crate) async fn evict_memory(&self) -> usize {
+        let Some(max) = self.config.memory.max_memory_sessions else {
+            return 0;
+        };
+        if self.inner.len() <= max {
+            return 0;
+        }
+
+        // Unload down to 90% of the cap so the scan below runs once per many new Sessions
+        // instead of on every new Session once the cap is reached.
+        let over = self.inner.len().saturating_sub(max - max / 10);
+        let keep_unstored = self.is_persistent() && self.config.session_mode.is_opt_in();
+        let evictable = |session: &SessionData| {
+            !session.is_parallel() && (session.store || session.load_failed || !keep_unstored)
+        };
+        let mut idle: Vec<(String, DateTime<Utc>)> = self
+            .inner
+            .iter()
+            .filter(|session| evictable(session))
+            .map(|session| {
+                let order = match self.config.memory.eviction_policy {
+                    EvictionPolicy::LeastRecentlyUsed => session.accessed,
+                    EvictionPolicy::SoonestAutoremove => session.autoremove,
+                };
+                (session.key().clone(), order)
+            })
+            .collect();
+
+        // Only the Sessions to unload need to be found, not sorted.
+        if idle.len() > over {
+            idle.select_nth_unstable_by_key(over, |(_, order)| *order);
+            idle.truncate(over);
+        }
+
+        let mut evicted = Vec::new();
+
+        for (id, _) in idle {
+            if self
+                .inner
+                .remove_if(&id, |_, session| evictable(session))
+                .is_some()
+            {
+                evicted.push(id);
+            }
+        }
+
+        // Without a database the filter only knows of the Sessions kept in memory.
+        #[cfg(feature = "key-store")]
+        if !self.is_persistent() && self.config.memory.use_bloom_filters {
+            let mut filter = self.filter.write().await;
+            evicted.iter().for_each(|id| filter.remove(id.as_bytes()));
+        }
+
+        tracing::debug!(
+            evicted = evicted.len(),
+            "Sessions unloaded from memory to stay within max_memory_sessions"
+        );
+        self.notify_evicted(&evicted);
+        evicted.len()
+    }
+
+    /// Calls the on_evict hook for each Session ID unloaded from memory.
+    fn notify_evicted(&self, evicted: &[String]) {
+        if let Some(on_evict) = &self.config.on_evict {
+            evicted.iter().for_each(|id| on_evict(id));
+        }
+    }
+
+    /// Removes the expired Sessions from the database and the filter and schedules the next database sweep.
+    /// Returns how many Sessions were removed.
+    pub(crate) async fn sweep_database(&self) -> Result<usize, SessionError> {
+        //Remove any old keys that expired and Remove them from our loaded filter.
+        let expired = self.cleanup().await?;
+
+        #[cfg(feature = "key-store")]
+        if !self.auto_handles_expiry() {
+            let mut filter = self.filter.write().await;
+            expired.iter().for_each(|id| filter.remove(id.as_bytes()));
+        }
+
+        self.timers.write().await.last_database_expiry_sweep =
+            self.config.now() + self.config.database.purge_database_update;
+        self.emit_event(SessionEvent::DatabaseSweep {
+            removed: expired.len(),
+        });
+        Ok(expired.len())
+    }
+
     /// Returns count of existing sessions within database.
     ///
     /// If client is None it will return Ok(0).
@@ -201,6 +774,73 @@ where
         Ok(0)
     }
 
+    /// Returns the ids of the Sessions loaded in memory, in no particular order.
+    /// Useful for admin pages. Sessions only in the database are not included.
+    ///
+    /// Each part of the memory store is only locked while its ids are copied.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// let ids = session_store.active_session_ids();
+    /// ```
+    ///
+    #[inline]
+    pub fn active_session_ids(&self) -> Vec<String> {
+        self.inner
+            .iter()
+            .map(|session| session.key().clone())
+            .collect()
+    }
+
+    /// Returns how many Sessions are loaded in memory.
+    /// Use `count` for the number of Sessions saved within the database.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// let count = session_store.session_count();
+    /// ```
+    ///
+    #[inline]
+    pub fn session_count(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns the ids of every Session in memory and in the database, in no particular order.
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    /// let ids = session_store.all_ids().await.unwrap();
+    /// ```
+    ///
+    pub async fn all_ids(&self) -> Result<Vec<String>, SessionError> {
+        let mut ids = self.active_session_ids();
+
+        if let Some(client) = &self.client {
+            for id in client.get_ids(&self.config.database.table_name).await? {
+                if !self.inner.contains_key(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+
+        Ok(ids)
+    }
+
     /// private internal function that loads a session's data from the database using a UUID string.
     ///
     /// If client is None it will return Ok(None).
@@ -208,6 +848,8 @@ where
     /// # Errors
     /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
     /// - ['SessionError::SerdeJson'] is returned if it failed to deserialize the sessions data.
+    /// - ['SessionError::MessagePackDecode'] is returned if it failed to deserialize MessagePack sessions data.
+    /// - ['SessionError::BackendTimeout'] is returned if the database did not respond within the backend timeout.
     ///
     /// # Examples
     /// ```rust ignore
@@ -227,30 +869,50 @@ where
         cookie_value: String,
     ) -> Result<Option<SessionData>, SessionError> {
         if let Some(client) = &self.client {
-            let result: Option<String> = client
-                .load(&cookie_value, &self.config.database.table_name)
+            let result: Option<(String, Option<i64>)> = self
+                .bounded(
+                    "load",
+                    client.load_with_expiry(&cookie_value, &self.config.database.table_name),
+                )
                 .await?;
 
             if let Ok(uuid) = Uuid::parse_str(&cookie_value) {
+                let expires = result.as_ref().and_then(|(_, expires)| *expires);
+
                 if let Some(mut session) = result
-                    .map(|session| {
-                        if let Some(key) = self.config.database.database_key.as_ref() {
-                            serde_json::from_str::<SessionData>(
-                                &match encrypt::decrypt(&uuid.to_string(), &session, key) {
-                                    Ok(v) => v,
-                                    Err(err) => {
-                                        tracing::error!(err = %err, "Failed to decrypt Session data from database.");
-                                        String::new()
-                                    }
+                    .map(|(session, _)| {
+                        let session = if let Some(key) = self.config.database.database_key.as_ref() {
+                            match encrypt::decrypt(&uuid.to_string(), &session, key) {
+                                Ok(v) => v,
+                                Err(err) => {
+                                    tracing::error!(err = %err, "Failed to decrypt Session data from database.");
+                                    String::new()
                                 }
-                            )
+                            }
                         } else {
-                            serde_json::from_str::<SessionData>(&session)
-                        }
+                            session
+                        };
+
+                        #[cfg(feature = "compression")]
+                        let session = compress::decompress(session)?;
+
+                        serializer::deserialize(&session)
                     })
                     .transpose()?
                 {
                     session.id = uuid;
+
+                    // The expiration is stored apart from the data. Databases that can not return it
+                    // get it set from the lifespan.
+                    match expires.and_then(|expires| DateTime::from_timestamp(expires, 0)) {
+                        Some(expires) => session.expires = expires,
+                        None => session.set_expires(&self.config),
+                    }
+
+                    if let Some(on_load) = &self.config.on_load {
+                        on_load(&mut session);
+                    }
+
                     return Ok(Some(session));
                 }
             }
@@ -266,6 +928,8 @@ where
     /// # Errors
     /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
     /// - ['SessionError::SerdeJson'] is returned if it failed to serialize the sessions data.
+    /// - ['SessionError::MessagePackEncode'] is returned if it failed to serialize MessagePack sessions data.
+    /// - ['SessionError::BackendTimeout'] is returned if the database did not respond within the backend timeout.
     ///
     /// # Examples
     /// ```rust ignore
@@ -285,25 +949,75 @@ where
     pub(crate) async fn store_session(&self, session: &SessionData) -> Result<(), SessionError> {
         if let Some(client) = &self.client {
             let uuid = session.id.to_string();
-            client
-                .store(
+            let value = if let Some(on_store) = &self.config.on_store {
+                let mut session = session.clone();
+                on_store(&mut session);
+                serializer::serialize(&session, self.config.database.serializer)?
+            } else {
+                serializer::serialize(session, self.config.database.serializer)?
+            };
+
+            if let Some(max_size) = self.config.database.max_session_size {
+                if value.len() > max_size {
+                    return Err(SessionError::SessionTooLarge(value.len(), max_size));
+                }
+            }
+
+            #[cfg(feature = "compression")]
+            let value = compress::compress(value, self.config.database.compression_threshold)?;
+
+            let value = if let Some(key) = self.config.database.database_key.as_ref() {
+                encrypt::encrypt(&uuid, &value, key).map_err(|e| {
+                    SessionError::GenericNotSupportedError(format!(
+                        "Error: {} Occured when encrypting a Session.",
+                        e
+                    ))
+                })?
+            } else {
+                value
+            };
+
+            self.bounded(
+                "store",
+                client.store(
                     &uuid,
-                    &if let Some(key) = self.config.database.database_key.as_ref() {
-                        encrypt::encrypt(&uuid, &serde_json::to_string(session)?, key).map_err(
-                            |e| {
-                                SessionError::GenericNotSupportedError(format!(
-                                    "Error: {} Occured when encrypting a Session.",
-                                    e
-                                ))
-                            },
-                        )?
-                    } else {
-                        serde_json::to_string(session)?
-                    },
+                    &value,
                     session.expires.timestamp(),
                     &self.config.database.table_name,
-                )
-                .await?;
+                ),
+            )
+            .await?;
+            self.publish_invalidation(&uuid).await;
+        }
+
+        Ok(())
+    }
+
+    /// Creates the Session's database table if it does not exist.
+    /// `SessionStore::new` already calls this, so it is only needed if the table
+    /// could have been removed while the store is running, like after a database reset.
+    ///
+    /// If client is None it will return Ok(()).
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config.clone()).await.unwrap();
+    ///
+    /// async {
+    ///     let _ = session_store.initiate().await.unwrap();
+    /// };
+    /// ```
+    ///
+    #[inline]
+    pub async fn initiate(&self) -> Result<(), SessionError> {
+        if let Some(client) = &self.client {
+            client.initiate(&self.config.database.table_name).await?;
         }
 
         Ok(())
@@ -338,6 +1052,221 @@ where
         Ok(())
     }
 
+    /// Destroys every session in memory and the database at once, such as to log everyone out
+    /// during a security incident. Clients start over with an empty Session under a new Session ID
+    /// on their next request. With invalidation enabled the other servers drop their copies as well.
+    ///
+    /// Sessions in memory that are still being used by a request are set to be Destroyed
+    /// so that request deletes it and its cookies upon the Response Phase instead of saving it.
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    ///
+    /// async {
+    ///     let _ = session_store.clear_all().await.unwrap();
+    /// };
+    /// ```
+    ///
+    pub async fn clear_all(&self) -> Result<(), SessionError> {
+        // Hold the filter for the whole clear so no new Session ID is handed out part way through.
+        #[cfg(feature = "key-store")]
+        let mut filter = self.filter.write().await;
+
+        let mut removed = Vec::new();
+        self.inner.retain(|id, session| {
+            if session.is_parallel() {
+                session.destroy();
+                true
+            } else {
+                removed.push(id.clone());
+                false
+            }
+        });
+
+        #[cfg(feature = "key-store")]
+        if !self.is_persistent() && self.config.memory.use_bloom_filters {
+            removed.iter().for_each(|id| filter.remove(id.as_bytes()));
+        }
+
+        if let Some(client) = &self.client {
+            client.delete_all(&self.config.database.table_name).await?;
+        }
+
+        self.publish_invalidation(INVALIDATE_ALL).await;
+        tracing::warn!(removed = removed.len(), "All Sessions were destroyed");
+        Ok(())
+    }
+
+    /// Destroys all sessions given the owner using `session.set_owner()`.
+    /// Useful to log a user out everywhere.
+    ///
+    /// Sessions in memory that are still being used by a request are set to be Destroyed
+    /// so that request deletes it and its cookies upon the Response Phase instead of saving it.
+    /// Sessions in the database are found by loading and decoding every stored session, so this
+    /// costs one database load per row and can be slow with a large database. Rows that fail to
+    /// load are logged and skipped. A session only stored in the database that is loaded by a
+    /// request while this is running can still be saved back by that request.
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// use axum_session::{SessionNullPool, SessionConfig, SessionStore};
+    ///
+    /// let config = SessionConfig::default();
+    /// let session_store = SessionStore::<SessionNullPool>::new(None, config).await.unwrap();
+    ///
+    /// async {
+    ///     let _ = session_store.destroy_all_by_owner("user-1").await.unwrap();
+    /// };
+    /// ```
+    ///
+    pub async fn destroy_all_by_owner(&self, owner: &str) -> Result<(), SessionError> {
+        let owned: Vec<String> = self
+            .inner
+            .iter()
+            .filter(|session| session.owner.as_deref() == Some(owner))
+            .map(|session| session.key().clone())
+            .collect();
+
+        for id in owned {
+            self.destroy_by_id(id).await?;
+        }
+
+        if let Some(client) = &self.client {
+            for id in client.get_ids(&self.config.database.table_name).await? {
+                let is_owner = self
+                    .load_stored(&id)
+                    .await
+                    .is_some_and(|session| session.owner.as_deref() == Some(owner));
+
+                if is_owner {
+                    self.destroy_by_id(id).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Destroys the owner's oldest sessions so at most `max` of them are left.
+    /// Call it after `session.set_owner()` when logging a user in to limit how many
+    /// devices they can be logged in on. Returns how many sessions were destroyed.
+    ///
+    /// Sessions are ordered by when their owner was last set, so the Session that just logged in
+    /// is always kept when `max` is at least 1. Like `destroy_all_by_owner`, Sessions only stored
+    /// in the database are found by loading each stored session, and rows that fail to load are
+    /// logged and skipped. Expired Sessions do not count towards the limit.
+    ///
+    /// # Errors
+    /// - ['SessionError::Sqlx'] is returned if database connection has failed or user does not have permissions.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// async fn login(session: Session<SessionPgPool>, store: SessionStore<SessionPgPool>) {
+    ///     session.set_owner(Some("user-1".to_owned()));
+    ///     let _ = store.enforce_session_limit("user-1", 3).await.unwrap();
+    /// }
+    /// ```
+    ///
+    pub async fn enforce_session_limit(
+        &self,
+        owner: &str,
+        max: usize,
+    ) -> Result<usize, SessionError> {
+        let now = self.config.now();
+        let mut owned: Vec<(String, DateTime<Utc>)> = self
+            .inner
+            .iter()
+            .filter(|session| {
+                session.owner.as_deref() == Some(owner)
+                    && !session.destroy
+                    && session.validate_at(now)
+            })
+            .map(|session| (session.key().clone(), session.owned_since()))
+            .collect();
+
+        if let Some(client) = &self.client {
+            for id in client.get_ids(&self.config.database.table_name).await? {
+                if self.inner.contains_key(&id) {
+                    continue;
+                }
+
+                if let Some(session) = self.load_stored(&id).await {
+                    if session.owner.as_deref() == Some(owner) && session.validate_at(now) {
+                        owned.push((id, session.owned_since()));
+                    }
+                }
+            }
+        }
+
+        // Newest first so the oldest are past the limit.
+        owned.sort_by_key(|session| Reverse(session.1));
+        let oldest = owned.split_off(max.min(owned.len()));
+        let destroyed = oldest.len();
+
+        for (id, _) in oldest {
+            self.destroy_by_id(id).await?;
+        }
+
+        if destroyed > 0 {
+            tracing::info!(
+                sessions.destroyed = destroyed,
+                "Sessions over the owner's limit were destroyed"
+            );
+        }
+
+        Ok(destroyed)
+    }
+
+    /// Loads a stored session for the owner functions. Rows that fail to load, such as ones
+    /// that can no longer be decoded, are logged and skipped so they do not stop the others.
+    async fn load_stored(&self, id: &str) -> Option<SessionData> {
+        match self.load_session(id.to_owned()).await {
+            Ok(session) => session,
+            Err(err) => {
+                tracing::error!(
+                    err = %err,
+                    session.id = %id,
+                    "Stored Session failed to load and was skipped"
+                );
+                None
+            }
+        }
+    }
+
+    /// Destroys a session in memory and the database.
+    /// A session in memory that is still being used by a request is set to be Destroyed
+    /// so that request deletes it and its cookies upon the Response Phase.
+    async fn destroy_by_id(&self, id: String) -> Result<(), SessionError> {
+        let in_use = if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.destroy();
+            instance.is_parallel()
+        } else {
+            false
+        };
+
+        if !in_use {
+            self.inner.remove(&id);
+        }
+
+        #[cfg(feature = "key-store")]
+        if !in_use && self.config.memory.use_bloom_filters {
+            let mut filter = self.filter.write().await;
+            filter.remove(id.as_bytes());
+        }
+
+        self.database_remove_session(id).await
+    }
+
     /// Deletes all sessions in Memory.
     /// This will also Clear those keys from the filter cache if a persistent database does not exist.
     ///
@@ -367,65 +1296,438 @@ where
         self.inner.clear();
     }
 
+    /// Counts a new Session against the ip's new_session_limit.
+    /// Returns false if the ip already created the most new Sessions allowed within the period.
+    /// Requests without an ip are not limited as they can not be told apart.
+    pub(crate) async fn allow_new_session(&self, ip: Option<String>) -> bool {
+        let Some(limit) = self.config.ip_user_agent.new_session_limit else {
+            return true;
+        };
+        let Some(ip) = ip else {
+            tracing::debug!("No ip was found for the request so its new Session is not limited");
+            return true;
+        };
+        let current_time = self.config.now();
+        let period = self.config.ip_user_agent.new_session_period;
+
+        // Once per period drop the ip's whose period ended so the counts do not grow forever.
+        {
+            let mut timers = self.timers.write().await;
+
+            if timers.last_new_session_prune <= current_time {
+                self.new_session_counts
+                    .retain(|_k, (started, _)| *started + period > current_time);
+                timers.last_new_session_prune = current_time + period;
+            }
+        }
+
+        let mut count = self
+            .new_session_counts
+            .entry(ip)
+            .or_insert((current_time, 0));
+
+        if count.0 + period <= current_time {
+            *count = (current_time, 0);
+        }
+
+        if count.1 >= limit {
+            false
+        } else {
+            count.1 += 1;
+            true
+        }
+    }
+
+    /// Inserts a newly created Session's data into the memory store.
+    /// Every new Session goes through here so they are all logged, counted and evicted the same way.
+    pub(crate) async fn insert_new_session(&self, sess: SessionData) {
+        let id = sess.id;
+        self.inner.insert(id.to_string(), sess);
+        tracing::debug!(session.id = %id, "Session created");
+        self.emit_event(SessionEvent::Created);
+        self.evict_memory().await;
+    }
+
+    /// Loads the session's data from the database or creates new data if it did not exist.
+    /// The data is then inserted into the memory store.
+    /// Creating new data is counted against the Session's limit_ip's new_session_limit.
+    ///
+    /// # Errors
+    /// - The load error is returned if loading failed and the LoadErrorPolicy is Fail.
+    /// - [`SessionError::TooManyNewSessions`] if the ip already created the most new Sessions allowed.
+    pub(crate) async fn load_or_create_session(
+        &self,
+        session: &Session<T>,
+    ) -> Result<(), SessionError> {
+        let (id, storable) = (session.id, session.storable);
+        let mut sess = match self.load_session(id.inner()).await {
+            Ok(Some(sess)) => sess,
+            Ok(None) => {
+                // Unknown Session ID's would otherwise let a client create Sessions without a limit.
+                if !self.allow_new_session(session.limit_ip.clone()).await {
+                    return Err(SessionError::TooManyNewSessions);
+                }
+
+                tracing::info!(
+                    "Session {} did not exist in Database. So it was Recreated.",
+                    id
+                );
+                let mut sess = SessionData::new(id.0, storable, &self.config);
+                // The ID could have been cleared or made up by the client so the Session gets a new one.
+                sess.renew = true;
+                sess
+            }
+            Err(err) => match self.config.database.load_error_policy {
+                LoadErrorPolicy::Fail => return Err(err),
+                LoadErrorPolicy::NewSession => {
+                    tracing::error!(err = %err, "Session {} failed to load from the Database. So it was Recreated.", id);
+                    let mut sess = SessionData::new(id.0, storable, &self.config);
+                    // A timed out load may still hold a valid Session so it must not be saved over.
+                    sess.load_failed = matches!(err, SessionError::BackendTimeout(_));
+                    sess
+                }
+                LoadErrorPolicy::MemoryOnly => {
+                    tracing::error!(err = %err, "Session {} failed to load from the Database. So it is only kept in memory.", id);
+                    let mut sess = SessionData::new(id.0, storable, &self.config);
+                    sess.load_failed = true;
+                    sess
+                }
+            },
+        };
+
+        if sess.service_expiration(&self.config) {
+            self.emit_event(SessionEvent::Expired);
+        }
+
+        sess.service_rotation(&self.config);
+
+        sess.autoremove = self.config.now() + self.memory_lifespan(&sess);
+        sess.store = storable;
+        sess.update = true;
+        sess.requests = 1;
+        sess.accessed = self.config.now();
+        sess.age_flashes(session.request);
+        self.inner.insert(id.inner(), sess);
+        self.apply_pending_changes(&id.inner());
+        self.evict_memory().await;
+        Ok(())
+    }
+
+    /// Checks if Sessions are loaded by the handler using `session.load()` instead of before it.
+    #[inline]
+    pub(crate) fn is_lazy_loading(&self) -> bool {
+        self.config.database.lazy_loading
+            && self.is_persistent()
+            && !self.config.ip_user_agent.is_binding()
+    }
+
+    /// Checks if a handler changed the lazily loaded Session before it was loaded.
+    #[inline]
+    pub(crate) fn has_pending_changes(&self, id: &str) -> bool {
+        self.pending_changes.contains_key(id)
+    }
+
+    /// Applies a change to the Session's data. When lazy loading has not loaded the Session yet
+    /// the change is kept and applied once it is loaded instead of being dropped.
+    fn change(&self, id: String, change: impl FnOnce(&mut SessionData) + Send + Sync + 'static) {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            change(&mut instance);
+        } else if self.is_lazy_loading() {
+            tracing::debug!(session.id = %id, "Session change kept until the Session is loaded");
+            self.pending_changes
+                .entry(id.clone())
+                .or_default()
+                .push(PendingChange(Box::new(change)));
+
+            // The Session could have been loaded while the change was being kept.
+            if self.inner.contains_key(&id) {
+                self.apply_pending_changes(&id);
+            }
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+        }
+    }
+
+    /// Logs a read of Session data that is not in memory.
+    /// When lazy loading this means the handler did not call `session.load()` first.
+    fn missing_data(&self, method: &str) {
+        if self.is_lazy_loading() {
+            tracing::error!(
+                "Session::{} was used before session.load() so the Session data is missing",
+                method
+            );
+        } else {
+            tracing::warn!("Session data unexpectedly missing");
+        }
+    }
+
+    /// Applies the changes kept for a lazily loaded Session now that it is loaded.
+    pub(crate) fn apply_pending_changes(&self, id: &str) {
+        if let Some(mut instance) = self.inner.get_mut(id) {
+            if let Some((_, changes)) = self.pending_changes.remove(id) {
+                changes
+                    .into_iter()
+                    .for_each(|change| (change.0)(&mut instance));
+            }
+        }
+    }
+
     /// Attempts to load check and clear Data.
     ///
     /// If no session is found returns false.
     pub(crate) fn service_session_data(&self, session: &Session<T>) -> bool {
-        if let Some(mut inner) = self.inner.get_mut(&session.id.inner()) {
-            inner.service_clear(
-                self.config.memory.memory_lifespan,
+        // Sessions that failed to load or were invalidated are dropped once unused so they get loaded again.
+        if self
+            .inner
+            .remove_if(&session.id.inner(), |_, inner| {
+                (inner.load_failed || inner.invalidated) && inner.requests == 0
+            })
+            .is_some()
+        {
+            return false;
+        }
+
+        let expired = if let Some(mut inner) = self.inner.get_mut(&session.id.inner()) {
+            let memory_lifespan = self.memory_lifespan(&inner);
+            let cleared = inner.service_clear(
+                memory_lifespan,
                 self.config.clear_check_on_load,
+                self.config.now(),
             );
-            inner.set_request();
-            return true;
+            let expired = inner.service_expiration(&self.config) || cleared;
+            inner.service_rotation(&self.config);
+            inner.set_request(self.config.now(), session.request);
+            expired
+        } else {
+            return false;
+        };
+
+        // Called after the session is unlocked so the callback can use the store.
+        if expired {
+            self.emit_event(SessionEvent::Expired);
         }
 
-        false
+        true
     }
 
-    #[inline]
-    pub(crate) fn renew(&self, id: String) {
+    /// Binds the session to the ip and user agent hashes if it is not bound to them yet.
+    ///
+    /// Returns false if the session was bound to a different ip or user agent.
+    pub(crate) fn verify_bindings(
+        &self,
+        id: String,
+        ip_binding: Option<&str>,
+        user_agent_binding: Option<&str>,
+    ) -> bool {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.renew();
+            instance.verify_bindings(ip_binding, user_agent_binding)
         } else {
-            tracing::warn!("Session data unexpectedly missing");
+            true
+        }
+    }
+
+    /// Calls the configured event callback if one was Set.
+    #[inline]
+    pub(crate) fn emit_event(&self, event: SessionEvent) {
+        if let Some(callback) = &self.config.event_callback {
+            callback(event);
         }
     }
 
+    #[inline]
+    pub(crate) fn renew(&self, id: String) {
+        self.change(id, |instance| instance.renew());
+    }
+
     #[inline]
     pub(crate) fn destroy(&self, id: String) {
-        if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.destroy();
+        self.change(id, |instance| instance.destroy());
+    }
+
+    #[inline]
+    pub(crate) fn set_longterm(&self, id: String, longterm: bool) {
+        self.change(id, move |instance| instance.set_longterm(longterm));
+    }
+
+    #[inline]
+    pub(crate) fn remember(&self, id: String, duration: Duration) {
+        self.change(id.clone(), move |instance| instance.remember(duration));
+        self.extend_autoremove(&id);
+    }
+
+    /// Gets the Max-Age of the Session's cookie or the configs max_age if the Session is not loaded.
+    #[inline]
+    pub(crate) fn cookie_max_age(&self, id: String) -> Option<Duration> {
+        if let Some(instance) = self.inner.get(&id) {
+            instance.cookie_max_age(&self.config)
         } else {
-            tracing::warn!("Session data unexpectedly missing");
+            self.config.cookie_and_header.cookie_max_age
         }
     }
 
     #[inline]
-    pub(crate) fn set_longterm(&self, id: String, longterm: bool) {
-        if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.set_longterm(longterm);
+    pub(crate) fn set_lifespan(&self, id: String, lifespan: Option<Duration>) {
+        self.change(id.clone(), move |instance| instance.set_lifespan(lifespan));
+        self.extend_autoremove(&id);
+    }
+
+    /// Extends when the Session is unloaded from memory to cover a longer lifespan.
+    fn extend_autoremove(&self, id: &str) {
+        if let Some(mut instance) = self.inner.get_mut(id) {
+            let autoremove = self.config.now() + self.memory_lifespan(&instance);
+
+            if autoremove > instance.autoremove {
+                instance.autoremove = autoremove;
+            }
+        }
+    }
+
+    /// Gets how long a session should be kept in memory for.
+    ///
+    /// Without a database memory is the only storage, so a session with a
+    /// custom lifespan is kept in memory for at least that lifespan.
+    #[inline]
+    pub(crate) fn memory_lifespan(&self, session: &SessionData) -> Duration {
+        let memory_lifespan = self.config.memory.memory_lifespan;
+
+        if self.is_persistent() || session.lifespan.is_none() || memory_lifespan.is_zero() {
+            memory_lifespan
         } else {
-            tracing::warn!("Session data unexpectedly missing");
+            memory_lifespan.max(session.get_lifespan(&self.config))
         }
     }
 
     #[inline]
-    pub(crate) fn set_store(&self, id: String, storable: bool) {
-        if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.set_store(storable);
+    pub(crate) fn set_owner(&self, id: String, owner: Option<String>) {
+        let now = self.config.now();
+        self.change(id, move |instance| instance.set_owner_at(owner, now));
+    }
+
+    #[inline]
+    pub(crate) fn set_authenticated(&self, id: String, authenticated: bool) {
+        self.change(id, move |instance| {
+            instance.set_authenticated(authenticated)
+        });
+    }
+
+    #[inline]
+    pub(crate) fn is_authenticated(&self, id: String) -> bool {
+        if let Some(instance) = self.inner.get(&id) {
+            instance.is_authenticated()
         } else {
-            tracing::warn!("Session data unexpectedly missing");
+            self.missing_data("is_authenticated");
+            false
+        }
+    }
+
+    #[inline]
+    pub(crate) fn get_owner(&self, id: String) -> Option<String> {
+        if let Some(instance) = self.inner.get(&id) {
+            instance.owner.clone()
+        } else {
+            self.missing_data("get_owner");
+            None
         }
     }
 
+    #[inline]
+    pub(crate) fn is_expired(&self, id: String) -> bool {
+        if let Some(instance) = self.inner.get(&id) {
+            instance.is_expired(&self.config)
+        } else {
+            self.missing_data("is_expired");
+            true
+        }
+    }
+
+    #[inline]
+    pub(crate) fn expires_at(&self, id: String) -> Option<DateTime<Utc>> {
+        if let Some(instance) = self.inner.get(&id) {
+            Some(instance.expires_at())
+        } else {
+            self.missing_data("expires_at");
+            None
+        }
+    }
+
+    #[inline]
+    pub(crate) fn created_at(&self, id: String) -> Option<DateTime<Utc>> {
+        if let Some(instance) = self.inner.get(&id) {
+            Some(instance.created_at())
+        } else {
+            self.missing_data("created_at");
+            None
+        }
+    }
+
+    #[inline]
+    pub(crate) fn will_autoremove_at(&self, id: String) -> Option<DateTime<Utc>> {
+        if let Some(instance) = self.inner.get(&id) {
+            Some(instance.will_autoremove_at())
+        } else {
+            self.missing_data("will_autoremove_at");
+            None
+        }
+    }
+
+    #[inline]
+    pub(crate) fn set_store(&self, id: String, storable: bool) {
+        self.change(id, move |instance| instance.set_store(storable));
+    }
+
     #[inline]
     pub(crate) fn update(&self, id: String) {
-        if let Some(mut instance) = self.inner.get_mut(&id) {
+        self.change(id, |instance| instance.update());
+    }
+
+    #[inline]
+    pub(crate) fn renew_expiry(&self, id: String) {
+        let store = self.clone();
+        self.change(id, move |instance| {
+            instance.set_expires(&store.config);
+            instance.autoremove = store.config.now() + store.memory_lifespan(instance);
             instance.update();
-        } else {
-            tracing::warn!("Session data unexpectedly missing");
+        });
+    }
+
+    #[inline]
+    pub(crate) fn load_failed(&self, id: String) -> bool {
+        self.inner
+            .get(&id)
+            .is_some_and(|instance| instance.load_failed)
+    }
+
+    #[inline]
+    pub(crate) fn touch(&self, id: String) {
+        let store = self.clone();
+        self.change(id, move |instance| {
+            instance.set_expires(&store.config);
+            instance.autoremove = store.config.now() + store.memory_lifespan(instance);
+            instance.touched = true;
+        });
+    }
+
+    /// Updates only the Session's expiration within the database.
+    /// Stores the whole Session if the database can not update the expiration alone.
+    pub(crate) async fn touch_session(
+        &self,
+        id: String,
+        expires: DateTime<Utc>,
+    ) -> Result<(), SessionError> {
+        if let Some(client) = &self.client {
+            if !client
+                .touch(&id, expires.timestamp(), &self.config.database.table_name)
+                .await?
+            {
+                let session = self.inner.get(&id).map(|session| session.clone());
+
+                if let Some(session) = session {
+                    self.store_session(&session).await?;
+                }
+            }
         }
+
+        Ok(())
     }
 
     #[inline]
@@ -433,7 +1735,108 @@ where
         if let Some(instance) = self.inner.get(&id) {
             instance.get(key)
         } else {
-            tracing::warn!("Session data unexpectedly missing");
+            self.missing_data("get");
+            None
+        }
+    }
+
+    #[inline]
+    pub(crate) fn get_raw(&self, id: String, key: &str) -> Option<String> {
+        if let Some(instance) = self.inner.get(&id) {
+            instance.get_raw(key)
+        } else {
+            self.missing_data("get_raw");
+            None
+        }
+    }
+
+    #[inline]
+    pub(crate) fn contains_key(&self, id: String, key: &str) -> bool {
+        if let Some(instance) = self.inner.get(&id) {
+            instance.contains_key(key)
+        } else {
+            self.missing_data("contains_key");
+            false
+        }
+    }
+
+    #[inline]
+    pub(crate) fn keys(&self, id: String) -> Vec<String> {
+        if let Some(instance) = self.inner.get(&id) {
+            instance.keys()
+        } else {
+            self.missing_data("keys");
+            Vec::new()
+        }
+    }
+
+    #[inline]
+    pub(crate) fn data_snapshot(&self, id: String) -> HashMap<String, String> {
+        if let Some(instance) = self.inner.get(&id) {
+            instance.data_snapshot()
+        } else {
+            self.missing_data("data_snapshot");
+            HashMap::new()
+        }
+    }
+
+    #[inline]
+    pub(crate) fn get_or_insert_with<N, F>(&self, id: String, key: &str, f: F) -> N
+    where
+        N: Serialize + serde::de::DeserializeOwned,
+        F: FnOnce() -> N,
+    {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.get_or_insert_with(key, f)
+        } else {
+            self.missing_data("get_or_insert_with");
+            f()
+        }
+    }
+
+    #[inline]
+    pub(crate) fn increment(&self, id: String, key: &str, by: i64) -> i64 {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            instance.increment(key, by)
+        } else {
+            self.missing_data("increment");
+            0
+        }
+    }
+
+    #[inline]
+    pub(crate) fn with_data<R>(
+        &self,
+        id: String,
+        f: impl FnOnce(&mut SessionData) -> R,
+    ) -> Option<R> {
+        self.locked(id, "with_data", |instance| {
+            let result = f(instance);
+            instance.update();
+            result
+        })
+    }
+
+    #[inline]
+    pub(crate) fn with_locked<R>(
+        &self,
+        id: String,
+        f: impl FnOnce(&mut SessionData) -> R,
+    ) -> Option<R> {
+        self.locked(id, "with_locked", f)
+    }
+
+    #[inline]
+    fn locked<R>(
+        &self,
+        id: String,
+        method: &str,
+        f: impl FnOnce(&mut SessionData) -> R,
+    ) -> Option<R> {
+        if let Some(mut instance) = self.inner.get_mut(&id) {
+            Some(f(&mut instance))
+        } else {
+            self.missing_data(method);
             None
         }
     }
@@ -447,42 +1850,91 @@ where
         if let Some(mut instance) = self.inner.get_mut(&id) {
             instance.get_remove(key)
         } else {
-            tracing::warn!("Session data unexpectedly missing");
+            self.missing_data("get_remove");
             None
         }
     }
 
     #[inline]
     pub(crate) fn set(&self, id: String, key: &str, value: impl Serialize) {
+        let value = serde_json::to_string(&value).unwrap_or_default();
+        let key = key.to_owned();
+        self.change(id, move |instance| instance.set_raw(&key, value));
+    }
+
+    #[inline]
+    pub(crate) fn set_raw(&self, id: String, key: &str, value: String) {
+        let key = key.to_owned();
+        self.change(id, move |instance| instance.set_raw(&key, value));
+    }
+
+    #[inline]
+    pub(crate) fn flash(&self, id: String, request: u64, key: &str, value: impl Serialize) {
+        let value = serde_json::to_value(&value).unwrap_or_default();
+        let key = key.to_owned();
+        self.change(id, move |instance| {
+            instance.flash_request(&key, value, request)
+        });
+    }
+
+    #[inline]
+    pub(crate) fn take_flash<N: serde::de::DeserializeOwned>(
+        &self,
+        id: String,
+        key: &str,
+    ) -> Option<N> {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.set(key, value);
+            instance.take_flash(key)
         } else {
-            tracing::warn!("Session data unexpectedly missing");
+            self.missing_data("take_flash");
+            None
         }
     }
 
     #[inline]
-    pub(crate) fn remove(&self, id: String, key: &str) {
+    pub(crate) fn csrf_token(&self, id: String) -> String {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.remove(key);
+            instance.csrf_token()
         } else {
-            tracing::warn!("Session data unexpectedly missing");
+            self.missing_data("csrf_token");
+            String::new()
         }
     }
 
+    #[inline]
+    pub(crate) fn verify_csrf(&self, id: String, token: &str) -> bool {
+        if let Some(instance) = self.inner.get(&id) {
+            instance.verify_csrf(token)
+        } else {
+            self.missing_data("verify_csrf");
+            false
+        }
+    }
+
+    #[inline]
+    pub(crate) fn remove(&self, id: String, key: &str) {
+        let key = key.to_owned();
+        self.change(id, move |instance| {
+            instance.remove(&key);
+        });
+    }
+
     #[inline]
     pub(crate) fn clear_session_data(&self, id: String) {
+        self.change(id, |instance| instance.clear());
+    }
+
+    #[inline]
+    pub(crate) fn expire_flashes(&self, id: String, request: u64) {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.clear();
-        } else {
-            tracing::warn!("Session data unexpectedly missing");
+            instance.expire_flashes(request);
         }
     }
 
     #[inline]
-    pub(crate) fn set_session_request(&self, id: String) {
+    pub(crate) fn set_session_request(&self, id: String, request: u64) {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.set_request();
+            instance.set_request(self.config.now(), request);
         } else {
             tracing::warn!("Session data unexpectedly missing");
         }
@@ -529,7 +1981,7 @@ where
     #[inline]
     pub(crate) fn verify(&self, id: String) -> Result<(), SessionError> {
         if let Some(instance) = self.inner.get(&id) {
-            if instance.expires < Utc::now() {
+            if instance.expires < self.config.now() {
                 Err(SessionError::OldSessionError)
             } else {
                 Ok(())
@@ -543,11 +1995,7 @@ where
     #[inline]
     pub(crate) fn update_database_expires(&self, id: String) -> Result<(), SessionError> {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            if instance.longterm {
-                instance.expires = Utc::now() + self.config.max_lifespan;
-            } else {
-                instance.expires = Utc::now() + self.config.lifespan;
-            }
+            instance.set_expires(&self.config);
 
             Ok(())
         } else {
@@ -559,7 +2007,7 @@ where
     #[inline]
     pub(crate) fn update_memory_expires(&self, id: String) -> Result<(), SessionError> {
         if let Some(mut instance) = self.inner.get_mut(&id) {
-            instance.autoremove = Utc::now() + self.config.memory.memory_lifespan;
+            instance.autoremove = self.config.now() + self.config.memory.memory_lifespan;
 
             Ok(())
         } else {
@@ -602,8 +2050,70 @@ where
             client
                 .delete_one_by_id(&id, &self.config.database.table_name)
                 .await?;
+            self.publish_invalidation(&id).await;
         }
 
         Ok(())
     }
 }
+
+/// Copies every unexpired Session from one SessionStore into another, such as when
+/// moving from a SQL database to Redis, so users stay logged in.
+/// Sessions loaded in memory are copied as they are in memory.
+/// Returns how many Sessions were copied.
+///
+/// # Errors
+/// - Any error from loading a Session out of `from` or storing it in `to` is returned.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::migrate_sessions;
+///
+/// let copied = migrate_sessions(&postgres_store, &redis_store).await.unwrap();
+/// ```
+///
+pub async fn migrate_sessions<A, B>(
+    from: &SessionStore<A>,
+    to: &SessionStore<B>,
+) -> Result<usize, SessionError>
+where
+    A: DatabasePool + Clone + Debug + Sync + Send + 'static,
+    B: DatabasePool + Clone + Debug + Sync + Send + 'static,
+{
+    let now = from.config.now();
+    let mut copied = 0;
+
+    for id in from.all_ids().await? {
+        let sess = match from.inner.get(&id) {
+            Some(sess) => Some(sess.clone()),
+            None => from.load_session(id.clone()).await?,
+        };
+
+        let Some(mut sess) = sess else {
+            continue;
+        };
+
+        if !sess.validate_at(now) {
+            continue;
+        }
+
+        sess.requests = 0;
+
+        if to.is_persistent() {
+            to.store_session(&sess).await?;
+        } else {
+            to.inner.insert(id.clone(), sess);
+        }
+
+        #[cfg(feature = "key-store")]
+        if to.config.memory.use_bloom_filters {
+            let mut filter = to.filter.write().await;
+            filter.add(id.as_bytes());
+        }
+
+        copied += 1;
+    }
+
+    tracing::info!(sessions.copied = copied, "Sessions were migrated");
+    Ok(copied)
+}
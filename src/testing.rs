@@ -0,0 +1,61 @@
+//! Helpers for testing handlers that use a Session without a database.
+//! Requires the test-util feature.
+//!
+//! # Examples
+//! ```rust ignore
+//! use axum::{routing::get, Router};
+//! use axum_session::{testing, SessionConfig, SessionNullSession};
+//!
+//! let store = testing::memory_store(SessionConfig::default()).await;
+//! let app = testing::router_with_session(
+//!     Router::new().route("/", get(|session: SessionNullSession| async move {
+//!         session.set("visited", true);
+//!     })),
+//!     store,
+//! );
+//! ```
+
+use crate::{
+    DatabasePool, SessionConfig, SessionLayer, SessionNullPool, SessionNullSessionStore,
+    SessionStore,
+};
+use axum::{response::Response, Router};
+use http::header;
+
+/// Creates a SessionStore that keeps all of its Sessions in memory.
+///
+/// # Panics
+/// Panics if the config is invalid, such as using an invalid cookie name.
+///
+pub async fn memory_store(config: SessionConfig) -> SessionNullSessionStore {
+    SessionStore::<SessionNullPool>::new(None, config)
+        .await
+        .expect("Failed to create the in memory SessionStore.")
+}
+
+/// Adds the SessionLayer for the given store to the router.
+pub fn router_with_session<T>(router: Router, session_store: SessionStore<T>) -> Router
+where
+    T: DatabasePool + Clone + std::fmt::Debug + Sync + Send + 'static,
+{
+    router.layer(SessionLayer::new(session_store))
+}
+
+/// Converts the response's Set-Cookie headers into a Cookie header value
+/// to send along with the next request, like a browser would.
+/// Returns None if the response did not set any cookies.
+pub fn cookie_header(response: &Response) -> Option<String> {
+    let cookies: Vec<&str> = response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.split(';').next())
+        .collect();
+
+    if cookies.is_empty() {
+        None
+    } else {
+        Some(cookies.join("; "))
+    }
+}
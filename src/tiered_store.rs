@@ -0,0 +1,143 @@
+use crate::{DatabasePool, SessionData, SessionError, SessionStore};
+use dashmap::mapref::entry::Entry;
+use std::fmt;
+
+/// Reads and writes Sessions through the SessionStore's memory cache with the database behind it.
+///
+/// Reads are served from memory and only load from the database on a miss, after which the
+/// Session is kept in memory. Writes go to the database first and then update memory, so the
+/// database stays the source of truth. Memory is bounded by `with_max_memory_sessions` and the
+/// configured `EvictionPolicy`, and unloaded by the memory lifespan like any other Session.
+///
+/// The memory cache is shared with the SessionStore, so Sessions read here are also found
+/// by the SessionLayer using the same store and the other way around.
+///
+/// # Examples
+/// ```rust ignore
+/// use axum_session::{SessionConfig, SessionStore, SessionPgPool, TieredStore};
+///
+/// let config = SessionConfig::default().with_max_memory_sessions(Some(10_000));
+/// let session_store = SessionStore::<SessionPgPool>::new(Some(pool.into()), config).await.unwrap();
+/// let tiered = TieredStore::new(session_store.clone());
+/// let session = tiered.load(&id).await.unwrap();
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct TieredStore<T>
+where
+    T: DatabasePool + Clone + fmt::Debug + Sync + Send + 'static,
+{
+    store: SessionStore<T>,
+}
+
+impl<T> TieredStore<T>
+where
+    T: DatabasePool + Clone + fmt::Debug + Sync + Send + 'static,
+{
+    /// Constructs a TieredStore in front of the SessionStore's database.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let tiered = TieredStore::new(session_store.clone());
+    /// ```
+    ///
+    #[inline]
+    pub fn new(store: SessionStore<T>) -> Self {
+        Self { store }
+    }
+
+    /// Gets the SessionStore the TieredStore reads and writes through.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let count = tiered.session_store().session_count();
+    /// ```
+    ///
+    #[inline]
+    pub fn session_store(&self) -> &SessionStore<T> {
+        &self.store
+    }
+
+    /// Gets a copy of the Session from memory or loads it from the database when it is not in memory.
+    /// A loaded Session is kept in memory so the next read does not hit the database.
+    /// A copy in memory that has expired is loaded again as another server could have extended it.
+    /// Returns None if the Session does not exist.
+    ///
+    /// # Errors
+    /// - The database's error is returned if the Session failed to load.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// let session = tiered.load(&id).await.unwrap();
+    /// ```
+    ///
+    pub async fn load(&self, id: &str) -> Result<Option<SessionData>, SessionError> {
+        let now = self.store.config.now();
+
+        if let Some(mut session) = self.store.inner.get_mut(id) {
+            if session.validate_at(now) {
+                session.accessed = now;
+                return Ok(Some(session.clone()));
+            }
+        }
+
+        self.store.inner.remove_if(id, |_, session| {
+            !session.validate_at(now) && !session.is_parallel()
+        });
+
+        let Some(mut session) = self.store.load_session(id.to_owned()).await? else {
+            return Ok(None);
+        };
+
+        session.autoremove = now + self.store.memory_lifespan(&session);
+        session.accessed = now;
+        session.requests = 0;
+        session.update = false;
+        session.store = true;
+
+        let cached = match self.store.inner.entry(id.to_owned()) {
+            Entry::Occupied(entry) if entry.get().validate_at(now) => entry.get().clone(),
+            // The expired copy is still used by a request so leave it and return the loaded one.
+            Entry::Occupied(_) => return Ok(Some(session)),
+            Entry::Vacant(entry) => entry.insert(session).clone(),
+        };
+        self.store.evict_memory().await;
+        Ok(Some(cached))
+    }
+
+    /// Saves the Session to the database and then updates its copy in memory.
+    ///
+    /// # Errors
+    /// - The database's error is returned if the Session failed to save. Memory is left unchanged.
+    ///
+    /// # Examples
+    /// ```rust ignore
+    /// session.set("user-id", 1);
+    /// tiered.store(&session).await.unwrap();
+    /// ```
+    ///
+    pub async fn store(&self, session: &SessionData) -> Result<(), SessionError> {
+        self.store.store_session(session).await?;
+
+        let now = self.store.config.now();
+        let mut cached = session.clone();
+        cached.autoremove = now + self.store.memory_lifespan(&cached);
+        cached.accessed = now;
+        cached.update = false;
+
+        // Requests still using the Session keep counting so it is not unloaded under them.
+        match self.store.inner.get_mut(&session.id.to_string()) {
+            Some(mut current) => {
+                cached.requests = current.requests;
+                *current = cached;
+            }
+            None => {
+                cached.requests = 0;
+                self.store.inner.insert(session.id.to_string(), cached);
+            }
+        }
+
+        self.store.evict_memory().await;
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use axum::{
+    body::Body,
+    http::{header, Request},
+    routing::get,
+    Router,
+};
+use axum_session::{
+    DatabasePool, Session, SessionConfig, SessionLayer, SessionNullPool, SessionStore,
+};
+use http_body_util::BodyExt;
+use std::sync::{Arc, Mutex};
+
+/// A Router with a single route using a Session without a database.
+pub async fn null_app(config: SessionConfig) -> Router {
+    let session_store = SessionStore::<SessionNullPool>::new(None, config)
+        .await
+        .unwrap();
+
+    Router::new()
+        .route("/", get(|| async { "Success" }))
+        .layer(SessionLayer::new(session_store))
+}
+
+/// Routes used to set, get and destroy a value within the Session.
+pub fn session_routes<T>(session_store: SessionStore<T>) -> Router
+where
+    T: DatabasePool + Clone + std::fmt::Debug + Sync + Send + 'static,
+{
+    session_handlers::<T>().layer(SessionLayer::new(session_store))
+}
+
+/// The routes of session_routes without the SessionLayer so tests can add their own
+/// routes before the layer is applied.
+pub fn session_handlers<T>() -> Router
+where
+    T: DatabasePool + Clone + std::fmt::Debug + Sync + Send + 'static,
+{
+    Router::new()
+        .route(
+            "/set",
+            get(|session: Session<T>| async move {
+                session.set("value", 42);
+            }),
+        )
+        .route(
+            "/get",
+            get(|session: Session<T>| async move {
+                session.get::<i32>("value").unwrap_or_default().to_string()
+            }),
+        )
+        .route(
+            "/destroy",
+            get(|session: Session<T>| async move {
+                session.destroy();
+            }),
+        )
+        .route(
+            "/renew",
+            get(|session: Session<T>| async move {
+                session.renew();
+            }),
+        )
+        .route(
+            "/remember",
+            get(|session: Session<T>| async move {
+                session.set("value", 42);
+                session.set_lifespan(chrono::Duration::try_days(30));
+            }),
+        )
+}
+
+pub fn request(uri: &str, cookie: Option<&str>) -> Request<Body> {
+    let mut builder = Request::builder().uri(uri);
+
+    if let Some(cookie) = cookie {
+        builder = builder.header(header::COOKIE, cookie);
+    }
+
+    builder.body(Body::empty()).unwrap()
+}
+
+/// Returns the Set-Cookie header for the given cookie name.
+pub fn set_cookie(response: &axum::response::Response, name: &str) -> Option<String> {
+    let prefix = format!("{}=", name);
+
+    response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .find(|value| value.starts_with(&prefix))
+        .map(|value| value.to_owned())
+}
+
+/// Converts a Set-Cookie header into the name=value pair sent back by the browser.
+pub fn cookie_pair(set_cookie: &str) -> String {
+    set_cookie.split(';').next().unwrap_or_default().to_owned()
+}
+
+pub async fn body_string(response: axum::response::Response) -> String {
+    let bytes = response
+        .into_body()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes()
+        .to_vec();
+    String::from_utf8(bytes).unwrap()
+}
+
+/// A clock that only moves when the test advances it.
+pub fn fake_clock(
+    config: SessionConfig,
+) -> (SessionConfig, Arc<Mutex<chrono::DateTime<chrono::Utc>>>) {
+    let now = Arc::new(Mutex::new(chrono::Utc::now()));
+    let clock = now.clone();
+    (config.with_clock(move || *clock.lock().unwrap()), now)
+}
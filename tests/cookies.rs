@@ -0,0 +1,315 @@
+//! Integration tests for the Session cookie and its attributes.
+//! The Session is sent within a header instead of a cookie with rest_mode so they are skipped for it.
+#![cfg(not(feature = "rest_mode"))]
+
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header, Request},
+    routing::get,
+    Router,
+};
+use axum_session::*;
+use common::*;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn cookie_secure() {
+    let request = || Request::builder().uri("/").body(Body::empty()).unwrap();
+
+    let response = null_app(SessionConfig::new())
+        .await
+        .oneshot(request())
+        .await
+        .unwrap();
+    let cookie = set_cookie(&response, "session").unwrap();
+    assert!(cookie.contains("Secure"));
+
+    let response = null_app(SessionConfig::new().with_secure(false))
+        .await
+        .oneshot(request())
+        .await
+        .unwrap();
+    let cookie = set_cookie(&response, "session").unwrap();
+    assert!(!cookie.contains("Secure"));
+}
+
+#[tokio::test]
+async fn cookie_same_site() {
+    let request = || Request::builder().uri("/").body(Body::empty()).unwrap();
+
+    let response = null_app(SessionConfig::new().with_cookie_same_site(SameSite::Strict))
+        .await
+        .oneshot(request())
+        .await
+        .unwrap();
+    let cookie = set_cookie(&response, "session").unwrap();
+    assert!(cookie.contains("SameSite=Strict"));
+
+    // SameSite=None must always be sent with the secure flag.
+    let config = SessionConfig::new().with_cookie_same_site(SameSite::None);
+    let response = null_app(config).await.oneshot(request()).await.unwrap();
+    let cookie = set_cookie(&response, "session").unwrap();
+    assert!(cookie.contains("SameSite=None"));
+    assert!(cookie.contains("Secure"));
+}
+
+#[tokio::test]
+async fn same_site_none_requires_secure() {
+    let config = SessionConfig::new()
+        .with_cookie_same_site(SameSite::None)
+        .with_secure(false);
+    assert!(matches!(
+        SessionStore::<SessionNullPool>::new(None, config).await,
+        Err(SessionError::InsecureSameSiteNone)
+    ));
+
+    let config = SessionConfig::new()
+        .with_cookie_same_site(SameSite::None)
+        .with_secure(true);
+    assert!(SessionStore::<SessionNullPool>::new(None, config)
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn cookie_http_only() {
+    let request = || Request::builder().uri("/").body(Body::empty()).unwrap();
+
+    let response = null_app(SessionConfig::new())
+        .await
+        .oneshot(request())
+        .await
+        .unwrap();
+    let cookie = set_cookie(&response, "session").unwrap();
+    assert!(cookie.contains("HttpOnly"));
+
+    let response = null_app(SessionConfig::new().with_http_only(false))
+        .await
+        .oneshot(request())
+        .await
+        .unwrap();
+    let cookie = set_cookie(&response, "session").unwrap();
+    assert!(!cookie.contains("HttpOnly"));
+}
+
+#[tokio::test]
+async fn cookie_browser_session() {
+    let response = null_app(SessionConfig::new())
+        .await
+        .oneshot(request("/", None))
+        .await
+        .unwrap();
+    let cookie = set_cookie(&response, "session").unwrap();
+    assert!(cookie.contains("Expires="));
+
+    // Without a max age the cookie is removed when the browser closes.
+    let response = null_app(SessionConfig::new().with_max_age(None))
+        .await
+        .oneshot(request("/", None))
+        .await
+        .unwrap();
+    let cookie = set_cookie(&response, "session").unwrap();
+    assert!(!cookie.contains("Expires="));
+    assert!(!cookie.contains("Max-Age="));
+}
+
+#[tokio::test]
+async fn cookie_path_and_domain() {
+    let config = SessionConfig::new()
+        .with_cookie_path("/app")
+        .with_cookie_domain("example.com");
+    let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+
+    let response = null_app(config).await.oneshot(request).await.unwrap();
+    let cookie = set_cookie(&response, "session").unwrap();
+    assert!(cookie.contains("Path=/app"));
+    assert!(cookie.contains("Domain=example.com"));
+}
+
+#[tokio::test]
+async fn cookie_prefix_round_trip() {
+    let config = SessionConfig::new()
+        .with_cookie_prefix(CookiePrefix::Host)
+        .with_cookie_path("/app")
+        .with_cookie_domain("example.com");
+    let session_store = SessionStore::<SessionNullPool>::new(None, config)
+        .await
+        .unwrap();
+    let app = session_routes(session_store);
+
+    let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+    let cookie = set_cookie(&response, "__Host-session").unwrap();
+    assert!(cookie.contains("Secure"));
+    assert!(cookie.contains("Path=/;") || cookie.ends_with("Path=/"));
+    assert!(!cookie.contains("Domain"));
+
+    let cookie = cookie_pair(&cookie);
+    let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+    assert_eq!(body_string(response).await, "42");
+}
+
+#[tokio::test]
+async fn malformed_session_cookie() {
+    let request = Request::builder()
+        .uri("/")
+        .header(header::COOKIE, "session=not-a-uuid")
+        .body(Body::empty())
+        .unwrap();
+
+    let response = null_app(SessionConfig::new())
+        .await
+        .oneshot(request)
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let cookie = set_cookie(&response, "session").unwrap();
+    let value = cookie_pair(&cookie)
+        .trim_start_matches("session=")
+        .to_owned();
+    assert!(uuid::Uuid::parse_str(&value).is_ok());
+}
+
+#[tokio::test]
+async fn partitioned_cookies() {
+    // Partitioned cookies are sent secure even when secure is disabled.
+    let config = SessionConfig::new()
+        .with_secure(false)
+        .with_partitioned(true);
+    let session_store = SessionStore::<SessionNullPool>::new(None, config)
+        .await
+        .unwrap();
+    let app = session_routes(session_store);
+
+    let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+    let set = set_cookie(&response, "session").unwrap();
+    let cookie = cookie::Cookie::parse(set.clone()).unwrap();
+    assert_eq!(cookie.partitioned(), Some(true));
+    assert_eq!(cookie.secure(), Some(true));
+
+    // The removal cookie is partitioned too so it removes the partitioned cookie.
+    let response = app
+        .oneshot(request("/destroy", Some(&cookie_pair(&set))))
+        .await
+        .unwrap();
+    let removal = cookie::Cookie::parse(set_cookie(&response, "session").unwrap()).unwrap();
+    assert_eq!(removal.partitioned(), Some(true));
+
+    let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+        .await
+        .unwrap();
+    let response = session_routes(session_store)
+        .oneshot(request("/set", None))
+        .await
+        .unwrap();
+    assert!(!set_cookie(&response, "session")
+        .unwrap()
+        .contains("Partitioned"));
+}
+
+#[tokio::test]
+async fn invalid_cookie_name() {
+    for config in [
+        SessionConfig::new().with_session_name(""),
+        SessionConfig::new().with_session_name("my session"),
+        SessionConfig::new().with_store_name("a;b"),
+        SessionConfig::new()
+            .with_session_name("same")
+            .with_store_name("same"),
+    ] {
+        assert!(matches!(
+            SessionStore::<SessionNullPool>::new(None, config).await,
+            Err(SessionError::InvalidCookieName(_))
+        ));
+    }
+}
+
+#[tokio::test]
+async fn remove_cookie_on_destroy() {
+    let session_store = SessionStore::<SessionNullPool>::new(
+        None,
+        SessionConfig::new().with_remove_cookie_on_destroy(false),
+    )
+    .await
+    .unwrap();
+    let app = session_routes(session_store);
+
+    let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+    let response = app
+        .clone()
+        .oneshot(request("/destroy", Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(set_cookie(&response, "session"), None);
+
+    // The old id is still sent but the destroyed session is gone.
+    let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+    assert_eq!(body_string(response).await, "0");
+}
+
+#[tokio::test]
+async fn custom_id_generator() {
+    let next = Arc::new(AtomicUsize::new(1));
+    let config = SessionConfig::new().with_id_generator(move || {
+        uuid::Uuid::from_u128(next.fetch_add(1, Ordering::SeqCst) as u128)
+    });
+    let app = null_app(config).await;
+
+    let response = app.clone().oneshot(request("/", None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+    assert_eq!(cookie, format!("session={}", uuid::Uuid::from_u128(1)));
+
+    let response = app.oneshot(request("/", None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+    assert_eq!(cookie, format!("session={}", uuid::Uuid::from_u128(2)));
+}
+
+#[tokio::test]
+async fn new_session() {
+    let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+        .await
+        .unwrap();
+    let app = Router::new()
+        .route(
+            "/",
+            get(|session: SessionNullSession| async move { session.is_new().to_string() }),
+        )
+        .layer(SessionLayer::new(session_store));
+
+    let response = app.clone().oneshot(request("/", None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+    assert_eq!(body_string(response).await, "true");
+
+    let response = app.oneshot(request("/", Some(&cookie))).await.unwrap();
+    assert_eq!(body_string(response).await, "false");
+}
+
+#[tokio::test]
+async fn destroy_session() {
+    let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+        .await
+        .unwrap();
+    let app = session_routes(session_store);
+
+    let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+    let response = app
+        .clone()
+        .oneshot(request("/destroy", Some(&cookie)))
+        .await
+        .unwrap();
+    let removal = set_cookie(&response, "session").unwrap();
+    assert!(removal.contains("Max-Age=0"));
+
+    let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+    assert_eq!(body_string(response).await, "0");
+}
@@ -0,0 +1,628 @@
+//! Integration tests for using the Session within handlers.
+//! The Session is sent within a header instead of a cookie with rest_mode so they are skipped for it.
+#![cfg(not(feature = "rest_mode"))]
+
+mod common;
+
+use axum::{http::header, response::Redirect, routing::get, Router};
+use axum_session::*;
+use common::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct Cart {
+    items: Vec<u64>,
+}
+
+impl TypedSessionData for Cart {
+    const KEY: &'static str = "cart";
+}
+
+#[tokio::test]
+async fn query_param() {
+    let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+        .await
+        .unwrap();
+    let app = session_routes(session_store);
+
+    let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+    let id = cookie_pair(&set_cookie(&response, "session").unwrap())
+        .trim_start_matches("session=")
+        .to_owned();
+
+    // The query parameter is ignored unless it is enabled.
+    let uri = format!("/get?sid={id}");
+    let response = app.oneshot(request(&uri, None)).await.unwrap();
+    assert_eq!(body_string(response).await, "0");
+
+    let session_store =
+        SessionStore::<SessionNullPool>::new(None, SessionConfig::new().with_query_param("sid"))
+            .await
+            .unwrap();
+    let app = session_routes(session_store);
+
+    let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+    let id = cookie_pair(&set_cookie(&response, "session").unwrap())
+        .trim_start_matches("session=")
+        .to_owned();
+
+    // Without a cookie the Session is found from the query and set in a cookie with a new id.
+    let uri = format!("/get?token=abc&sid={id}");
+    let response = app.clone().oneshot(request(&uri, None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+    assert_ne!(cookie, format!("session={id}"));
+    assert_eq!(body_string(response).await, "42");
+
+    // The old id no longer finds the Session.
+    let response = app.clone().oneshot(request(&uri, None)).await.unwrap();
+    assert_eq!(body_string(response).await, "0");
+
+    // A Session ID chosen by someone else is not kept.
+    let fixed = uuid::Uuid::new_v4().to_string();
+    let response = app
+        .clone()
+        .oneshot(request(&format!("/set?sid={fixed}"), None))
+        .await
+        .unwrap();
+    assert_ne!(
+        cookie_pair(&set_cookie(&response, "session").unwrap()),
+        format!("session={fixed}")
+    );
+    let response = app
+        .clone()
+        .oneshot(request("/get", Some(&format!("session={fixed}"))))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "0");
+
+    // The cookie wins over the query parameter.
+    let response = app
+        .oneshot(request("/get?sid=unknown", Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "42");
+}
+
+#[tokio::test]
+async fn ip_binding() {
+    let from =
+        |uri: &str, cookie: Option<&str>, ip: [u8; 4]| {
+            let mut request = request(uri, cookie);
+            request.extensions_mut().insert(axum::extract::ConnectInfo(
+                std::net::SocketAddr::from((ip, 8080)),
+            ));
+            request
+        };
+    let session_store =
+        SessionStore::<SessionNullPool>::new(None, SessionConfig::new().with_ip_binding(true))
+            .await
+            .unwrap();
+    let app = session_routes(session_store);
+
+    let response = app
+        .clone()
+        .oneshot(from("/set", None, [10, 0, 0, 1]))
+        .await
+        .unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+    let response = app
+        .clone()
+        .oneshot(from("/get", Some(&cookie), [10, 0, 0, 1]))
+        .await
+        .unwrap();
+    assert_eq!(
+        set_cookie(&response, "session").map(|c| cookie_pair(&c)),
+        Some(cookie.clone())
+    );
+    assert_eq!(body_string(response).await, "42");
+
+    // A different ip gets its own session.
+    let response = app
+        .clone()
+        .oneshot(from("/get", Some(&cookie), [10, 0, 0, 2]))
+        .await
+        .unwrap();
+    assert_ne!(
+        set_cookie(&response, "session").map(|c| cookie_pair(&c)),
+        Some(cookie.clone())
+    );
+    assert_eq!(body_string(response).await, "0");
+
+    // The bound session is left as is.
+    let response = app
+        .oneshot(from("/get", Some(&cookie), [10, 0, 0, 1]))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "42");
+}
+
+#[tokio::test]
+async fn user_agent_binding() {
+    let from = |uri: &str, cookie: Option<&str>, user_agent: &str| {
+        let mut request = request(uri, cookie);
+        request
+            .headers_mut()
+            .insert(header::USER_AGENT, user_agent.parse().unwrap());
+        request
+    };
+    let session_store = SessionStore::<SessionNullPool>::new(
+        None,
+        SessionConfig::new().with_user_agent_binding(true),
+    )
+    .await
+    .unwrap();
+    let app = session_routes(session_store);
+
+    let response = app
+        .clone()
+        .oneshot(from("/set", None, "browser/1.0"))
+        .await
+        .unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+    let response = app
+        .clone()
+        .oneshot(from("/get", Some(&cookie), "browser/1.0"))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "42");
+
+    let response = app
+        .oneshot(from("/get", Some(&cookie), "curl/8.0"))
+        .await
+        .unwrap();
+    assert_ne!(
+        set_cookie(&response, "session").map(|c| cookie_pair(&c)),
+        Some(cookie)
+    );
+    assert_eq!(body_string(response).await, "0");
+}
+
+#[tokio::test]
+async fn flash_messages() {
+    let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+        .await
+        .unwrap();
+    let app = Router::new()
+        .route(
+            "/save",
+            get(|session: Session<SessionNullPool>| async move {
+                session.set("notice", "not a flash");
+                session.flash("notice", "Profile saved");
+                Redirect::to("/profile")
+            }),
+        )
+        .route(
+            "/profile",
+            get(|session: Session<SessionNullPool>| async move {
+                format!(
+                    "{}|{}",
+                    session.take_flash::<String>("notice").unwrap_or_default(),
+                    session.get::<String>("notice").unwrap_or_default()
+                )
+            }),
+        )
+        .route(
+            "/keys",
+            get(|session: Session<SessionNullPool>| async move {
+                assert_eq!(session.keys(), vec!["notice".to_string()]);
+                assert_eq!(session.data_snapshot().len(), 1);
+            }),
+        )
+        .layer(SessionLayer::new(session_store));
+
+    let response = app.clone().oneshot(request("/save", None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+    let response = app
+        .clone()
+        .oneshot(request("/profile", Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "Profile saved|not a flash");
+
+    let response = app
+        .clone()
+        .oneshot(request("/profile", Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "|not a flash");
+
+    // A flash message the next request does not take is gone after it.
+    app.clone()
+        .oneshot(request("/save", Some(&cookie)))
+        .await
+        .unwrap();
+    app.clone()
+        .oneshot(request("/keys", Some(&cookie)))
+        .await
+        .unwrap();
+    let response = app
+        .oneshot(request("/profile", Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "|not a flash");
+}
+
+#[tokio::test]
+async fn flash_overlapping_requests() {
+    let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+        .await
+        .unwrap();
+    let flashed = Arc::new(tokio::sync::Notify::new());
+    let release = Arc::new(tokio::sync::Notify::new());
+    let (on_flash, on_release) = (flashed.clone(), release.clone());
+    let app = Router::new()
+        .route(
+            "/save",
+            get(move |session: Session<SessionNullPool>| async move {
+                session.flash("notice", "Profile saved");
+                on_flash.notify_one();
+                on_release.notified().await;
+            }),
+        )
+        .route(
+            "/other",
+            get(|session: Session<SessionNullPool>| async move {
+                session.get::<String>("notice").unwrap_or_default()
+            }),
+        )
+        .route(
+            "/profile",
+            get(|session: Session<SessionNullPool>| async move {
+                session.take_flash::<String>("notice").unwrap_or_default()
+            }),
+        )
+        .layer(SessionLayer::new(session_store));
+
+    let response = app.clone().oneshot(request("/other", None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+    // Another request running while the flash message is set does not remove it.
+    let save = tokio::spawn(app.clone().oneshot(request("/save", Some(&cookie))));
+    flashed.notified().await;
+    app.clone()
+        .oneshot(request("/other", Some(&cookie)))
+        .await
+        .unwrap();
+    release.notify_one();
+    save.await.unwrap().unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(request("/profile", Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "Profile saved");
+}
+
+#[tokio::test]
+async fn csrf_token() {
+    let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+        .await
+        .unwrap();
+    let app = Router::new()
+        .route(
+            "/token",
+            get(|session: Session<SessionNullPool>| async move { session.csrf_token() }),
+        )
+        .route(
+            "/keys",
+            get(|session: Session<SessionNullPool>| async move {
+                format!("{:?}{:?}", session.keys(), session.data_snapshot())
+            }),
+        )
+        .route(
+            "/verify/:token",
+            get(
+                |session: Session<SessionNullPool>,
+                 axum::extract::Path(token): axum::extract::Path<String>| async move {
+                    session.verify_csrf(&token).to_string()
+                },
+            ),
+        )
+        .layer(SessionLayer::new(session_store));
+
+    let response = app.clone().oneshot(request("/token", None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+    let token = body_string(response).await;
+
+    let response = app
+        .clone()
+        .oneshot(request("/token", Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, token);
+
+    // The token is kept apart from the keys set by the app.
+    let response = app
+        .clone()
+        .oneshot(request("/keys", Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "[]{}");
+
+    let response = app
+        .clone()
+        .oneshot(request(&format!("/verify/{}", token), Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "true");
+
+    let response = app
+        .oneshot(request("/verify/wrong", Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "false");
+}
+
+#[tokio::test]
+async fn csrf_header() {
+    let routes = |config: SessionConfig| async move {
+        let session_store = SessionStore::<SessionNullPool>::new(None, config)
+            .await
+            .unwrap();
+        session_handlers::<SessionNullPool>()
+            .route(
+                "/token",
+                get(|session: SessionNullSession| async move { session.csrf_token() }),
+            )
+            .layer(SessionLayer::new(session_store))
+    };
+
+    let app = routes(SessionConfig::new()).await;
+    let response = app.oneshot(request("/token", None)).await.unwrap();
+    assert!(response.headers().get("x-csrf-token").is_none());
+
+    let app = routes(SessionConfig::new().with_csrf_header(true)).await;
+    let response = app.clone().oneshot(request("/token", None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+    let header = response.headers()["x-csrf-token"]
+        .to_str()
+        .unwrap()
+        .to_owned();
+    assert_eq!(body_string(response).await, header);
+
+    // Handlers that never asked for the token still get the same one.
+    let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+    assert_eq!(response.headers()["x-csrf-token"], header.as_str());
+}
+
+#[tokio::test]
+async fn increment() {
+    let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+        .await
+        .unwrap();
+    let app = Router::new()
+        .route(
+            "/",
+            get(|session: Session<SessionNullPool>| async move {
+                session.increment("count", 5).to_string()
+            }),
+        )
+        .layer(SessionLayer::new(session_store));
+
+    let response = app.clone().oneshot(request("/", None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+    assert_eq!(body_string(response).await, "5");
+
+    let response = app.oneshot(request("/", Some(&cookie))).await.unwrap();
+    assert_eq!(body_string(response).await, "10");
+}
+
+#[tokio::test]
+async fn typed_session() {
+    let session_store = SessionStore::<SessionNullPool>::new(None, SessionConfig::new())
+        .await
+        .unwrap();
+    let app = Router::new()
+        .route(
+            "/add",
+            get(|cart: TypedSession<Cart, SessionNullPool>| async move {
+                let mut data = cart.get().unwrap_or_default();
+                data.items.push(data.items.len() as u64 + 1);
+                cart.set(&data);
+                serde_json::to_string(&data).unwrap()
+            }),
+        )
+        .route(
+            "/raw",
+            get(|session: Session<SessionNullPool>| async move {
+                session
+                    .get::<Cart>("cart")
+                    .unwrap_or_default()
+                    .items
+                    .len()
+                    .to_string()
+            }),
+        )
+        .layer(SessionLayer::new(session_store));
+
+    let response = app.clone().oneshot(request("/add", None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+    assert_eq!(body_string(response).await, r#"{"items":[1]}"#);
+
+    let response = app
+        .clone()
+        .oneshot(request("/add", Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, r#"{"items":[1,2]}"#);
+
+    // The typed data is stored under its key within the untyped session.
+    let response = app.oneshot(request("/raw", Some(&cookie))).await.unwrap();
+    assert_eq!(body_string(response).await, "2");
+}
+
+#[tokio::test]
+async fn new_session_limit() {
+    let session_store = SessionStore::<SessionNullPool>::new(
+        None,
+        SessionConfig::new()
+            .with_hashed_xforward(true)
+            .with_new_session_limit(Some(3), chrono::Duration::try_minutes(1).unwrap()),
+    )
+    .await
+    .unwrap();
+    let app = session_routes(session_store);
+    let from = |ip: &str, cookie: Option<&str>| {
+        let mut req = request("/set", cookie);
+        req.headers_mut()
+            .insert("x-forwarded-for", ip.parse().unwrap());
+        req
+    };
+
+    let mut cookies = Vec::new();
+
+    for _ in 0..3 {
+        let response = app.clone().oneshot(from("10.0.0.1", None)).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        cookies.push(cookie_pair(&set_cookie(&response, "session").unwrap()));
+    }
+
+    for _ in 0..5 {
+        let response = app.clone().oneshot(from("10.0.0.1", None)).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(set_cookie(&response, "session"), None);
+    }
+
+    // Existing Sessions and other ip's are not throttled.
+    let response = app
+        .clone()
+        .oneshot(from("10.0.0.1", Some(&cookies[0])))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    let response = app.clone().oneshot(from("10.0.0.2", None)).await.unwrap();
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    // Addresses a client adds in front of the proxy's do not get around the limit.
+    let response = app
+        .clone()
+        .oneshot(from("192.168.1.1, 10.0.0.1", None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+
+    // Requests without an ip can not be told apart so they are not limited.
+    for _ in 0..5 {
+        let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn clock_expiry() {
+    let (config, now) = fake_clock(SessionConfig::new());
+    let session_store = SessionStore::<SessionNullPool>::new(None, config)
+        .await
+        .unwrap();
+    let app = session_routes(session_store);
+
+    let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+    *now.lock().unwrap() += chrono::Duration::try_minutes(30).unwrap();
+    let response = app
+        .clone()
+        .oneshot(request("/get", Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "42");
+
+    // Past the 6 hour lifespan the Session expired without any real waiting.
+    *now.lock().unwrap() += chrono::Duration::try_hours(7).unwrap();
+    let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+    assert_eq!(body_string(response).await, "0");
+}
+
+#[tokio::test]
+async fn rotation_interval() {
+    let (config, now) =
+        fake_clock(SessionConfig::new().with_rotation_interval(chrono::Duration::try_minutes(15)));
+    let session_store = SessionStore::<SessionNullPool>::new(None, config)
+        .await
+        .unwrap();
+    let app = session_routes(session_store);
+
+    let response = app.clone().oneshot(request("/set", None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+
+    // Within the interval the Session ID stays the same.
+    *now.lock().unwrap() += chrono::Duration::try_minutes(5).unwrap();
+    let response = app
+        .clone()
+        .oneshot(request("/get", Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(
+        set_cookie(&response, "session").map(|c| cookie_pair(&c)),
+        Some(cookie.clone())
+    );
+
+    // Past the interval the data is kept under a new Session ID.
+    *now.lock().unwrap() += chrono::Duration::try_minutes(15).unwrap();
+    let response = app
+        .clone()
+        .oneshot(request("/get", Some(&cookie)))
+        .await
+        .unwrap();
+    let rotated = cookie_pair(&set_cookie(&response, "session").unwrap());
+    assert_ne!(rotated, cookie);
+    assert_eq!(body_string(response).await, "42");
+
+    let response = app
+        .clone()
+        .oneshot(request("/get", Some(&rotated)))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "42");
+
+    // The old Session ID no longer has the data.
+    let response = app.oneshot(request("/get", Some(&cookie))).await.unwrap();
+    assert_eq!(body_string(response).await, "0");
+}
+
+#[tokio::test]
+async fn authenticated_expires() {
+    let (config, now) = fake_clock(SessionConfig::new());
+    let session_store = SessionStore::<SessionNullPool>::new(None, config)
+        .await
+        .unwrap();
+    let app = Router::new()
+        .route(
+            "/login",
+            get(|session: Session<SessionNullPool>| async move {
+                session.set_owner(Some("user-1".to_owned()));
+                session.set_authenticated(true);
+            }),
+        )
+        .route(
+            "/status",
+            get(|session: Session<SessionNullPool>| async move {
+                format!("{} {:?}", session.is_authenticated(), session.get_owner())
+            }),
+        )
+        .layer(SessionLayer::new(session_store));
+
+    let response = app.clone().oneshot(request("/login", None)).await.unwrap();
+    let cookie = cookie_pair(&set_cookie(&response, "session").unwrap());
+    let response = app
+        .clone()
+        .oneshot(request("/status", Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "true Some(\"user-1\")");
+
+    // Past the 6 hour lifespan the Session is no longer logged in.
+    *now.lock().unwrap() += chrono::Duration::try_hours(7).unwrap();
+    let response = app
+        .oneshot(request("/status", Some(&cookie)))
+        .await
+        .unwrap();
+    assert_eq!(body_string(response).await, "false None");
+}